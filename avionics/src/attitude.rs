@@ -0,0 +1,245 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! In-house attitude control. One PID loop per axis turns the difference between the wanted and
+//! the measured attitude into raw pitch, yaw and roll inputs, in place of KSP's autopilot.
+
+use libkerbx::vehicle::{ControlInputs, EulerAngles};
+
+/// Proportional, integral and derivative gains of one loop. Errors are in degrees and outputs
+/// are control inputs from -1 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PidGains {
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+}
+
+/// Tuning of the attitude controller
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttitudeConfig {
+    pub pitch: PidGains,
+    pub yaw: PidGains,
+    pub roll: PidGains,
+    /// Most any input may change per second, so the controls are never slammed from one side
+    /// to the other
+    pub max_input_rate: f64,
+}
+
+impl Default for AttitudeConfig {
+    /// Gentle gains that settle a 10 degree error in a few seconds without overshoot on a small
+    /// rocket. Bigger craft will want their own.
+    fn default() -> AttitudeConfig {
+        let gains = PidGains {
+            kp: 0.05,
+            ki: 0.002,
+            kd: 0.01,
+        };
+        AttitudeConfig {
+            pitch: gains,
+            yaw: gains,
+            roll: gains,
+            max_input_rate: 2.0,
+        }
+    }
+}
+
+/// A single PID loop with its output limited to -1 to 1
+#[derive(Clone, Debug)]
+pub struct Pid {
+    gains: PidGains,
+    integral: f64,
+    last_error: Option<f64>,
+    output: f64,
+}
+
+impl Pid {
+    pub fn new(gains: PidGains) -> Pid {
+        Pid {
+            gains,
+            integral: 0.0,
+            last_error: None,
+            output: 0.0,
+        }
+    }
+
+    /// Forgets the accumulated error and the last output
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_error = None;
+        self.output = 0.0;
+    }
+
+    /// Returns the next output.
+    /// error: Wanted minus measured value
+    /// dt: Seconds since the last update
+    /// max_step: Most the output may move from the last one
+    pub fn update(&mut self, error: f64, dt: f64, max_step: f64) -> f64 {
+        if dt <= 0.0 {
+            return self.output;
+        }
+        let derivative = self.last_error.map_or(0.0, |last| (error - last) / dt);
+        self.last_error = Some(error);
+
+        let PidGains { kp, ki, kd } = self.gains;
+        let integral = self.integral + error * dt;
+        let wanted = kp * error + ki * integral + kd * derivative;
+
+        // Stop integrating while saturated so the loop does not wind up during long turns
+        if wanted.abs() <= 1.0 {
+            self.integral = integral;
+        }
+
+        self.output = wanted
+            .clamp(self.output - max_step, self.output + max_step)
+            .clamp(-1.0, 1.0);
+        self.output
+    }
+}
+
+/// Holds a pitch and heading, and whatever roll the craft had when the target was set
+#[derive(Clone, Debug)]
+pub struct AttitudeController {
+    config: AttitudeConfig,
+    pitch: Pid,
+    yaw: Pid,
+    roll: Pid,
+    target: Option<EulerAngles>,
+}
+
+impl AttitudeController {
+    pub fn new(config: AttitudeConfig) -> AttitudeController {
+        AttitudeController {
+            config,
+            pitch: Pid::new(config.pitch),
+            yaw: Pid::new(config.yaw),
+            roll: Pid::new(config.roll),
+            target: None,
+        }
+    }
+
+    /// Attitude being held, if any
+    pub fn target(&self) -> Option<EulerAngles> {
+        self.target
+    }
+
+    /// Starts holding a new attitude.
+    /// target: Wanted pitch and heading, with the roll to keep
+    pub fn set_target(&mut self, target: EulerAngles) {
+        self.target = Some(target);
+        self.pitch.reset();
+        self.yaw.reset();
+        self.roll.reset();
+    }
+
+    /// Stops holding an attitude. Inputs are centered from the next update.
+    pub fn clear_target(&mut self) {
+        self.target = None;
+    }
+
+    /// Returns the inputs that steer toward the target, or centered inputs without one.
+    /// attitude: Measured attitude
+    /// dt: Seconds since the last update
+    pub fn update(&mut self, attitude: &EulerAngles, dt: f64) -> ControlInputs {
+        let target = match self.target {
+            Some(target) => target,
+            None => return ControlInputs::default(),
+        };
+        let max_step = self.config.max_input_rate * dt;
+
+        // Heading and roll wrap around, so always turn the short way
+        let pitch_error = target.pitch - attitude.pitch;
+        let heading_error = wrap(target.heading - attitude.heading);
+        let roll_error = wrap(target.roll - attitude.roll);
+
+        ControlInputs {
+            pitch: self.pitch.update(pitch_error, dt, max_step) as f32,
+            yaw: self.yaw.update(heading_error, dt, max_step) as f32,
+            roll: self.roll.update(roll_error, dt, max_step) as f32,
+            ..Default::default()
+        }
+    }
+}
+
+/// Brings an angle in degrees into -180 to 180
+fn wrap(angle: f64) -> f64 {
+    (angle + 540.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pid_output_is_limited() {
+        let gains = PidGains {
+            kp: 1.0,
+            ki: 0.0,
+            kd: 0.0,
+        };
+        let mut pid = Pid::new(gains);
+        assert_eq!(pid.update(50.0, 0.1, 0.2), 0.2);
+        assert!((pid.update(50.0, 0.1, 0.2) - 0.4).abs() < 1e-12);
+        for _ in 0..10 {
+            pid.update(50.0, 0.1, 0.2);
+        }
+        assert_eq!(pid.update(50.0, 0.1, 0.2), 1.0);
+    }
+
+    #[test]
+    fn integral_does_not_wind_up_while_saturated() {
+        let gains = PidGains {
+            kp: 0.1,
+            ki: 0.1,
+            kd: 0.0,
+        };
+        let mut pid = Pid::new(gains);
+        for _ in 0..100 {
+            pid.update(100.0, 1.0, 1.0);
+        }
+        // A small error on the other side reverses the output straight away
+        assert!(pid.update(-2.0, 1.0, 2.0) < 0.0);
+    }
+
+    #[test]
+    fn turns_the_short_way_around() {
+        let mut controller = AttitudeController::new(AttitudeConfig::default());
+        controller.set_target(EulerAngles {
+            roll: 0.0,
+            pitch: 45.0,
+            heading: 350.0,
+        });
+        let attitude = EulerAngles {
+            roll: 0.0,
+            pitch: 50.0,
+            heading: 10.0,
+        };
+        let inputs = controller.update(&attitude, 0.1);
+        assert!(inputs.pitch < 0.0);
+        assert!(inputs.yaw < 0.0);
+        assert_eq!(inputs.roll, 0.0);
+
+        controller.clear_target();
+        assert_eq!(controller.update(&attitude, 0.1), ControlInputs::default());
+    }
+}
@@ -21,19 +21,47 @@
 * =================================================================================================
 */
 
-use contracts::*;
-use krpc_mars::protobuf::reflect::ProtobufValue;
+pub mod attitude;
+#[cfg(test)]
+mod regression;
+
+use attitude::{AttitudeConfig, AttitudeController};
 use krpc_mars::protobuf::CodedOutputStream;
-use krpc_mars::RPCClient;
-use libkerbx::kerbx::Sheath_oneof_message::flightplan;
+use libkerbx::hud::Hud;
 use libkerbx::kerbx::*;
-use libkerbx::KerbxTransport;
+use libkerbx::vehicle::{
+    self, CommsStatus, ControlInputs, EulerAngles, LinkStatus, ManeuverNode, SasMode, Servos,
+    SpeedMode, VehicleActuators, VehicleSensors, WarpMode, ACTION_GROUPS,
+};
+use libkerbx::KerbxError;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::net::TcpStream;
-use std::thread::current;
-use std::time::SystemTime;
+use std::time::{Duration, Instant};
+
+/// How often delta-v is recomputed for telemetry while the stage does not change
+const DELTA_V_REFRESH: Duration = Duration::from_secs(1);
+/// How often the radio link to mission control is checked
+const COMMS_REFRESH: Duration = Duration::from_secs(1);
+/// How often telemetry is recorded while out of contact
+const BUFFER_INTERVAL: Duration = Duration::from_secs(1);
+/// Most telemetry packets kept while out of contact; the oldest are dropped first
+const BUFFER_LIMIT: usize = 3600;
+/// Wall clock seconds of warp kept in hand ahead of a trigger. KSP takes a moment to slow down
+/// and the trigger is only checked once a tick.
+const WARP_LEAD: f64 = 3.0;
+/// Degrees the nose may be off the burn vector when the engines are lit for a maneuver node
+const NODE_ALIGNMENT: f64 = 5.0;
+/// Seconds of full throttle left at which a maneuver node burn starts throttling down, so it
+/// can be cut off close to the end
+const NODE_THROTTLE_DOWN: f64 = 2.0;
+/// Lowest throttle while throttling down, so the last of the burn still gets done
+const NODE_MIN_THROTTLE: f64 = 0.05;
+/// Delta-v in m/s left on a maneuver node at which its burn is over
+const NODE_CUTOFF: f64 = 0.1;
 
-// Derive allows for boolean comparison of enums used in the contracts
-#[derive(Eq, PartialEq)]
+// Derive allows for boolean comparison of enums used in the state transition checks
+#[derive(Debug, Eq, PartialEq)]
 pub enum AvionicsState {
     OFF,
     POST,
@@ -45,21 +73,74 @@ pub enum AvionicsState {
     ERROR,
 }
 
-pub struct Avionics {
+/// What the avionics does while the link to the vehicle is degraded
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinkLossPolicy {
+    /// Keep the last commanded attitude and throttle and wait for the link to come back
+    Hold,
+    /// Go to ERROR as soon as the link is lost
+    Abort,
+}
+
+/// What steers the vehicle to the attitude asked for by REORIENT steps
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttitudeControl {
+    /// KSP's built-in autopilot
+    AutoPilot,
+    /// The avionics' own PID loops, setting the control inputs directly
+    Pid(AttitudeConfig),
+}
+
+/// The avionics computer is generic over the vehicle it flies. `V` is usually a
+/// `libkerbx::KerbxTransport` connected to KSP, but any type providing the sensor and actuator
+/// traits will do.
+pub struct Avionics<V: VehicleSensors + VehicleActuators> {
     state: AvionicsState,
     current_step: u32,     // Current step of the flight plan
     error_message: String, // Last error message set
     flight_planner: TcpStream,
-    sensors: KerbxTransport,
+    sensors: V,
     flightplan: Option<FlightPlan>,
+    link: LinkStatus, // Link status as of the last check
+    link_loss: LinkLossPolicy,
+    twr_target: Cell<Option<f64>>, // Thrust-to-weight ratio the throttle is held at, if any
+    delta_v: Option<DeltaVReading>, // Last delta-v sent in telemetry
+    attitude: Option<RefCell<AttitudeController>>, // None when KSP's autopilot steers
+    attitude_updated: Cell<Option<Instant>>, // Time of the last attitude controller update
+    commanded: Cell<Option<(f32, f32)>>, // Pitch and heading of the last REORIENT, until released
+    comms: Option<CommsReading>,   // Last radio link status read from the vehicle
+    buffer: VecDeque<Telemetry>,   // Telemetry recorded while out of contact, oldest first
+    buffered_at: Option<Instant>,  // Time the last packet was buffered
+    hud: RefCell<Option<Hud>>,     // Shows the flight in game, if wanted
+    warp: Cell<Option<(WarpMode, u32)>>, // Mode and factor of the warp a WARP step started
+    node_burn: Cell<Option<NodeBurn>>, // Maneuver node an EXECUTENODE step is flying, if any
 }
 
-impl Avionics {
-    pub fn new(
-        ip: String,
-        port: String,
-        sensors: KerbxTransport,
-    ) -> Result<Avionics, std::io::Error> {
+/// Progress of the maneuver node burn an EXECUTENODE step started
+#[derive(Clone, Copy)]
+enum NodeBurn {
+    /// Turning onto the burn vector and waiting for the time to light the engines
+    Waiting,
+    /// Engines lit, with the delta-v in m/s left as of the last tick
+    Burning(f64),
+}
+
+/// Radio link as last read from the vehicle
+struct CommsReading {
+    read_at: Instant,
+    status: CommsStatus,
+}
+
+/// Delta-v as last read from the vehicle, kept so it is not recomputed every telemetry packet
+struct DeltaVReading {
+    stage: i32,
+    read_at: Instant,
+    stage_delta_v: f64,
+    delta_v: f64,
+}
+
+impl<V: VehicleSensors + VehicleActuators> Avionics<V> {
+    pub fn new(ip: String, port: String, sensors: V) -> Result<Avionics<V>, KerbxError> {
         let connection = TcpStream::connect(format!("{}:{}", ip, port))?;
         Ok(Avionics {
             state: AvionicsState::OFF,
@@ -68,169 +149,586 @@ impl Avionics {
             flight_planner: connection,
             sensors,
             flightplan: None,
+            link: LinkStatus::Nominal,
+            link_loss: LinkLossPolicy::Hold,
+            twr_target: Cell::new(None),
+            delta_v: None,
+            attitude: None,
+            attitude_updated: Cell::new(None),
+            commanded: Cell::new(None),
+            comms: None,
+            buffer: VecDeque::new(),
+            buffered_at: None,
+            hud: RefCell::new(None),
+            warp: Cell::new(None),
+            node_burn: Cell::new(None),
         })
     }
 
-    pub fn load_flightplan(&mut self) {
+    /// Sets what steers the vehicle on REORIENT steps. Defaults to AutoPilot.
+    pub fn set_attitude_control(&mut self, control: AttitudeControl) {
+        self.attitude = match control {
+            AttitudeControl::AutoPilot => None,
+            AttitudeControl::Pid(config) => Some(RefCell::new(AttitudeController::new(config))),
+        };
+        self.attitude_updated.set(None);
+    }
+
+    /// Shows state changes, executed steps and errors on screen in game from now on
+    pub fn set_hud(&mut self, hud: Hud) {
+        self.hud = RefCell::new(Some(hud));
+    }
+
+    /// Sets what to do while the link to the vehicle is degraded. Defaults to Hold.
+    pub fn set_link_loss_policy(&mut self, policy: LinkLossPolicy) {
+        self.link_loss = policy;
+    }
+
+    /// Checks the link to the vehicle, letting it reconnect if it dropped. A degraded link is
+    /// returned under the Hold policy; under Abort the avionics goes to ERROR and returns a
+    /// LinkDown error.
+    pub fn check_link(&mut self) -> Result<LinkStatus, KerbxError> {
+        self.link = self.sensors.check_link();
+        if self.link == LinkStatus::Degraded && self.link_loss == LinkLossPolicy::Abort {
+            self.to_error("Lost link to vehicle.");
+            return Err(KerbxError::LinkDown);
+        }
+        Ok(self.link)
+    }
+
+    /// Passes result through, except that under the Hold policy an error caused by losing the
+    /// link to the vehicle becomes None so the caller can wait for the link to come back.
+    pub fn tolerate_link_loss<T>(
+        &mut self,
+        result: Result<T, KerbxError>,
+    ) -> Result<Option<T>, KerbxError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if error.is_link_failure() => {
+                self.check_link()?;
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn load_flightplan(&mut self) -> Result<(), KerbxError> {
         // todo: Replace with networking code that receives flight plan from flight planner
         self.flightplan = Some(libkerbx::flightplan::load_from_string(
-            r#"{"step_count":1,"steps":[{"count":1,"field_type":"IGNITE","trigger":{"trigger_condition":{"time":{"seconds":0}}},"action":null}]}"#,
-        ))
+            r#"{"step_count":1,"steps":[{"count":1,"field_type":"IGNITE","trigger":{"trigger_condition":{"time":{"seconds":0,"nanos":0,"base":"WALLCLOCK"}}},"action":null}]}"#,
+        )?);
+        Ok(())
+    }
+
+    /// Loads a flight plan from a file written by the flightplan-creator.
+    /// filename: Full canonical path to the flightplan to load
+    pub fn load_flightplan_from_file(&mut self, filename: &str) -> Result<(), KerbxError> {
+        self.flightplan = Some(libkerbx::flightplan::load_from_file(filename)?);
+        Ok(())
+    }
+
+    /// The flight plan being flown, without the steps already executed
+    pub fn flightplan(&self) -> Option<&FlightPlan> {
+        self.flightplan.as_ref()
+    }
+
+    /// Pitch and heading in degrees the last REORIENT step asked for, while the avionics is
+    /// still steering for it
+    pub fn commanded_direction(&self) -> Option<(f32, f32)> {
+        self.commanded.get()
     }
 
-    /// Returns false if flightplan is invalide
-    //TODO: Add in a proper result type for the flight plan validator
-    #[requires(self.flightplan.is_some(), "Flightplan must exist to validate.")]
-    pub fn validate_flightplan(&self) -> bool {
+    /// Returns a Validation error describing the first problem found if the flight plan is not
+    /// safe to fly.
+    pub fn validate_flightplan(&self) -> Result<(), KerbxError> {
         // We shouldn't be calling this function if we have not already loaded the flight plan
-        let plan: &FlightPlan = self.flightplan.as_ref().unwrap();
+        let plan: &FlightPlan = self.flightplan.as_ref().ok_or_else(|| {
+            KerbxError::Contract(String::from("Flightplan must exist to validate."))
+        })?;
+        let invalid = |message: &str| Err(KerbxError::Validation(String::from(message)));
 
         // Flight plan must have at least one step
-        if plan.step_count == 0 {
-            eprintln!("Flight plan does not have at least one step.");
-            return false;
+        if plan.step_count == 0 || plan.steps.is_empty() {
+            return invalid("Flight plan does not have at least one step.");
         };
 
         // First plan step should be an engine ignite, i.e., launch
         if plan.steps[0].field_type != Step_ActionType::IGNITE {
-            eprintln!("Flight plan does not begin with an IGNITE action.");
-            return false;
+            return invalid("Flight plan does not begin with an IGNITE action.");
         };
 
         // First trigger should be a Time trigger with 0 as the trigger time.
-        if plan.steps[0].trigger.is_some() {
-            if plan.steps[0].trigger.as_ref().unwrap().has_time() {
-                if plan.steps[0].trigger.as_ref().unwrap().get_time().seconds != 0 {
-                    eprintln!("First step must trigger on Time 0.");
-                    return false;
+        match plan.steps[0].trigger.as_ref() {
+            Some(trigger) if trigger.has_time() => {
+                if libkerbx::clock::seconds(trigger.get_time()) != 0.0 {
+                    return invalid("First step must trigger on Time 0.");
                 }
-            } else {
-                eprintln!("First step must use a Time trigger.");
-                return false;
             }
-        } else {
-            eprintln!(
-                "All steps must have triggers. Use a time of 0 if you have no valid condition.."
-            );
-            return false;
+            Some(_) => return invalid("First step must use a Time trigger."),
+            None => {
+                return invalid(
+                    "All steps must have triggers. Use a time of 0 if you have no valid condition.",
+                )
+            }
         }
 
-        for step in self.flightplan.as_ref().unwrap().steps.as_ref() {
-            /*
-            TODO: Flight plan steps should be checked against craft composition to make sure that
-             each step can actually be executed by the constructed craft. For example, if an ignite
-             is called and the crafts current "stage" isn't an engine, this validation should fail
-             for safety reasons.
+        // Every IGNITE and NEXTSTAGE fires the next stage down, so each is checked against what
+        // that stage holds. Vehicles that cannot list their parts are not checked.
+        let composition = self.sensors.get_composition()?;
+        let mut next_stage = match composition {
+            Some(_) => self.sensors.get_stage()? - 1,
+            None => 0,
+        };
 
-             todo: Validate IGNITE action Type corresponds to a stage with an engine
-             */
+        for step in plan.steps.iter() {
             if step.trigger.is_none() {
-                eprintln!("Every step must have a trigger. Use a Time of 0 if you have no valid condition.");
-                return false;
+                return invalid(
+                    "Every step must have a trigger. Use a Time of 0 if you have no valid condition.",
+                );
+            }
+
+            if step.get_trigger().get_time().get_nanos() >= 1_000_000_000 {
+                return invalid("Time triggers must have fewer than a second of nanoseconds.");
+            }
+
+            if step.get_field_type() == Step_ActionType::ACTIONGROUP
+                && !(1..=ACTION_GROUPS).contains(&step.get_action_group().get_group())
+            {
+                return invalid("Action groups are numbered 1 to 10.");
+            }
+
+            if step.get_field_type() == Step_ActionType::SERVO {
+                let servo_move = step.get_servo();
+                if servo_move.get_servo().is_empty() == servo_move.get_group().is_empty() {
+                    return invalid("A SERVO step names either a servo or a servo group.");
+                }
+                let speed = servo_move.get_speed();
+                if speed.is_nan() || speed <= 0.0 || !servo_move.get_position().is_finite() {
+                    return invalid("A SERVO step needs a position and a speed above 0.");
+                }
+            }
+
+            if step.get_field_type() == Step_ActionType::WARP {
+                let warp = step.get_warp();
+                if warp.get_factor() as usize >= warp_mode(warp.get_mode()).rates().len() {
+                    return invalid("WARP factors: 0 ends warp; 1 to 7 on rails, 1 to 3 physics.");
+                }
+            }
+
+            let staging = matches!(
+                step.get_field_type(),
+                Step_ActionType::IGNITE | Step_ActionType::NEXTSTAGE
+            );
+            if let (true, Some(composition)) = (staging, &composition) {
+                // Stage -1 holds the parts staging never reaches
+                if next_stage < 0 {
+                    return invalid("Flight plan stages more times than the craft has stages.");
+                }
+                let fired = composition.stage(next_stage);
+                if step.get_field_type() == Step_ActionType::IGNITE
+                    && fired.map_or(true, |stage| stage.engines.is_empty())
+                {
+                    return invalid("IGNITE fires a stage without an engine.");
+                }
+                if fired.map_or(true, |stage| {
+                    stage.engines.is_empty()
+                        && stage.decouplers.is_empty()
+                        && stage.parachutes.is_empty()
+                }) {
+                    return invalid("NEXTSTAGE fires a stage with nothing to activate.");
+                }
+                next_stage -= 1;
             }
         }
 
-        true
+        Ok(())
     }
 
-    pub fn flightplan_pop_step(&mut self) -> Option<Step> {
-        if let Some(mut plan) = self.flightplan.as_mut() {
-            plan.steps.pop()
+    /// Removes and returns the next step of the flight plan, or None once every step has run.
+    pub fn flightplan_pop_step(&mut self) -> Result<Option<Step>, KerbxError> {
+        let plan = self.flightplan.as_mut().ok_or_else(|| {
+            KerbxError::Contract(String::from("Popping a step without a flight plan."))
+        })?;
+
+        // Steps execute in the order they were written, so take from the front
+        if plan.steps.is_empty() {
+            Ok(None)
         } else {
-            // todo: more robust/safe error handling
-            panic!("Popping an empty flight plan.");
-            None
+            Ok(Some(plan.steps.remove(0)))
         }
     }
 
     /// Executes a single step in the flight plan
-    pub fn flightplan_exe_single_action(&self, step: &Step) {
+    pub fn flightplan_exe_single_action(&self, step: &Step) -> Result<(), KerbxError> {
         match &step.get_field_type() {
             Step_ActionType::REORIENT => {
                 let pitch = step.get_position().get_pitch();
                 let heading = step.get_position().get_yaw();
 
-                self.sensors.set_auto_pilot(true);
-                self.sensors.set_auto_pilot_direction(pitch, heading);
+                // SAS would fight whatever steers, so drop any HOLD from an earlier step
+                self.sensors.set_sas(false)?;
+                self.commanded.set(Some((pitch, heading)));
+                match &self.attitude {
+                    Some(controller) => {
+                        self.sensors.set_auto_pilot(false)?;
+                        let roll = self.sensors.get_roll()?;
+                        controller.borrow_mut().set_target(EulerAngles {
+                            roll,
+                            pitch: pitch.into(),
+                            heading: heading.into(),
+                        });
+                        self.attitude_updated.set(None);
+                        self.track_attitude()?;
+                    }
+                    None => {
+                        self.sensors.set_auto_pilot(true)?;
+                        self.sensors.set_auto_pilot_direction(pitch, heading)?;
+                    }
+                }
             }
             Step_ActionType::IGNITE => {
-                // TODO: See validation of making sure the IGNITE type corresponds to an engine
-                self.sensors.trigger_stage();
+                // Validation checked the stage this fires has an engine
+                self.sensors.trigger_stage()?;
             }
             Step_ActionType::THROTTLELEVEL => {
-                // Double call to get_throttle is a byproduct of a throttle action type also containing
-                // a field of the same name. Mea culpa.
-                self.sensors
-                    .set_throttle(step.get_throttle().get_throttle());
+                let twr = step.get_throttle().get_twr();
+                if twr > 0.0 {
+                    self.twr_target.set(Some(twr.into()));
+                    self.track_twr()?;
+                } else {
+                    // Double call to get_throttle is a byproduct of a throttle action type also
+                    // containing a field of the same name. Mea culpa.
+                    self.twr_target.set(None);
+                    self.sensors
+                        .set_throttle(step.get_throttle().get_throttle())?;
+                }
             }
             Step_ActionType::COAST => {
-                self.sensors.set_auto_pilot(false);
+                self.sensors.set_auto_pilot(false)?;
+                self.release_attitude()?;
                 //todo
             }
             Step_ActionType::NEXTSTAGE => {
-                self.sensors.trigger_stage();
+                self.sensors.trigger_stage()?;
                 //todo
             }
+            Step_ActionType::ACTIONGROUP => {
+                let action_group = step.get_action_group();
+                self.sensors
+                    .set_action_group(action_group.get_group(), action_group.get_on())?;
+            }
+            Step_ActionType::GEAR => self.sensors.set_gear(step.get_toggle().get_on())?,
+            Step_ActionType::BRAKES => self.sensors.set_brakes(step.get_toggle().get_on())?,
+            Step_ActionType::LIGHTS => self.sensors.set_lights(step.get_toggle().get_on())?,
+            Step_ActionType::ABORT => self.sensors.abort()?,
+            Step_ActionType::ARMPARACHUTES => {
+                let parachutes = step.get_parachutes();
+                self.sensors.arm_parachutes(
+                    parachutes.get_deploy_altitude(),
+                    parachutes.get_min_pressure(),
+                )?;
+            }
+            Step_ActionType::DEPLOYPARACHUTES => self.sensors.deploy_parachutes()?,
+            Step_ActionType::SOLARPANELS => {
+                self.sensors.set_solar_panels(step.get_toggle().get_on())?
+            }
+            Step_ActionType::ANTENNAS => self.sensors.set_antennas(step.get_toggle().get_on())?,
+            Step_ActionType::HOLD => {
+                let hold = step.get_hold();
+                self.sensors.set_auto_pilot(false)?;
+                self.release_attitude()?;
+
+                // KSP only takes a SAS mode once SAS is on
+                self.sensors.set_sas(true)?;
+                self.sensors.set_speed_mode(match hold.get_speed_mode() {
+                    Hold_SpeedMode::SURFACE => SpeedMode::Surface,
+                    Hold_SpeedMode::ORBIT => SpeedMode::Orbit,
+                })?;
+                self.sensors.set_sas_mode(match hold.get_direction() {
+                    Hold_Direction::PROGRADE => SasMode::Prograde,
+                    Hold_Direction::RETROGRADE => SasMode::Retrograde,
+                })?;
+            }
+            Step_ActionType::SERVO => {
+                let servo_move = step.get_servo();
+                let servos = if servo_move.get_group().is_empty() {
+                    Servos::Servo(String::from(servo_move.get_servo()))
+                } else {
+                    Servos::Group(String::from(servo_move.get_group()))
+                };
+                self.sensors.move_servos(
+                    &servos,
+                    servo_move.get_position(),
+                    servo_move.get_speed(),
+                )?;
+            }
+            Step_ActionType::WARP => {
+                let warp = step.get_warp();
+                let mode = warp_mode(warp.get_mode());
+                let factor = warp.get_factor();
+                self.sensors.set_warp(mode, factor)?;
+                self.warp.set(Some((mode, factor)).filter(|_| factor > 0));
+            }
+            Step_ActionType::EXECUTENODE => {
+                if self.sensors.get_maneuver_node()?.is_none() {
+                    return Err(KerbxError::Contract(String::from(
+                        "EXECUTENODE without a maneuver node to burn.",
+                    )));
+                }
+                self.sensors.set_auto_pilot(false)?;
+                self.release_attitude()?;
+
+                // The burn sets the throttle from here on
+                self.twr_target.set(None);
+                self.sensors.set_throttle(0.0)?;
+
+                // KSP only takes a SAS mode once SAS is on
+                self.sensors.set_sas(true)?;
+                self.sensors.set_sas_mode(SasMode::Maneuver)?;
+                self.node_burn.set(Some(NodeBurn::Waiting));
+                self.track_node()?;
+            }
+        }
+
+        let next = self.flightplan.as_ref().and_then(|plan| plan.steps.first());
+        self.show(|hud| {
+            hud.step_executed(step)?;
+            hud.update_panel(Some(step), next)
+        });
+        Ok(())
+    }
+
+    /// Slows down a warp started by a WARP step as the next trigger or a maneuver node burn
+    /// comes up, so the warp never skips past either. Triggers whose time cannot be predicted
+    /// end rails warp straight away, while physics warp still checks them every tick. Called
+    /// every tick while waiting on next.
+    pub fn track_warp(&self, next: &Trigger) -> Result<(), KerbxError> {
+        let (mode, factor) = match self.warp.get() {
+            Some(warp) => warp,
+            None => return Ok(()),
+        };
+        // Factor of the fastest rate that leaves WARP_LEAD seconds in hand before due
+        let fastest = |due: f64| {
+            mode.rates()
+                .iter()
+                .rposition(|rate| rate * WARP_LEAD <= due)
+                .unwrap_or(0) as u32
+        };
+        let burn = self.seconds_to_node_burn()?;
+        let allowed = match self.seconds_to_trigger(next)? {
+            Some(due) => fastest(due.min(burn)),
+            None if mode == WarpMode::Rails => 0,
+            None => factor.min(fastest(burn)),
+        };
+        if allowed < factor {
+            self.sensors.set_warp(mode, allowed)?;
+            self.warp.set(Some((mode, allowed)).filter(|_| allowed > 0));
+        }
+        Ok(())
+    }
+
+    /// Game seconds until trigger fires, for the triggers whose time can be predicted
+    fn seconds_to_trigger(&self, trigger: &Trigger) -> Result<Option<f64>, KerbxError> {
+        match &trigger.trigger_condition {
+            Some(Trigger_oneof_trigger_condition::time(time)) => {
+                let clock = self.sensors.get_clock()?;
+                Ok(Some(clock.to_ut(time) - clock.ut))
+            }
+            Some(Trigger_oneof_trigger_condition::time_to_apoapsis(seconds)) => {
+                Ok(Some(self.sensors.get_orbit()?.time_to_apoapsis - seconds))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Game seconds until the engines are due to be lit for the maneuver node an EXECUTENODE
+    /// step is flying, 0 once they are and infinite without one
+    fn seconds_to_node_burn(&self) -> Result<f64, KerbxError> {
+        match self.node_burn.get() {
+            Some(NodeBurn::Waiting) => match self.sensors.get_maneuver_node()? {
+                Some(node) => {
+                    let propulsion = self.sensors.get_propulsion()?;
+                    self.seconds_to_burn(&node, propulsion.burn_time(node.remaining_delta_v()))
+                }
+                None => Ok(f64::INFINITY),
+            },
+            Some(NodeBurn::Burning(_)) => Ok(0.0),
+            None => Ok(f64::INFINITY),
         }
     }
 
+    /// Game seconds until the engines are lit for node, half of burn_time ahead of it so the burn
+    /// is centered on the node. Without thrust to work the burn time out, they are lit at the
+    /// node.
+    fn seconds_to_burn(&self, node: &ManeuverNode, burn_time: f64) -> Result<f64, KerbxError> {
+        let lead = if burn_time.is_finite() {
+            burn_time / 2.0
+        } else {
+            0.0
+        };
+        Ok(node.ut - lead - self.sensors.get_clock()?.ut)
+    }
+
+    /// Flies the maneuver node an EXECUTENODE step started, while SAS holds the nose on the burn
+    /// vector. The engines are lit once the burn is due and the nose is within NODE_ALIGNMENT of
+    /// the vector, and throttled down over the last NODE_THROTTLE_DOWN seconds of the burn. They
+    /// are cut off and the node removed once the delta-v left is gone, or starts growing again
+    /// as the burn overshoots. Called every tick.
+    pub fn track_node(&self) -> Result<(), KerbxError> {
+        let burn = match self.node_burn.get() {
+            Some(burn) => burn,
+            None => return Ok(()),
+        };
+        let node = match self.sensors.get_maneuver_node()? {
+            Some(node) => node,
+            // Deleted in game, so there is nothing left to burn
+            None => {
+                self.node_burn.set(None);
+                return self.sensors.set_throttle(0.0);
+            }
+        };
+
+        let remaining = node.remaining_delta_v();
+        let finished = match burn {
+            NodeBurn::Waiting => remaining <= NODE_CUTOFF,
+            NodeBurn::Burning(last) => remaining <= NODE_CUTOFF || remaining > last,
+        };
+        if finished {
+            self.sensors.set_throttle(0.0)?;
+            self.sensors.remove_maneuver_node()?;
+            self.node_burn.set(None);
+            return Ok(());
+        }
+
+        let burn_time = self.sensors.get_propulsion()?.burn_time(remaining);
+        if let NodeBurn::Waiting = burn {
+            let nose = (self.sensors.get_pitch()?, self.sensors.get_heading()?);
+            if self.seconds_to_burn(&node, burn_time)? > 0.0
+                || angle_between(nose, node.direction()) > NODE_ALIGNMENT
+            {
+                return Ok(());
+            }
+        }
+
+        let throttle = (burn_time / NODE_THROTTLE_DOWN).clamp(NODE_MIN_THROTTLE, 1.0);
+        self.sensors.set_throttle(throttle as f32)?;
+        self.node_burn.set(Some(NodeBurn::Burning(remaining)));
+        Ok(())
+    }
+
+    /// Adjusts the throttle to hold the thrust-to-weight ratio set by the last THROTTLELEVEL step,
+    /// if it asked for one. Called every tick as the vehicle burns off mass.
+    pub fn track_twr(&self) -> Result<(), KerbxError> {
+        if let Some(twr) = self.twr_target.get() {
+            let propulsion = self.sensors.get_propulsion()?;
+            self.sensors
+                .set_throttle(propulsion.throttle_for_twr(twr))?;
+        }
+        Ok(())
+    }
+
+    /// Stops the PID attitude controller holding an attitude and centers the inputs it set
+    fn release_attitude(&self) -> Result<(), KerbxError> {
+        self.commanded.set(None);
+        if let Some(controller) = &self.attitude {
+            controller.borrow_mut().clear_target();
+            self.sensors.set_control_inputs(ControlInputs::default())?;
+        }
+        Ok(())
+    }
+
+    /// Updates the control inputs from the PID attitude controller, if it is in use and holding
+    /// an attitude. Called every tick.
+    pub fn track_attitude(&self) -> Result<(), KerbxError> {
+        let controller = match &self.attitude {
+            Some(controller) if controller.borrow().target().is_some() => controller,
+            _ => return Ok(()),
+        };
+        let attitude = EulerAngles {
+            roll: self.sensors.get_roll()?,
+            pitch: self.sensors.get_pitch()?,
+            heading: self.sensors.get_heading()?,
+        };
+
+        let now = Instant::now();
+        let dt = self
+            .attitude_updated
+            .replace(Some(now))
+            .map_or(0.0, |last| (now - last).as_secs_f64());
+        let inputs = controller.borrow_mut().update(&attitude, dt);
+        self.sensors.set_control_inputs(inputs)
+    }
+
+    /// Runs every tracker that keeps a step going between ticks: the TWR hold, the attitude
+    /// controller, a maneuver node burn and, while waiting on next, a warp. Trackers that fail
+    /// because the link dropped are skipped for this tick under the Hold policy.
+    pub fn track(&mut self, next: Option<&Trigger>) -> Result<(), KerbxError> {
+        let tracked = self.track_twr();
+        self.tolerate_link_loss(tracked)?;
+        let steered = self.track_attitude();
+        self.tolerate_link_loss(steered)?;
+        let burned = self.track_node();
+        self.tolerate_link_loss(burned)?;
+        if let Some(trigger) = next {
+            let warped = self.track_warp(trigger);
+            self.tolerate_link_loss(warped)?;
+        }
+        Ok(())
+    }
+
     /// Checks the trigger of a flight plan action returning true if the trigger is met.
-    pub fn flightplan_check_trigger(&self, trigger: &Trigger) -> bool {
+    pub fn flightplan_check_trigger(&self, trigger: &Trigger) -> Result<bool, KerbxError> {
         if let Some(type_of_trigger) = &trigger.trigger_condition {
             match type_of_trigger {
-                Trigger_oneof_trigger_condition::position(x) => {
+                Trigger_oneof_trigger_condition::position(_) => {
                     //todo: Implement such that there is some room for error in the lat/lon checking in a way that makes sense
-                    let current_lat = self.sensors.get_lat().expect("Error getting latitude.");
+                    let current_lat = self.sensors.get_lat()?;
                     let min_lat = trigger.get_position().get_lat() - 10.0;
                     let max_lat = trigger.get_position().get_lat() + 10.0;
 
-                    let current_lon = self.sensors.get_lon().expect("Error getting longitude.");
+                    let current_lon = self.sensors.get_lon()?;
                     let min_lon = trigger.get_position().get_lon() - 10.0;
                     let max_lon = trigger.get_position().get_lon() + 10.0;
 
                     // If current_lat is within the latitude range, then we check the current lon.
                     // We'll return only true if both are within range.
-                    return if min_lat <= current_lat && current_lat <= max_lat {
-                        if min_lon <= current_lon && current_lon <= max_lon {
-                            true
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    };
+                    Ok(min_lat <= current_lat
+                        && current_lat <= max_lat
+                        && min_lon <= current_lon
+                        && current_lon <= max_lon)
                 }
-                Trigger_oneof_trigger_condition::time(x) => {
-                    let current_time = libkerbx::time().unwrap().seconds;
-
-                    return if trigger.get_time().seconds < current_time {
-                        true
-                    } else {
-                        false
-                    };
+                Trigger_oneof_trigger_condition::time(time) => {
+                    // Judged in game time, so warp and pauses carry time triggers along with
+                    // the game rather than the wall clock
+                    Ok(self.sensors.get_clock()?.reached(time))
                 }
-                Trigger_oneof_trigger_condition::alt(x) => {
-                    //todo: Error handling
-                    let current_alt = self.sensors.get_alt().expect("Error getting altitude.");
+                Trigger_oneof_trigger_condition::alt(_) => {
+                    let current_alt = self.sensors.get_alt()?;
 
                     //todo: fix altitude checking such that the error is a function of the velocity/acceleration of the craft otherwise it's possible for the craft to be going so fast it misses its window
                     let minimum_alt = trigger.get_alt() - 10.0;
                     let maximum_alt = trigger.get_alt() + 10.0;
 
-                    return if minimum_alt <= current_alt && current_alt <= maximum_alt {
-                        true
-                    } else {
-                        false
-                    };
+                    Ok(minimum_alt <= current_alt && current_alt <= maximum_alt)
+                }
+                Trigger_oneof_trigger_condition::apoapsis(altitude) => {
+                    let orbit = self.sensors.get_orbit()?;
+                    Ok(orbit.apoapsis_altitude >= *altitude)
+                }
+                Trigger_oneof_trigger_condition::periapsis(altitude) => {
+                    let orbit = self.sensors.get_orbit()?;
+                    Ok(orbit.periapsis_altitude >= *altitude)
+                }
+                Trigger_oneof_trigger_condition::time_to_apoapsis(seconds) => {
+                    let orbit = self.sensors.get_orbit()?;
+                    Ok(orbit.time_to_apoapsis <= *seconds)
                 }
             }
         } else {
-            //todo: In the real world we shouldn't panic here and instead fail gracefully as I don't
-            // believe this state is reachable.
-            panic!("Trigger without condition type! This should be impossible.")
+            Err(KerbxError::Validation(String::from(
+                "Trigger without condition type.",
+            )))
         }
-
-        false
     }
 
     pub fn set_error(&mut self, message: &str) {
@@ -242,107 +740,1137 @@ impl Avionics {
         self.current_step += 1;
     }
 
-    #[requires(self.state == AvionicsState::OFF, "POST state only valid from OFF")]
-    pub fn to_post(&mut self) {
-        self.state = AvionicsState::POST;
+    /// Returns a Contract error with the given message unless the avionics are in the expected state
+    fn require_state(&self, expected: AvionicsState, message: &str) -> Result<(), KerbxError> {
+        if self.state == expected {
+            Ok(())
+        } else {
+            Err(KerbxError::Contract(String::from(message)))
+        }
     }
 
-    #[requires(self.state == AvionicsState::POST, "IDLE state only valid from POST")]
-    pub fn to_idle(&mut self) {
-        self.state = AvionicsState::IDLE;
+    pub fn to_post(&mut self) -> Result<(), KerbxError> {
+        self.require_state(AvionicsState::OFF, "POST state only valid from OFF")?;
+        self.enter(AvionicsState::POST);
+        Ok(())
     }
 
-    #[requires(self.state == AvionicsState::IDLE, "READY state only valid from IDLE")]
-    #[requires(self.flightplan.is_some(), "Vessel must have a valid flight plan")]
-    pub fn to_ready(&mut self) {
-        self.state = AvionicsState::READY;
+    pub fn to_idle(&mut self) -> Result<(), KerbxError> {
+        self.require_state(AvionicsState::POST, "IDLE state only valid from POST")?;
+        self.enter(AvionicsState::IDLE);
+        Ok(())
     }
 
-    #[requires(self.state == AvionicsState::READY, "COUNTDOWN state only valid from READY")]
-    pub fn to_countdown(&mut self) {
-        self.state = AvionicsState::COUNTDOWN;
+    pub fn to_ready(&mut self) -> Result<(), KerbxError> {
+        self.require_state(AvionicsState::IDLE, "READY state only valid from IDLE")?;
+        if self.flightplan.is_none() {
+            return Err(KerbxError::Contract(String::from(
+                "Vessel must have a valid flight plan",
+            )));
+        }
+        self.enter(AvionicsState::READY);
+        Ok(())
     }
 
-    #[requires(self.state == AvionicsState::COUNTDOWN, "InFlight state only valid from COUNTDOWN")]
-    pub fn to_inflight(&mut self) {
-        self.state = AvionicsState::INFLIGHT;
+    pub fn to_countdown(&mut self) -> Result<(), KerbxError> {
+        self.require_state(
+            AvionicsState::READY,
+            "COUNTDOWN state only valid from READY",
+        )?;
+        self.enter(AvionicsState::COUNTDOWN);
+        Ok(())
     }
 
-    #[requires(self.state == AvionicsState::INFLIGHT, "Landed state only valid from INFLIGHT")]
-    pub fn to_landed(&mut self) {
-        self.state = AvionicsState::LANDED;
+    pub fn to_inflight(&mut self) -> Result<(), KerbxError> {
+        self.require_state(
+            AvionicsState::COUNTDOWN,
+            "InFlight state only valid from COUNTDOWN",
+        )?;
+        self.enter(AvionicsState::INFLIGHT);
+        Ok(())
+    }
+
+    pub fn to_landed(&mut self) -> Result<(), KerbxError> {
+        self.require_state(
+            AvionicsState::INFLIGHT,
+            "Landed state only valid from INFLIGHT",
+        )?;
+        self.enter(AvionicsState::LANDED);
+        Ok(())
     }
 
     // Error state is valid from all other states
     pub fn to_error(&mut self, message: &str) {
         self.state = AvionicsState::ERROR;
         self.error_message = String::from(message);
+        self.show(|hud| hud.error(message));
+    }
+
+    /// Announces the seconds left before launch on the HUD
+    pub fn show_countdown(&self, seconds: u32) {
+        self.show(|hud| hud.countdown(seconds));
+    }
+
+    fn enter(&mut self, state: AvionicsState) {
+        self.state = state;
+        let name = format!("{:?}", self.state);
+        self.show(|hud| hud.state(&name));
+        if self.state == AvionicsState::READY {
+            // Nothing has run yet, so the panel starts on the first step's trigger
+            let first = self.flightplan.as_ref().and_then(|plan| plan.steps.first());
+            self.show(|hud| hud.update_panel(None, first));
+        }
+    }
+
+    /// Posts to the HUD, if there is one. A HUD that fails to take a post is not posted to again.
+    fn show<F: FnOnce(&mut Hud) -> Result<(), KerbxError>>(&self, post: F) {
+        let mut hud = self.hud.borrow_mut();
+        if let Some(display) = hud.as_mut() {
+            if let Err(error) = post(display) {
+                eprintln!("Stopped showing the HUD: {}", error);
+                *hud = None;
+            }
+        }
     }
 
     pub fn get_state(&self) -> &AvionicsState {
         &self.state
     }
 
-    pub fn send_alive(&mut self) {
+    /// Whether mission control can hear the vehicle, as of the last telemetry packet. Always
+    /// true without RemoteTech.
+    pub fn in_contact(&self) -> bool {
+        self.comms
+            .as_ref()
+            .map_or(true, |reading| reading.status.in_contact())
+    }
+
+    pub fn send_alive(&mut self) -> Result<(), KerbxError> {
         let mut message = WatchDog::new();
-        message.set_status(WatchDog_Status::ACKALIVE);
-        message.set_time(libkerbx::time().unwrap());
+        message.set_status(match self.link {
+            LinkStatus::Degraded => WatchDog_Status::LINKDEGRADED,
+            LinkStatus::Nominal if !self.in_contact() => WatchDog_Status::NOCONTACT,
+            LinkStatus::Nominal => WatchDog_Status::ACKALIVE,
+        });
+        message.set_time(libkerbx::time()?);
 
         let mut wrapper = Sheath::new();
         wrapper.set_field_type(Sheath_MessageType::WATCHDOG);
         wrapper.set_watchdog(message);
+        self.send(&wrapper)
+    }
 
+    fn send(&mut self, wrapper: &Sheath) -> Result<(), KerbxError> {
         let mut output = CodedOutputStream::new(&mut self.flight_planner);
-        if let Ok(()) = output.write_message_no_tag(&wrapper) {
-            output.flush();
-        } else {
-            eprintln!("Error writing message to flight planner.");
-        }
+        output.write_message_no_tag(wrapper)?;
+        output.flush()?;
+        Ok(())
     }
 
     // Set throttle to 100 percent and disable flight stabalizers in prep for autopilot
-    // TODO: Add error handling
-    #[requires(self.state == AvionicsState::READY, "Cannot prep for launch unless flight plan is valid")]
-    pub fn ready_for_launch(&mut self) {
-        self.sensors.set_sas(false);
-        self.sensors.set_rcs(false);
-        self.sensors.set_throttle(1.0);
-    }
-
-    pub fn send_telemetry(&mut self) {
-        // In a real world situation these errors should be passed up the stack and handled in
-        // the main avionics loop. I'm running out of time as I write this, so this will just fail
-        // early and hopefully not often.
+    pub fn ready_for_launch(&mut self) -> Result<(), KerbxError> {
+        self.require_state(
+            AvionicsState::READY,
+            "Cannot prep for launch unless flight plan is valid",
+        )?;
+        self.sensors.set_sas(false)?;
+        self.sensors.set_rcs(false)?;
+        self.sensors.set_throttle(1.0)?;
+        Ok(())
+    }
+
+    pub fn send_telemetry(&mut self) -> Result<(), KerbxError> {
+        // All values are sampled together so the packet describes a single moment of flight
+        let state = self.sensors.snapshot()?;
+
         let mut message = Telemetry::new();
-        message.set_lat(self.sensors.get_lat().expect("Error getting Latitude."));
-        message.set_lon(self.sensors.get_lon().expect("Error getting Longitude."));
-        message.set_alt(self.sensors.get_alt().expect("Error getting altitude."));
-        message.set_yaw(self.sensors.get_heading().expect("Error getting heading."));
-        message.set_pitch(self.sensors.get_pitch().expect("Error getting pitch."));
-        message.set_roll(self.sensors.get_roll().expect("Error getting roll."));
+        message.set_lat(state.lat);
+        message.set_lon(state.lon);
+        message.set_alt(state.alt);
+        message.set_yaw(state.heading);
+        message.set_pitch(state.pitch);
+        message.set_roll(state.roll);
+        message.set_velocity(state.velocity);
         message.set_flight_plan_step(self.current_step);
-        message.set_velocity(
-            self.sensors
-                .get_velocity()
-                .expect("Error getting velocity."),
+
+        let mut orbit = Orbit::new();
+        orbit.set_apoapsis_altitude(state.orbit.apoapsis_altitude);
+        orbit.set_periapsis_altitude(state.orbit.periapsis_altitude);
+        orbit.set_time_to_apoapsis(state.orbit.time_to_apoapsis);
+        orbit.set_time_to_periapsis(state.orbit.time_to_periapsis);
+        orbit.set_eccentricity(state.orbit.eccentricity);
+        orbit.set_inclination(state.orbit.inclination);
+        orbit.set_semi_major_axis(state.orbit.semi_major_axis);
+        orbit.set_period(state.orbit.period);
+        orbit.set_longitude_of_ascending_node(state.orbit.longitude_of_ascending_node);
+        orbit.set_argument_of_periapsis(state.orbit.argument_of_periapsis);
+        message.set_orbit(orbit);
+        message.set_vessel_resources(resource_levels(&state.resources.vessel));
+        message.set_stage_resources(resource_levels(&state.resources.stage));
+
+        let stale = match &self.delta_v {
+            Some(reading) => {
+                reading.stage != state.stage || reading.read_at.elapsed() >= DELTA_V_REFRESH
+            }
+            None => true,
+        };
+        if stale {
+            let stages = self.sensors.get_delta_v()?;
+            self.delta_v = Some(DeltaVReading {
+                stage: state.stage,
+                read_at: Instant::now(),
+                stage_delta_v: stages.first().map_or(0.0, |stage| stage.delta_v),
+                delta_v: stages.iter().map(|stage| stage.delta_v).sum(),
+            });
+        }
+
+        let mut propulsion = Propulsion::new();
+        propulsion.set_thrust(state.propulsion.thrust);
+        propulsion.set_available_thrust(state.propulsion.available_thrust);
+        propulsion.set_max_thrust(state.propulsion.max_thrust);
+        propulsion.set_mass(state.propulsion.mass);
+        propulsion.set_dry_mass(state.propulsion.dry_mass);
+        propulsion.set_twr(state.propulsion.twr());
+        propulsion.set_available_twr(state.propulsion.available_twr());
+        if let Some(reading) = &self.delta_v {
+            propulsion.set_stage_delta_v(reading.stage_delta_v);
+            propulsion.set_delta_v(reading.delta_v);
+        }
+        message.set_propulsion(propulsion);
+
+        let stale = match &self.comms {
+            Some(reading) => reading.read_at.elapsed() >= COMMS_REFRESH,
+            None => true,
+        };
+        if stale {
+            self.comms = Some(CommsReading {
+                read_at: Instant::now(),
+                status: self.sensors.get_comms()?,
+            });
+        }
+        if let Some(reading) = &self.comms {
+            message.set_comms(comms(&reading.status));
+        }
+
+        message.set_time(libkerbx::time()?);
+
+        // Nothing reaches mission control without a link, so keep a sparser record of the
+        // flight and send it once contact is back
+        if !self.in_contact() {
+            if self
+                .buffered_at
+                .map_or(true, |at| at.elapsed() >= BUFFER_INTERVAL)
+            {
+                if self.buffer.len() == BUFFER_LIMIT {
+                    self.buffer.pop_front();
+                }
+                message.set_buffered(true);
+                self.buffer.push_back(message);
+                self.buffered_at = Some(Instant::now());
+            }
+            return Ok(());
+        }
+
+        // A packet leaves the buffer only once it is sent, so none are lost if the link drops
+        // again partway through
+        while let Some(buffered) = self.buffer.front() {
+            let packet = telemetry(buffered.clone());
+            self.send(&packet)?;
+            self.buffer.pop_front();
+        }
+        self.buffered_at = None;
+        self.send(&telemetry(message))
+    }
+}
+
+/// Wraps a telemetry packet for sending
+fn telemetry(message: Telemetry) -> Sheath {
+    let mut wrapper = Sheath::new();
+    wrapper.set_field_type(Sheath_MessageType::TELEMETRY);
+    wrapper.set_telemetry(message);
+    wrapper
+}
+
+/// Packs the radio link status into its telemetry message
+fn comms(status: &CommsStatus) -> Comms {
+    let mut message = Comms::new();
+    message.set_remote_tech(status.remote_tech);
+    message.set_connected(status.connected);
+    message.set_ground_station_link(status.ground_station_link);
+    message.set_local_control(status.local_control);
+    message.set_signal_delay(status.signal_delay);
+    if let Some(ground_station) = &status.ground_station {
+        message.set_ground_station(ground_station.clone());
+    }
+    message
+}
+
+/// Packs resource levels into their telemetry message
+fn resource_levels(levels: &vehicle::ResourceLevels) -> ResourceLevels {
+    let level = |level: &vehicle::ResourceLevel| {
+        let mut message = ResourceLevel::new();
+        message.set_amount(level.amount);
+        message.set_max(level.max);
+        message
+    };
+
+    let mut message = ResourceLevels::new();
+    message.set_liquid_fuel(level(&levels.liquid_fuel));
+    message.set_oxidizer(level(&levels.oxidizer));
+    message.set_solid_fuel(level(&levels.solid_fuel));
+    message.set_mono_propellant(level(&levels.mono_propellant));
+    message.set_electric_charge(level(&levels.electric_charge));
+    message
+}
+
+/// Angle in degrees between two directions, each given as a pitch and heading in degrees
+fn angle_between((pitch_a, heading_a): (f64, f64), (pitch_b, heading_b): (f64, f64)) -> f64 {
+    let (pitch_a, pitch_b) = (pitch_a.to_radians(), pitch_b.to_radians());
+    let cos = pitch_a.sin() * pitch_b.sin()
+        + pitch_a.cos() * pitch_b.cos() * (heading_a - heading_b).to_radians().cos();
+    cos.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// Warp mode a WARP step asks for
+fn warp_mode(mode: Warp_Mode) -> WarpMode {
+    match mode {
+        Warp_Mode::RAILS => WarpMode::Rails,
+        Warp_Mode::PHYSICS => WarpMode::Physics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libkerbx::clock::GameClock;
+    use libkerbx::composition::{EngineInfo, PartInfo, StageDeltaV, VesselComposition};
+    use libkerbx::flightplan::*;
+    use libkerbx::vehicle::{OrbitalElements, VesselResources};
+    use nalgebra::Vector3;
+    use std::net::TcpListener;
+
+    /// Vehicle stand-in that reports fixed sensor values and records every command it receives.
+    #[derive(Default)]
+    struct MockVehicle {
+        lat: f64,
+        lon: f64,
+        alt: f64,
+        orbit: OrbitalElements,
+        resources: VesselResources,
+        propulsion: vehicle::Propulsion,
+        delta_v: Vec<StageDeltaV>,
+        stage: i32,
+        composition: Option<VesselComposition>,
+        stages_triggered: Cell<u32>,
+        throttle: Cell<f32>,
+        auto_pilot: Cell<bool>,
+        direction: Cell<(f32, f32)>,
+        sas: Cell<bool>,
+        sas_mode: Cell<Option<SasMode>>,
+        speed_mode: Cell<Option<SpeedMode>>,
+        rcs: Cell<bool>,
+        inputs: Cell<ControlInputs>,
+        action_groups: Cell<[bool; 10]>,
+        gear: Cell<bool>,
+        brakes: Cell<bool>,
+        lights: Cell<bool>,
+        aborted: Cell<bool>,
+        parachutes: Cell<Option<(f32, f32)>>,
+        parachutes_deployed: Cell<bool>,
+        solar_panels: Cell<bool>,
+        antennas: Cell<bool>,
+        link_down: Cell<bool>,
+        comms: RefCell<CommsStatus>,
+        servo_moves: RefCell<Vec<(Servos, f32, f32)>>,
+        ut: Cell<f64>,
+        met: Cell<f64>,
+        warps: RefCell<Vec<(WarpMode, u32)>>,
+        node: Cell<Option<ManeuverNode>>,
+    }
+
+    impl VehicleSensors for MockVehicle {
+        fn get_lat(&self) -> Result<f64, KerbxError> {
+            Ok(self.lat)
+        }
+        fn get_lon(&self) -> Result<f64, KerbxError> {
+            Ok(self.lon)
+        }
+        fn get_alt(&self) -> Result<f64, KerbxError> {
+            Ok(self.alt)
+        }
+        fn get_roll(&self) -> Result<f64, KerbxError> {
+            Ok(0.0)
+        }
+        fn get_pitch(&self) -> Result<f64, KerbxError> {
+            Ok(90.0)
+        }
+        fn get_heading(&self) -> Result<f64, KerbxError> {
+            Ok(90.0)
+        }
+        fn get_velocity(&self) -> Result<f64, KerbxError> {
+            Ok(0.0)
+        }
+        fn get_stage(&self) -> Result<i32, KerbxError> {
+            Ok(self.stage)
+        }
+        fn get_orbit(&self) -> Result<OrbitalElements, KerbxError> {
+            Ok(self.orbit.clone())
+        }
+        fn get_resources(&self) -> Result<VesselResources, KerbxError> {
+            Ok(self.resources)
+        }
+        fn get_propulsion(&self) -> Result<vehicle::Propulsion, KerbxError> {
+            Ok(self.propulsion)
+        }
+        fn get_delta_v(&self) -> Result<Vec<StageDeltaV>, KerbxError> {
+            Ok(self.delta_v.clone())
+        }
+        fn get_clock(&self) -> Result<GameClock, KerbxError> {
+            GameClock::new(self.ut.get(), self.met.get())
+        }
+        fn check_link(&self) -> LinkStatus {
+            if self.link_down.get() {
+                LinkStatus::Degraded
+            } else {
+                LinkStatus::Nominal
+            }
+        }
+        fn get_comms(&self) -> Result<CommsStatus, KerbxError> {
+            Ok(self.comms.borrow().clone())
+        }
+        fn get_maneuver_node(&self) -> Result<Option<ManeuverNode>, KerbxError> {
+            Ok(self.node.get())
+        }
+        fn get_composition(&self) -> Result<Option<VesselComposition>, KerbxError> {
+            Ok(self.composition.clone())
+        }
+    }
+
+    impl VehicleActuators for MockVehicle {
+        fn set_throttle(&self, percent: f32) -> Result<(), KerbxError> {
+            self.throttle.set(percent);
+            Ok(())
+        }
+        fn trigger_stage(&self) -> Result<(), KerbxError> {
+            self.stages_triggered.set(self.stages_triggered.get() + 1);
+            Ok(())
+        }
+        fn set_auto_pilot(&self, setting: bool) -> Result<(), KerbxError> {
+            self.auto_pilot.set(setting);
+            Ok(())
+        }
+        fn set_auto_pilot_direction(&self, pitch: f32, heading: f32) -> Result<(), KerbxError> {
+            self.direction.set((pitch, heading));
+            Ok(())
+        }
+        fn set_control_inputs(&self, inputs: ControlInputs) -> Result<(), KerbxError> {
+            self.inputs.set(inputs);
+            Ok(())
+        }
+        fn set_sas(&self, setting: bool) -> Result<(), KerbxError> {
+            self.sas.set(setting);
+            Ok(())
+        }
+        fn set_sas_mode(&self, mode: SasMode) -> Result<(), KerbxError> {
+            self.sas_mode.set(Some(mode));
+            Ok(())
+        }
+        fn set_speed_mode(&self, mode: SpeedMode) -> Result<(), KerbxError> {
+            self.speed_mode.set(Some(mode));
+            Ok(())
+        }
+        fn set_rcs(&self, setting: bool) -> Result<(), KerbxError> {
+            self.rcs.set(setting);
+            Ok(())
+        }
+        fn set_action_group(&self, group: u32, setting: bool) -> Result<(), KerbxError> {
+            let mut groups = self.action_groups.get();
+            groups[group as usize - 1] = setting;
+            self.action_groups.set(groups);
+            Ok(())
+        }
+        fn set_gear(&self, setting: bool) -> Result<(), KerbxError> {
+            self.gear.set(setting);
+            Ok(())
+        }
+        fn set_brakes(&self, setting: bool) -> Result<(), KerbxError> {
+            self.brakes.set(setting);
+            Ok(())
+        }
+        fn set_lights(&self, setting: bool) -> Result<(), KerbxError> {
+            self.lights.set(setting);
+            Ok(())
+        }
+        fn abort(&self) -> Result<(), KerbxError> {
+            self.aborted.set(true);
+            Ok(())
+        }
+        fn arm_parachutes(
+            &self,
+            deploy_altitude: f32,
+            min_pressure: f32,
+        ) -> Result<(), KerbxError> {
+            self.parachutes.set(Some((deploy_altitude, min_pressure)));
+            Ok(())
+        }
+        fn deploy_parachutes(&self) -> Result<(), KerbxError> {
+            self.parachutes_deployed.set(true);
+            Ok(())
+        }
+        fn set_solar_panels(&self, setting: bool) -> Result<(), KerbxError> {
+            self.solar_panels.set(setting);
+            Ok(())
+        }
+        fn set_antennas(&self, setting: bool) -> Result<(), KerbxError> {
+            self.antennas.set(setting);
+            Ok(())
+        }
+        fn move_servos(
+            &self,
+            servos: &Servos,
+            position: f32,
+            speed: f32,
+        ) -> Result<(), KerbxError> {
+            self.servo_moves
+                .borrow_mut()
+                .push((servos.clone(), position, speed));
+            Ok(())
+        }
+        fn set_warp(&self, mode: WarpMode, factor: u32) -> Result<(), KerbxError> {
+            self.warps.borrow_mut().push((mode, factor));
+            Ok(())
+        }
+        fn remove_maneuver_node(&self) -> Result<(), KerbxError> {
+            self.node.set(None);
+            Ok(())
+        }
+    }
+
+    /// Builds an avionics computer whose flight planner link is a local loopback listener. The
+    /// listener must be kept alive for the duration of the test.
+    fn avionics(vehicle: MockVehicle) -> (Avionics<MockVehicle>, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let avionics = Avionics::new(String::from("127.0.0.1"), port.to_string(), vehicle).unwrap();
+        (avionics, listener)
+    }
+
+    #[test]
+    fn state_machine_nominal_sequence() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.to_post().unwrap();
+        avionics.to_idle().unwrap();
+        avionics.load_flightplan().unwrap();
+        avionics.to_ready().unwrap();
+        avionics.ready_for_launch().unwrap();
+        avionics.to_countdown().unwrap();
+        avionics.to_inflight().unwrap();
+        avionics.to_landed().unwrap();
+        assert!(avionics.get_state() == &AvionicsState::LANDED);
+
+        let vehicle = &avionics.sensors;
+        assert!(!vehicle.sas.get());
+        assert!(!vehicle.rcs.get());
+        assert_eq!(vehicle.throttle.get(), 1.0);
+    }
+
+    #[test]
+    fn state_machine_rejects_skipped_state() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        assert!(matches!(avionics.to_idle(), Err(KerbxError::Contract(_))));
+        assert!(avionics.get_state() == &AvionicsState::OFF);
+    }
+
+    #[test]
+    fn validate_default_flightplan() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.load_flightplan().unwrap();
+        assert!(avionics.validate_flightplan().is_ok());
+    }
+
+    #[test]
+    fn validate_checks_staging_against_composition() {
+        use Step_ActionType::{IGNITE, NEXTSTAGE};
+        let part = |stage: i32| PartInfo {
+            stage,
+            ..Default::default()
+        };
+        // Stage 2 lights the engine, stage 1 drops it and stage 0 opens the parachute
+        let composition = VesselComposition::new(
+            vec![],
+            vec![EngineInfo {
+                part: part(2),
+                ..Default::default()
+            }],
+            vec![part(1)],
+            vec![part(0)],
+            vec![],
         );
+        let validate = |stage: i32, steps: Vec<Step_ActionType>| {
+            let (mut avionics, _listener) = avionics(MockVehicle {
+                stage,
+                composition: Some(composition.clone()),
+                ..Default::default()
+            });
+            let steps = steps
+                .into_iter()
+                .enumerate()
+                .map(|(i, action)| gen_other_step(i as u32 + 1, action, gen_time_trigger(0)))
+                .collect::<Result<Vec<Step>, KerbxError>>()
+                .unwrap();
+            avionics.flightplan = Some(gen_flightplan_from_steps(steps));
+            avionics.validate_flightplan()
+        };
 
-        let mut time = Time::new();
-        time.seconds = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("Error getting system time.")
-            .as_secs();
-        message.set_time(time);
+        assert!(validate(3, vec![IGNITE, NEXTSTAGE, NEXTSTAGE]).is_ok());
+        // Already past the engine, so IGNITE would only fire the decoupler
+        assert!(matches!(
+            validate(2, vec![IGNITE]),
+            Err(KerbxError::Validation(_))
+        ));
+        // There is no fourth stage to fire
+        assert!(matches!(
+            validate(3, vec![IGNITE, NEXTSTAGE, NEXTSTAGE, NEXTSTAGE]),
+            Err(KerbxError::Validation(_))
+        ));
+    }
 
-        let mut wrapper = Sheath::new();
-        wrapper.set_field_type(Sheath_MessageType::TELEMETRY);
-        wrapper.set_telemetry(message);
+    #[test]
+    fn validate_rejects_plan_without_ignite() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.flightplan = Some(gen_flightplan_from_steps(vec![gen_throttle_step(
+            1,
+            1.0,
+            gen_time_trigger(0),
+        )]));
+        assert!(matches!(
+            avionics.validate_flightplan(),
+            Err(KerbxError::Validation(_))
+        ));
+    }
 
-        let mut output = CodedOutputStream::new(&mut self.flight_planner);
-        if let Ok(()) = output.write_message_no_tag(&wrapper) {
-            output.flush();
-        } else {
-            eprintln!("Error writing message to flight planner.");
+    #[test]
+    fn pid_attitude_control_replaces_autopilot() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.set_attitude_control(AttitudeControl::Pid(AttitudeConfig::default()));
+
+        // The mock points straight up on a heading of 90
+        let trigger = gen_time_trigger(0);
+        avionics
+            .flightplan_exe_single_action(&gen_reorient_step(1, 0.0, 80.0, 100.0, trigger.clone()))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        avionics.track_attitude().unwrap();
+
+        let vehicle = &avionics.sensors;
+        assert!(!vehicle.auto_pilot.get());
+        assert_eq!(vehicle.direction.get(), (0.0, 0.0));
+        let inputs = vehicle.inputs.get();
+        assert!(inputs.pitch < 0.0);
+        assert!(inputs.yaw > 0.0);
+
+        avionics
+            .flightplan_exe_single_action(
+                &gen_other_step(2, Step_ActionType::COAST, trigger).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(vehicle.inputs.get(), ControlInputs::default());
+    }
+
+    #[test]
+    fn hold_step_hands_attitude_to_sas() {
+        let (avionics, _listener) = avionics(MockVehicle::default());
+        let trigger = gen_time_trigger(0);
+        avionics
+            .flightplan_exe_single_action(&gen_reorient_step(1, 0.0, 80.0, 90.0, trigger.clone()))
+            .unwrap();
+        avionics
+            .flightplan_exe_single_action(&gen_hold_step(
+                2,
+                Hold_Direction::RETROGRADE,
+                Hold_SpeedMode::SURFACE,
+                trigger.clone(),
+            ))
+            .unwrap();
+
+        let vehicle = &avionics.sensors;
+        assert!(!vehicle.auto_pilot.get());
+        assert!(vehicle.sas.get());
+        assert_eq!(vehicle.sas_mode.get(), Some(SasMode::Retrograde));
+        assert_eq!(vehicle.speed_mode.get(), Some(SpeedMode::Surface));
+        assert_eq!(avionics.commanded_direction(), None);
+
+        // Reorienting again takes the craft back from SAS
+        avionics
+            .flightplan_exe_single_action(&gen_reorient_step(3, 0.0, 45.0, 90.0, trigger))
+            .unwrap();
+        assert!(vehicle.auto_pilot.get());
+        assert!(!vehicle.sas.get());
+        assert_eq!(avionics.commanded_direction(), Some((45.0, 90.0)));
+    }
+
+    #[test]
+    fn validate_rejects_unknown_action_group() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.flightplan = Some(gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+            gen_action_group_step(2, 11, true, gen_alt_trigger(1000.0)),
+        ]));
+        assert!(matches!(
+            avionics.validate_flightplan(),
+            Err(KerbxError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn executes_part_group_steps() {
+        let (avionics, _listener) = avionics(MockVehicle::default());
+        let trigger = gen_time_trigger(0);
+        for step in vec![
+            gen_action_group_step(1, 10, true, trigger.clone()),
+            gen_toggle_step(2, Step_ActionType::GEAR, true, trigger.clone()).unwrap(),
+            gen_toggle_step(3, Step_ActionType::SOLARPANELS, true, trigger.clone()).unwrap(),
+            gen_parachute_step(4, 1_500.0, 0.04, trigger.clone()),
+            gen_other_step(5, Step_ActionType::ABORT, trigger.clone()).unwrap(),
+        ] {
+            avionics.flightplan_exe_single_action(&step).unwrap();
         }
+
+        let vehicle = &avionics.sensors;
+        assert!(vehicle.action_groups.get()[9]);
+        assert!(vehicle.gear.get());
+        assert!(vehicle.solar_panels.get());
+        assert!(!vehicle.antennas.get());
+        assert_eq!(vehicle.parachutes.get(), Some((1_500.0, 0.04)));
+        assert!(vehicle.aborted.get());
+        assert!(gen_toggle_step(6, Step_ActionType::ABORT, true, trigger).is_err());
+    }
+
+    #[test]
+    fn executes_servo_steps() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        let trigger = gen_time_trigger(0);
+        let legs = gen_servo_group_step(2, "Legs", 90.0, 0.5, gen_alt_trigger(500.0));
+        avionics.flightplan = Some(gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, trigger.clone()).unwrap(),
+            legs.clone(),
+        ]));
+        avionics.validate_flightplan().unwrap();
+
+        avionics.flightplan_exe_single_action(&legs).unwrap();
+        avionics
+            .flightplan_exe_single_action(&gen_servo_step(3, "Arm", -0.25, 1.0, trigger.clone()))
+            .unwrap();
+        assert_eq!(
+            *avionics.sensors.servo_moves.borrow(),
+            vec![
+                (Servos::Group(String::from("Legs")), 90.0, 0.5),
+                (Servos::Servo(String::from("Arm")), -0.25, 1.0),
+            ]
+        );
+
+        // A speed of 0 would leave the servos where they are
+        avionics.flightplan = Some(gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, trigger.clone()).unwrap(),
+            gen_servo_step(2, "Arm", 10.0, 0.0, trigger),
+        ]));
+        assert!(matches!(
+            avionics.validate_flightplan(),
+            Err(KerbxError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn warp_slows_down_ahead_of_the_next_trigger() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        let due = gen_ut_trigger(1_000_000.0);
+        let warp = gen_warp_step(2, Warp_Mode::RAILS, 7, gen_time_trigger(0));
+        avionics.flightplan = Some(gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+            warp.clone(),
+            gen_throttle_step(3, 1.0, due.clone()),
+        ]));
+        avionics.validate_flightplan().unwrap();
+
+        avionics.flightplan_exe_single_action(&warp).unwrap();
+        avionics.track_warp(&due).unwrap();
+        assert_eq!(*avionics.sensors.warps.borrow(), vec![(WarpMode::Rails, 7)]);
+
+        // 100,000 seconds out is too close for 100,000x but not for 10,000x
+        avionics.sensors.ut.set(900_000.0);
+        avionics.track_warp(&due).unwrap();
+        avionics.sensors.ut.set(999_999.0);
+        avionics.track_warp(&due).unwrap();
+        avionics.track_warp(&due).unwrap();
+        assert_eq!(
+            *avionics.sensors.warps.borrow(),
+            vec![
+                (WarpMode::Rails, 7),
+                (WarpMode::Rails, 6),
+                (WarpMode::Rails, 0)
+            ]
+        );
+
+        // There is no telling when an altitude is reached, so rails warp ends right away
+        avionics.flightplan_exe_single_action(&warp).unwrap();
+        avionics.track_warp(&gen_alt_trigger(70_000.0)).unwrap();
+        assert_eq!(
+            avionics.sensors.warps.borrow().last(),
+            Some(&(WarpMode::Rails, 0))
+        );
+
+        avionics.flightplan = Some(gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+            gen_warp_step(2, Warp_Mode::PHYSICS, 4, gen_time_trigger(0)),
+        ]));
+        assert!(matches!(
+            avionics.validate_flightplan(),
+            Err(KerbxError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn maneuver_node_burn_is_centered_and_cut_off() {
+        // 10 m/s^2 at full throttle, so the 100 m/s burn takes 10 s
+        let (mut avionics, _listener) = avionics(MockVehicle {
+            propulsion: vehicle::Propulsion {
+                available_thrust: 20_000.0,
+                mass: 2_000.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let execute = gen_other_step(3, Step_ActionType::EXECUTENODE, gen_time_trigger(0)).unwrap();
+        assert!(matches!(
+            avionics.flightplan_exe_single_action(&execute),
+            Err(KerbxError::Contract(_))
+        ));
+
+        // Straight up, where the mock vehicle points
+        let node = ManeuverNode {
+            ut: 1_000.0,
+            prograde: 100.0,
+            normal: 0.0,
+            radial: 0.0,
+            remaining: Vector3::new(100.0, 0.0, 0.0),
+        };
+        avionics.sensors.node.set(Some(node));
+        let warp = gen_warp_step(2, Warp_Mode::RAILS, 7, gen_time_trigger(0));
+        avionics.flightplan_exe_single_action(&warp).unwrap();
+        avionics.flightplan_exe_single_action(&execute).unwrap();
+        assert!(avionics.sensors.sas.get());
+        assert_eq!(avionics.sensors.sas_mode.get(), Some(SasMode::Maneuver));
+        assert_eq!(avionics.sensors.throttle.get(), 0.0);
+
+        // Warp leaves the burn three seconds in hand however far off the next trigger is
+        avionics.track_warp(&gen_ut_trigger(1_000_000.0)).unwrap();
+        assert_eq!(
+            avionics.sensors.warps.borrow().last(),
+            Some(&(WarpMode::Rails, 4))
+        );
+
+        avionics.sensors.ut.set(994.0);
+        avionics.track_node().unwrap();
+        assert_eq!(avionics.sensors.throttle.get(), 0.0);
+        avionics.sensors.ut.set(995.0);
+        avionics.track_node().unwrap();
+        assert_eq!(avionics.sensors.throttle.get(), 1.0);
+
+        // Throttled down over the last two seconds of the burn
+        avionics.sensors.node.set(Some(ManeuverNode {
+            remaining: Vector3::new(5.0, 0.0, 0.0),
+            ..node
+        }));
+        avionics.track_node().unwrap();
+        assert!((avionics.sensors.throttle.get() - 0.25).abs() < 1e-6);
+
+        avionics.sensors.node.set(Some(ManeuverNode {
+            remaining: Vector3::new(0.05, 0.0, 0.0),
+            ..node
+        }));
+        avionics.track_node().unwrap();
+        assert_eq!(avionics.sensors.throttle.get(), 0.0);
+        assert!(avionics.sensors.node.get().is_none());
+    }
+
+    #[test]
+    fn flightplan_steps_pop_in_order() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.flightplan = Some(gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+            gen_throttle_step(2, 0.5, gen_alt_trigger(1000.0)),
+        ]));
+        assert_eq!(
+            avionics.flightplan_pop_step().unwrap().unwrap().get_count(),
+            1
+        );
+        assert_eq!(
+            avionics.flightplan_pop_step().unwrap().unwrap().get_count(),
+            2
+        );
+        assert!(avionics.flightplan_pop_step().unwrap().is_none());
+    }
+
+    #[test]
+    fn alt_trigger() {
+        let (avionics, _listener) = avionics(MockVehicle {
+            alt: 1000.0,
+            ..Default::default()
+        });
+        assert!(avionics
+            .flightplan_check_trigger(&gen_alt_trigger(1005.0))
+            .unwrap());
+        assert!(!avionics
+            .flightplan_check_trigger(&gen_alt_trigger(2000.0))
+            .unwrap());
+    }
+
+    #[test]
+    fn orbit_triggers() {
+        let (avionics, _listener) = avionics(MockVehicle {
+            orbit: OrbitalElements {
+                apoapsis_altitude: 80_000.0,
+                periapsis_altitude: -200_000.0,
+                time_to_apoapsis: 45.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert!(avionics
+            .flightplan_check_trigger(&gen_apoapsis_trigger(75_000.0))
+            .unwrap());
+        assert!(!avionics
+            .flightplan_check_trigger(&gen_apoapsis_trigger(85_000.0))
+            .unwrap());
+        assert!(!avionics
+            .flightplan_check_trigger(&gen_periapsis_trigger(70_000.0))
+            .unwrap());
+        assert!(avionics
+            .flightplan_check_trigger(&gen_time_to_apoapsis_trigger(60.0))
+            .unwrap());
+        assert!(!avionics
+            .flightplan_check_trigger(&gen_time_to_apoapsis_trigger(30.0))
+            .unwrap());
+    }
+
+    #[test]
+    fn telemetry_reports_resources() {
+        let mut vehicle = MockVehicle::default();
+        vehicle.resources.vessel.liquid_fuel.amount = 360.0;
+        vehicle.resources.vessel.liquid_fuel.max = 900.0;
+        vehicle.resources.stage.electric_charge.amount = 50.0;
+        let (mut avionics, listener) = avionics(vehicle);
+
+        avionics.send_telemetry().unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let sheath: Sheath = krpc_mars::protobuf::CodedInputStream::new(&mut stream)
+            .read_message()
+            .unwrap();
+
+        let telemetry = sheath.get_telemetry();
+        let fuel = telemetry.get_vessel_resources().get_liquid_fuel();
+        assert_eq!((fuel.amount, fuel.max), (360.0, 900.0));
+        let charge = telemetry.get_stage_resources().get_electric_charge();
+        assert_eq!(charge.amount, 50.0);
+    }
+
+    #[test]
+    fn telemetry_reports_propulsion() {
+        let stage = |delta_v: f64| StageDeltaV {
+            delta_v,
+            ..Default::default()
+        };
+        let (mut avionics, listener) = avionics(MockVehicle {
+            propulsion: vehicle::Propulsion {
+                thrust: 200_000.0,
+                available_thrust: 200_000.0,
+                mass: 10_000.0,
+                gravity: 10.0,
+                ..Default::default()
+            },
+            delta_v: vec![stage(2_000.0), stage(1_500.0)],
+            ..Default::default()
+        });
+
+        avionics.send_telemetry().unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let sheath: Sheath = krpc_mars::protobuf::CodedInputStream::new(&mut stream)
+            .read_message()
+            .unwrap();
+
+        let propulsion = sheath.get_telemetry().get_propulsion();
+        assert_eq!(propulsion.twr, 2.0);
+        assert_eq!(propulsion.stage_delta_v, 2_000.0);
+        assert_eq!(propulsion.delta_v, 3_500.0);
+    }
+
+    #[test]
+    fn buffers_telemetry_out_of_contact() {
+        let blackout = CommsStatus {
+            remote_tech: true,
+            connected: false,
+            ground_station_link: false,
+            ..Default::default()
+        };
+        let (mut avionics, listener) = avionics(MockVehicle {
+            comms: RefCell::new(blackout),
+            ..Default::default()
+        });
+
+        // Only one packet a second is kept while nobody is listening
+        avionics.send_telemetry().unwrap();
+        avionics.send_telemetry().unwrap();
+        assert!(!avionics.in_contact());
+        assert_eq!(avionics.buffer.len(), 1);
+
+        avionics.send_alive().unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut input = krpc_mars::protobuf::CodedInputStream::new(&mut stream);
+        let sheath: Sheath = input.read_message().unwrap();
+        assert_eq!(sheath.get_watchdog().status, WatchDog_Status::NOCONTACT);
+
+        avionics.sensors.comms.replace(CommsStatus {
+            remote_tech: true,
+            signal_delay: 0.5,
+            ground_station: Some(String::from("Mission Control")),
+            ..Default::default()
+        });
+        avionics.comms = None;
+        avionics.send_telemetry().unwrap();
+        assert!(avionics.buffer.is_empty());
+
+        let sheath: Sheath = input.read_message().unwrap();
+        assert!(sheath.get_telemetry().buffered);
+        assert!(!sheath.get_telemetry().get_comms().ground_station_link);
+        let sheath: Sheath = input.read_message().unwrap();
+        let comms = sheath.get_telemetry().get_comms();
+        assert!(!sheath.get_telemetry().buffered);
+        assert_eq!(comms.signal_delay, 0.5);
+        assert_eq!(comms.ground_station, "Mission Control");
+    }
+
+    #[test]
+    fn twr_step_follows_mass() {
+        let (mut avionics, _listener) = avionics(MockVehicle {
+            propulsion: vehicle::Propulsion {
+                available_thrust: 300_000.0,
+                mass: 10_000.0,
+                gravity: 10.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let trigger = gen_time_trigger(0);
+
+        avionics
+            .flightplan_exe_single_action(&gen_twr_step(1, 1.5, trigger.clone()))
+            .unwrap();
+        assert_eq!(avionics.sensors.throttle.get(), 0.5);
+
+        // Lighter craft need less throttle for the same TWR
+        avionics.sensors.propulsion.mass = 5_000.0;
+        avionics.track_twr().unwrap();
+        assert_eq!(avionics.sensors.throttle.get(), 0.25);
+
+        // A plain throttle step releases the TWR hold
+        avionics
+            .flightplan_exe_single_action(&gen_throttle_step(2, 1.0, trigger))
+            .unwrap();
+        avionics.track_twr().unwrap();
+        assert_eq!(avionics.sensors.throttle.get(), 1.0);
+    }
+
+    #[test]
+    fn position_trigger() {
+        let (avionics, _listener) = avionics(MockVehicle {
+            lat: -0.1,
+            lon: -74.5,
+            ..Default::default()
+        });
+        assert!(avionics
+            .flightplan_check_trigger(&gen_pos_trigger(-0.1, -74.0))
+            .unwrap());
+        assert!(!avionics
+            .flightplan_check_trigger(&gen_pos_trigger(45.0, -74.0))
+            .unwrap());
+    }
+
+    #[test]
+    fn time_trigger() {
+        let (avionics, _listener) = avionics(MockVehicle::default());
+        assert!(avionics
+            .flightplan_check_trigger(&gen_time_trigger(0))
+            .unwrap());
+        assert!(!avionics
+            .flightplan_check_trigger(&gen_time_trigger(u64::MAX))
+            .unwrap());
+    }
+
+    #[test]
+    fn time_triggers_follow_game_time() {
+        let (avionics, _listener) = avionics(MockVehicle {
+            ut: Cell::new(10_000.0),
+            met: Cell::new(60.0),
+            ..Default::default()
+        });
+        let ut_trigger = gen_ut_trigger(10_000.5);
+        let met_trigger = gen_met_trigger(90.0);
+        assert!(!avionics.flightplan_check_trigger(&ut_trigger).unwrap());
+        assert!(!avionics.flightplan_check_trigger(&met_trigger).unwrap());
+
+        // Warp runs game time ahead of the wall clock
+        avionics.sensors.ut.set(10_030.0);
+        avionics.sensors.met.set(90.0);
+        assert!(avionics.flightplan_check_trigger(&ut_trigger).unwrap());
+        assert!(avionics.flightplan_check_trigger(&met_trigger).unwrap());
+    }
+
+    #[test]
+    fn execute_actions() {
+        let (avionics, _listener) = avionics(MockVehicle::default());
+        let trigger = gen_time_trigger(0);
+
+        avionics
+            .flightplan_exe_single_action(&gen_reorient_step(1, 0.0, 45.0, 90.0, trigger.clone()))
+            .unwrap();
+        assert!(avionics.sensors.auto_pilot.get());
+        assert_eq!(avionics.sensors.direction.get(), (45.0, 90.0));
+
+        avionics
+            .flightplan_exe_single_action(&gen_throttle_step(2, 0.5, trigger.clone()))
+            .unwrap();
+        assert_eq!(avionics.sensors.throttle.get(), 0.5);
+
+        avionics
+            .flightplan_exe_single_action(
+                &gen_other_step(3, Step_ActionType::IGNITE, trigger).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(avionics.sensors.stages_triggered.get(), 1);
+    }
+
+    #[test]
+    fn hold_policy_waits_out_link_loss() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.sensors.link_down.set(true);
+
+        assert_eq!(avionics.check_link().unwrap(), LinkStatus::Degraded);
+        let lost: Result<(), KerbxError> = Err(KerbxError::LinkDown);
+        assert!(avionics.tolerate_link_loss(lost).unwrap().is_none());
+        assert!(avionics.get_state() == &AvionicsState::OFF);
+
+        let failed: Result<(), KerbxError> = Err(KerbxError::Validation(String::from("bad")));
+        assert!(avionics.tolerate_link_loss(failed).is_err());
+    }
+
+    #[test]
+    fn abort_policy_errors_on_link_loss() {
+        let (mut avionics, _listener) = avionics(MockVehicle::default());
+        avionics.set_link_loss_policy(LinkLossPolicy::Abort);
+        assert_eq!(avionics.check_link().unwrap(), LinkStatus::Nominal);
+
+        avionics.sensors.link_down.set(true);
+        assert!(avionics.check_link().unwrap_err().is_link_failure());
+        assert!(avionics.get_state() == &AvionicsState::ERROR);
     }
 }
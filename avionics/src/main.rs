@@ -22,19 +22,22 @@
 */
 
 use clap::{App, Arg};
-use krpc_mars::RPCClient;
-use libkerbx::kerbx::{Telemetry, Time};
-use libkerbx::KerbxTransport;
+use libkerbx::hud::Hud;
+use libkerbx::overlay::Overlay;
+use libkerbx::sim::{SimulatedVehicle, DEFAULT_PAYLOAD_MASS};
+use libkerbx::vehicle::{LinkStatus, VehicleActuators, VehicleSensors};
+use libkerbx::{KerbxError, KerbxTransport, VesselTarget};
 
-use avionics::AvionicsState;
-use std::time::{Duration, SystemTime};
-use std::{error::Error, thread};
+use avionics::attitude::{AttitudeConfig, PidGains};
+use avionics::{AttitudeControl, AvionicsState, LinkLossPolicy};
+use std::thread;
+use std::time::Duration;
 
 use avionics::Avionics;
 
 const FIVEHUNDREDHZ_IN_MS: u64 = 2;
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> Result<(), KerbxError> {
     // Parse command line arguments.
     let matches = App::new("KerbX Avionics Computer")
         .version(env!("CARGO_PKG_VERSION"))
@@ -44,7 +47,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             Arg::with_name("simip")
                 .short("i")
                 .takes_value(true)
-                .required(true)
+                .required_unless("offline")
                 .help("IP Address of the KRPC (Sim) Server"),
         )
         .arg(
@@ -54,11 +57,25 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .default_value("50000")
                 .help("Port of the KRPC (SIM) Server"),
         )
+        .arg(
+            Arg::with_name("simstreamport")
+                .short("t")
+                .takes_value(true)
+                .default_value("50001")
+                .help("Stream Port of the KRPC (SIM) Server"),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .short("s")
+                .long("offline")
+                .conflicts_with("simip")
+                .help("Fly the built-in simulated vehicle instead of connecting to KSP"),
+        )
         .arg(
             Arg::with_name("plannerip")
                 .short("a")
                 .takes_value(true)
-                .required(true)
+                .required_unless("listvessels")
                 .help("IP Address of the Flight Planning Computer in Mission Control"),
         )
         .arg(
@@ -68,54 +85,297 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .default_value("51961")
                 .help("Port of the Flight Planning Computer in Mission Control"),
         )
+        .arg(
+            Arg::with_name("flightplan")
+                .short("f")
+                .takes_value(true)
+                .help("Flight plan file to fly instead of the built-in launch plan"),
+        )
+        .arg(
+            Arg::with_name("linkloss")
+                .short("l")
+                .long("link-loss")
+                .takes_value(true)
+                .possible_values(&["hold", "abort"])
+                .default_value("hold")
+                .help("Hold the last commands or go to ERROR while the link to KSP is down"),
+        )
+        .arg(
+            Arg::with_name("attitude")
+                .long("attitude")
+                .takes_value(true)
+                .possible_values(&["autopilot", "pid"])
+                .default_value("autopilot")
+                .help("Steer with KSP's autopilot or with the avionics' own PID loops"),
+        )
+        .arg(
+            Arg::with_name("attitudegains")
+                .long("attitude-gains")
+                .takes_value(true)
+                .value_name("KP,KI,KD")
+                .help("Gains of the pitch, yaw and roll PID loops"),
+        )
+        .arg(
+            Arg::with_name("attituderate")
+                .long("attitude-rate-limit")
+                .takes_value(true)
+                .help("Most a PID control input may change per second, from -1 to 1 being 2"),
+        )
+        .arg(
+            Arg::with_name("alarms")
+                .long("alarms")
+                .takes_value(true)
+                .value_name("MARGIN")
+                .requires("flightplan")
+                .conflicts_with("offline")
+                .help("Set Kerbal Alarm Clock alarms this many seconds before each timed step"),
+        )
+        .arg(
+            Arg::with_name("overlay")
+                .long("overlay")
+                .conflicts_with("offline")
+                .help("Draw the flight plan and the vessel's direction vectors in game"),
+        )
+        .arg(
+            Arg::with_name("hud")
+                .long("hud")
+                .conflicts_with("offline")
+                .help("Show avionics state, executed steps and errors on screen in game"),
+        )
+        .arg(
+            Arg::with_name("vessel")
+                .long("vessel")
+                .takes_value(true)
+                .conflicts_with_all(&["offline", "vesselid"])
+                .help("Fly the vessel with this name instead of the active vessel"),
+        )
+        .arg(
+            Arg::with_name("vesselid")
+                .long("vessel-id")
+                .takes_value(true)
+                .conflicts_with("offline")
+                .help("Fly the vessel with this kRPC id instead of the active vessel"),
+        )
+        .arg(
+            Arg::with_name("listvessels")
+                .long("list-vessels")
+                .conflicts_with("offline")
+                .help("List the id and name of every vessel in the game and exit"),
+        )
         .get_matches();
 
+    // Only --list-vessels runs without a flight planner
+    let planner_ip = String::from(matches.value_of("plannerip").unwrap_or_default());
+    let planner_port = String::from(matches.value_of("plannerport").unwrap());
+    let flightplan = matches.value_of("flightplan");
+    let link_loss = match matches.value_of("linkloss") {
+        Some("abort") => LinkLossPolicy::Abort,
+        _ => LinkLossPolicy::Hold,
+    };
+    let attitude = match matches.value_of("attitude") {
+        Some("pid") => AttitudeControl::Pid(attitude_config(
+            matches.value_of("attitudegains"),
+            matches.value_of("attituderate"),
+        )?),
+        _ => AttitudeControl::AutoPilot,
+    };
+
+    if matches.is_present("offline") {
+        // No KSP available, so our sensor inputs and control surface outputs come from the
+        // built-in flight dynamics model.
+        let ship =
+            SimulatedVehicle::real_time(SimulatedVehicle::default_stages(), DEFAULT_PAYLOAD_MASS);
+        return fly(
+            &mut Avionics::new(planner_ip, planner_port, ship)?,
+            flightplan,
+            link_loss,
+            attitude,
+            None,
+        );
+    }
+
     // Connect to KSP via krpc-rs -- this provides our sensor inputs and control surface outputs
     let server_address = format!(
         "{}:{}",
         matches.value_of("simip").unwrap(),
         matches.value_of("simport").unwrap()
     );
-    let client = RPCClient::connect("Avionics Computer", server_address)
-        .expect("Could not connect to KRPC Server.");
 
-    // We obfuscate the RPC interface with our KerbxTransport wrapper.
-    // TODO: Move RPC connections into the KerbxTransport Interface and just pass in the simip and simport
-    let ship = KerbxTransport::new(client)?;
+    let target = match (matches.value_of("vessel"), matches.value_of("vesselid")) {
+        (Some(name), _) => VesselTarget::Name(String::from(name)),
+        (None, Some(id)) => VesselTarget::Id(
+            id.parse()
+                .map_err(|_| KerbxError::Contract(format!("Vessel id {} is not a number.", id)))?,
+        ),
+        (None, None) => VesselTarget::Active,
+    };
+    // Name each client after its vessel so avionics computers flying side by side can be told
+    // apart in the kRPC server window
+    let client_name = match &target {
+        VesselTarget::Active => String::from("Avionics Computer"),
+        VesselTarget::Name(name) => format!("Avionics Computer ({})", name),
+        VesselTarget::Id(id) => format!("Avionics Computer (#{})", id),
+    };
+
+    // We obfuscate the RPC interface with our KerbxTransport wrapper, which also reconnects if
+    // the kRPC server drops us.
+    let mut ship =
+        KerbxTransport::connect_to(&client_name, server_address.clone(), target.clone())?;
+
+    if matches.is_present("listvessels") {
+        for vessel in ship.list_vessels()? {
+            println!("{}\t{}", vessel.id, vessel.name);
+        }
+        return Ok(());
+    }
+
+    // Telemetry is read from kRPC streams so each packet is a single physics frame
+    ship.enable_streams(format!(
+        "{}:{}",
+        matches.value_of("simip").unwrap(),
+        matches.value_of("simstreamport").unwrap()
+    ))?;
+
+    if let (Some(margin), Some(filename)) = (matches.value_of("alarms"), flightplan) {
+        let margin = margin
+            .parse()
+            .map_err(|_| KerbxError::Contract(format!("Margin {} is not a number.", margin)))?;
+        set_alarms(&ship, filename, margin)?;
+    }
+
+    // Drawing goes over a connection of its own so it never holds up flying
+    let overlay = if matches.is_present("overlay") {
+        Some(Overlay::new(KerbxTransport::connect_to(
+            &format!("{} Overlay", client_name),
+            server_address.clone(),
+            target.clone(),
+        )?))
+    } else {
+        None
+    };
+
+    let mut status = Avionics::new(planner_ip, planner_port, ship)?;
+    // As does the HUD, which the avionics posts to from POST on
+    if matches.is_present("hud") {
+        status.set_hud(Hud::new(KerbxTransport::connect_to(
+            &format!("{} HUD", client_name),
+            server_address,
+            target,
+        )?));
+    }
+
+    fly(&mut status, flightplan, link_loss, attitude, overlay)
+}
+
+/// Replaces the alarms of an earlier flight with one for each timed step of the flight plan.
+/// Flying goes ahead without them if Kerbal Alarm Clock is not installed.
+/// margin: Seconds of warning before each step
+fn set_alarms(ship: &KerbxTransport, filename: &str, margin: f64) -> Result<(), KerbxError> {
+    let plan = libkerbx::flightplan::load_from_file(filename)?;
+    let set = ship
+        .clear_alarms()
+        .and_then(|_| ship.alarm_flight_plan(&plan, margin));
+    match set {
+        Ok(alarms) => eprintln!("Set {} alarms in Kerbal Alarm Clock.", alarms.len()),
+        Err(KerbxError::ModUnavailable(name)) => {
+            eprintln!("{} is not installed, no alarms set.", name)
+        }
+        Err(error) => return Err(error),
+    }
+    Ok(())
+}
+
+/// Builds the PID attitude controller tuning from the command line, keeping the defaults for
+/// anything not given.
+/// gains: "kp,ki,kd" used for all three axes
+/// rate_limit: Most a control input may change per second
+fn attitude_config(
+    gains: Option<&str>,
+    rate_limit: Option<&str>,
+) -> Result<AttitudeConfig, KerbxError> {
+    let mut config = AttitudeConfig::default();
+    if let Some(gains) = gains {
+        let parsed: Vec<f64> = gains
+            .split(',')
+            .map(|gain| gain.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| KerbxError::Contract(format!("Gains {} are not numbers.", gains)))?;
+        if let [kp, ki, kd] = parsed[..] {
+            let gains = PidGains { kp, ki, kd };
+            config.pitch = gains;
+            config.yaw = gains;
+            config.roll = gains;
+        } else {
+            return Err(KerbxError::Contract(String::from(
+                "Attitude gains must be given as kp,ki,kd.",
+            )));
+        }
+    }
+    if let Some(rate_limit) = rate_limit {
+        config.max_input_rate = rate_limit.parse().map_err(|_| {
+            KerbxError::Contract(format!("Rate limit {} is not a number.", rate_limit))
+        })?;
+    }
+    Ok(config)
+}
+
+/// Runs the avionics computer from POST through the end of the flight plan.
+/// flightplan: Optional flight plan file to load in place of the built-in plan
+/// link_loss: What to do while the link to the vehicle is degraded
+/// attitude: What steers the vehicle on REORIENT steps
+/// overlay: Draws the flight in game, if wanted
+fn fly<V: VehicleSensors + VehicleActuators>(
+    status: &mut Avionics<V>,
+    flightplan: Option<&str>,
+    link_loss: LinkLossPolicy,
+    attitude: AttitudeControl,
+    mut overlay: Option<Overlay>,
+) -> Result<(), KerbxError> {
+    status.set_link_loss_policy(link_loss);
+    status.set_attitude_control(attitude);
 
     // Area where we perform the Power-On-Self-Test Routine Operations //
-    // "Boot UP" our flight computer
-    let mut status = Avionics::new(
-        String::from(matches.value_of("plannerip").unwrap()),
-        String::from(matches.value_of("plannerport").unwrap()),
-        ship,
-    )?;
     // Now Entering POST
-    status.to_post();
+    status.to_post()?;
 
     // TODO: Check for POST failure and set ERROR message.
 
     //***********************************************************************//
     // Area where we perform initial load of the flight plan from the flight planning computer //
-    status.send_alive();
+    status.send_alive()?;
     // Now wait for flight plan...
-    status.to_idle();
+    status.to_idle()?;
 
     // Here's where we block for receipt of the flight plan
     // TODO: Receive flight plan over the network and validate flight plan
-    status.load_flightplan();
+    match flightplan {
+        Some(filename) => status.load_flightplan_from_file(filename)?,
+        None => status.load_flightplan()?,
+    }
+
+    // A plan this craft cannot fly never leaves IDLE
+    if let Err(error) = status.validate_flightplan() {
+        status.to_error(&error.to_string());
+        return Err(error);
+    }
 
-    status.to_ready();
+    status.to_ready()?;
+    if let (Some(drawing), Some(plan)) = (overlay.as_mut(), status.flightplan()) {
+        if let Err(error) = drawing.draw_flight_plan(plan) {
+            eprintln!("Could not draw the flight plan: {}", error);
+        }
+    }
 
     // Prepare craft for launch
-    status.ready_for_launch();
+    status.ready_for_launch()?;
     //**********************************************************************//
 
     // Area where we initiate launch //
-    status.to_countdown();
+    status.to_countdown()?;
 
     for tick in (1..11).rev() {
         println!("Launching in {} seconds...", tick);
+        status.show_countdown(tick);
 
         // TODO: This should be a spinlock waiting for abort commands from the flight planning system
         // TODO: Check craft for errors...
@@ -123,31 +383,49 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     // Execute the first step!
-    let first = status.flightplan_pop_step().expect("First step empty.");
-    status.flightplan_exe_single_action(&first);
+    let first = status
+        .flightplan_pop_step()?
+        .ok_or_else(|| KerbxError::Validation(String::from("First step empty.")))?;
+    status.flightplan_exe_single_action(&first)?;
+    status.to_inflight()?;
     //*********************************************************************//
     // todo: Improve robustness of flight control loop
     while status.get_state() != &AvionicsState::LANDED {
-        while let Some(step) = status.flightplan_pop_step() {
+        while let Some(step) = status.flightplan_pop_step()? {
             // Keep looking until our Trigger is met and we execute our step
             let trigger = step.trigger.get_ref();
             loop {
-                if status.flightplan_check_trigger(trigger) {
-                    // todo: add verification that the step count is always accurately tracking the number of steps executed.
-                    status.flightplan_exe_single_action(&step);
-                    status.inc_step();
+                // Commands cannot reach the vehicle over a degraded link, so hold until it is back
+                if status.check_link()? == LinkStatus::Nominal {
+                    let triggered = status.flightplan_check_trigger(trigger);
+                    if let Some(true) = status.tolerate_link_loss(triggered)? {
+                        // todo: add verification that the step count is always accurately tracking the number of steps executed.
+                        let executed = status.flightplan_exe_single_action(&step);
+                        if status.tolerate_link_loss(executed)?.is_some() {
+                            status.inc_step();
+                            break;
+                        }
+                    }
+                    status.track(Some(trigger))?;
                 }
                 //
-                status.send_alive();
-                status.send_telemetry();
+                status.send_alive()?;
+                let sent = status.send_telemetry();
+                status.tolerate_link_loss(sent)?;
+                draw(&mut overlay, status.commanded_direction());
                 // Effectively this is sending the environment at 500hz
                 thread::sleep(Duration::from_millis(FIVEHUNDREDHZ_IN_MS));
             }
         }
 
         // We're out of flight planning steps but keep sending our watchdogs and telemetry
-        status.send_alive();
-        status.send_telemetry();
+        if status.check_link()? == LinkStatus::Nominal {
+            status.track(None)?;
+        }
+        status.send_alive()?;
+        let sent = status.send_telemetry();
+        status.tolerate_link_loss(sent)?;
+        draw(&mut overlay, status.commanded_direction());
 
         // Todo: Implement check for transition to to_landed state -- could just cheat and use krpc
 
@@ -156,3 +434,52 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+/// Redraws the vectors of the overlay, switching the overlay off for the rest of the flight if
+/// a draw fails.
+fn draw(overlay: &mut Option<Overlay>, commanded: Option<(f32, f32)>) {
+    if let Some(drawing) = overlay {
+        if let Err(error) = drawing.update(commanded) {
+            eprintln!("Stopped drawing the overlay: {}", error);
+            *overlay = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libkerbx::flightplan::*;
+    use libkerbx::kerbx::Step_ActionType;
+    use std::net::TcpListener;
+
+    #[test]
+    fn invalid_plans_are_not_flown() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let vehicle =
+            SimulatedVehicle::new(SimulatedVehicle::default_stages(), DEFAULT_PAYLOAD_MASS);
+        let mut status =
+            Avionics::new(String::from("127.0.0.1"), port.to_string(), vehicle).unwrap();
+
+        // Action groups are numbered 1 to 10
+        let plan = gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+            gen_action_group_step(2, 11, true, gen_met_trigger(10.0)),
+        ]);
+        let filename = std::env::temp_dir().join("kerbx-invalid-plan.json");
+        write_to_file(filename.to_str().unwrap(), &plan).unwrap();
+
+        let flown = fly(
+            &mut status,
+            filename.to_str(),
+            LinkLossPolicy::Hold,
+            AttitudeControl::AutoPilot,
+            None,
+        );
+        std::fs::remove_file(&filename).unwrap();
+
+        assert!(matches!(flown, Err(KerbxError::Validation(_))));
+        assert!(status.get_state() == &AvionicsState::ERROR);
+    }
+}
@@ -0,0 +1,165 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Flies whole flight plans through the avionics against the simulated vehicle on its manual
+//! clock. Each tick runs the same trigger, step and tracking calls as the flight loop of the
+//! avionics binary, then advances the simulation by one physics tick, so a flight comes out the
+//! same every time it is flown.
+
+use crate::{Avionics, AvionicsState};
+use libkerbx::flightplan::*;
+use libkerbx::kerbx::{FlightPlan, Step_ActionType};
+use libkerbx::sim::{SimulatedVehicle, DEFAULT_PAYLOAD_MASS};
+use libkerbx::vehicle::VehicleSensors;
+use std::net::TcpListener;
+
+/// Simulated seconds per tick. Matches the physics tick of the simulation.
+const TICK: f64 = 0.02;
+/// Simulated seconds a flight may take before the test gives up on it
+const FLIGHT_LIMIT: f64 = 3_600.0;
+/// Altitude in m below which the vehicle is on the ground
+const GROUND_ALTITUDE: f64 = 1.0;
+
+/// Flies plan from the launch pad until the vehicle is back on the ground, returning the
+/// avionics in LANDED. The listener stands in for the flight planner and must be kept alive
+/// while the avionics is used.
+fn fly(plan: Option<FlightPlan>) -> (Avionics<SimulatedVehicle>, TcpListener) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let vehicle = SimulatedVehicle::new(SimulatedVehicle::default_stages(), DEFAULT_PAYLOAD_MASS);
+    let mut avionics = Avionics::new(String::from("127.0.0.1"), port.to_string(), vehicle).unwrap();
+
+    avionics.to_post().unwrap();
+    avionics.to_idle().unwrap();
+    match plan {
+        Some(plan) => avionics.flightplan = Some(plan),
+        None => avionics.load_flightplan().unwrap(),
+    }
+    avionics.validate_flightplan().unwrap();
+    avionics.to_ready().unwrap();
+    avionics.ready_for_launch().unwrap();
+    avionics.to_countdown().unwrap();
+
+    let first = avionics.flightplan_pop_step().unwrap().unwrap();
+    avionics.flightplan_exe_single_action(&first).unwrap();
+    avionics.to_inflight().unwrap();
+
+    while let Some(step) = avionics.flightplan_pop_step().unwrap() {
+        let trigger = step.get_trigger();
+        while !avionics.flightplan_check_trigger(trigger).unwrap() {
+            avionics.track(Some(trigger)).unwrap();
+            tick(&avionics, step.get_count());
+        }
+        avionics.flightplan_exe_single_action(&step).unwrap();
+        avionics.inc_step();
+    }
+
+    // Out of steps, so coast until the vehicle comes back down
+    while !landed(&avionics) {
+        avionics.track(None).unwrap();
+        tick(&avionics, 0);
+    }
+    avionics.to_landed().unwrap();
+    (avionics, listener)
+}
+
+/// Whether the vehicle has come to rest on the ground since launch. The simulation stops
+/// anything that reaches the ground moving down.
+fn landed(avionics: &Avionics<SimulatedVehicle>) -> bool {
+    let vehicle = &avionics.sensors;
+    vehicle.get_clock().unwrap().met > 0.0
+        && vehicle.get_alt().unwrap() < GROUND_ALTITUDE
+        && vehicle.get_velocity().unwrap() == 0.0
+}
+
+/// Advances the simulation by one tick, failing the test once the flight runs too long.
+/// waiting_on: Count of the step whose trigger is awaited, or 0 once every step has run
+fn tick(avionics: &Avionics<SimulatedVehicle>, waiting_on: u32) {
+    avionics.sensors.advance(TICK);
+    assert!(
+        avionics.sensors.elapsed() < FLIGHT_LIMIT,
+        "Flight ran past {} s waiting on step {}",
+        FLIGHT_LIMIT,
+        waiting_on
+    );
+}
+
+/// Climbs away under the autopilot, drops the spent first stage without lighting the second
+/// and comes back down under the parachute.
+fn suborbital_plan() -> FlightPlan {
+    gen_flightplan_from_steps(vec![
+        gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+        gen_reorient_step(2, 0.0, 80.0, 90.0, gen_met_trigger(10.0)),
+        gen_twr_step(3, 2.0, gen_met_trigger(20.0)),
+        gen_throttle_step(4, 0.0, gen_apoapsis_trigger(30_000.0)),
+        gen_other_step(5, Step_ActionType::NEXTSTAGE, gen_met_trigger(60.0)).unwrap(),
+        gen_parachute_step(6, 1_000.0, 0.04, gen_met_trigger(70.0)),
+    ])
+}
+
+/// Where and when the vehicle landed, and what was left of it
+fn end_state(avionics: &Avionics<SimulatedVehicle>) -> (f64, f64, i32, f64, f64) {
+    let vehicle = &avionics.sensors;
+    (
+        vehicle.get_lat().unwrap(),
+        vehicle.get_lon().unwrap(),
+        vehicle.get_stage().unwrap(),
+        vehicle.get_mass(),
+        vehicle.elapsed(),
+    )
+}
+
+#[test]
+fn default_plan_flies_to_completion() {
+    let (avionics, _listener) = fly(None);
+    assert!(avionics.get_state() == &AvionicsState::LANDED);
+    assert!(avionics.flightplan().unwrap().steps.is_empty());
+
+    // The one IGNITE lit the first stage, which burned dry and carried the vehicle up and back
+    let vehicle = &avionics.sensors;
+    assert_eq!(vehicle.get_stage().unwrap(), 1);
+    assert_eq!(vehicle.get_stage_fuel(), 0.0);
+    assert!(vehicle.get_alt().unwrap() < GROUND_ALTITUDE);
+    assert!(vehicle.get_clock().unwrap().met > 0.0);
+}
+
+#[test]
+fn suborbital_plan_runs_every_step() {
+    let (avionics, _listener) = fly(Some(suborbital_plan()));
+    assert!(avionics.get_state() == &AvionicsState::LANDED);
+    assert!(avionics.flightplan().unwrap().steps.is_empty());
+
+    // Both stages went, and the second never burned
+    let vehicle = &avionics.sensors;
+    assert_eq!(vehicle.get_stage().unwrap(), 0);
+    let second = &SimulatedVehicle::default_stages()[1];
+    assert_eq!(vehicle.get_stage_fuel(), second.fuel_mass);
+    assert!(avionics.commanded_direction().is_some());
+}
+
+#[test]
+fn flights_are_repeatable() {
+    let (first, _first_listener) = fly(Some(suborbital_plan()));
+    let (second, _second_listener) = fly(Some(suborbital_plan()));
+    assert_eq!(end_state(&first), end_state(&second));
+}
@@ -23,12 +23,60 @@
 
 use libkerbx::kerbx::Sheath_oneof_message::flightplan;
 use libkerbx::kerbx::*;
+use libkerbx::KerbxError;
 use protobuf::{CodedInputStream, CodedOutputStream, ProtobufError, ProtobufResult};
-use std::error::Error;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpSocket, TcpStream};
 use tokio::sync::broadcast::{Receiver, Sender};
 
+/// Whether a command sent now reaches the vehicle, as of the last telemetry received
+#[derive(Clone, Debug, PartialEq)]
+pub enum Uplink {
+    /// Commands arrive at once
+    Immediate,
+    /// Commands arrive after the RemoteTech signal delay, relayed by the named ground station
+    Delayed { delay: Duration, station: String },
+    /// No link to a ground station, so commands would be lost
+    Down,
+}
+
+impl Uplink {
+    /// Reads the uplink from a telemetry packet. Returns None for packets recorded while out of
+    /// contact, as they describe the link as it was.
+    pub fn from_telemetry(telemetry: &Telemetry) -> Option<Uplink> {
+        if telemetry.get_buffered() {
+            return None;
+        }
+        // Avionics without RemoteTech leave the link out
+        if !telemetry.has_comms() || !telemetry.get_comms().get_remote_tech() {
+            return Some(Uplink::Immediate);
+        }
+
+        let comms = telemetry.get_comms();
+        Some(if !comms.get_ground_station_link() {
+            Uplink::Down
+        } else if comms.get_signal_delay() > 0.0 {
+            Uplink::Delayed {
+                delay: Duration::from_secs_f64(comms.get_signal_delay()),
+                station: String::from(comms.get_ground_station()),
+            }
+        } else {
+            Uplink::Immediate
+        })
+    }
+
+    /// How long a command sent now takes to arrive. Commands are refused with NoContact rather
+    /// than sent into a blackout.
+    pub fn command_delay(&self) -> Result<Duration, KerbxError> {
+        match self {
+            Uplink::Immediate => Ok(Duration::from_secs(0)),
+            Uplink::Delayed { delay, .. } => Ok(*delay),
+            Uplink::Down => Err(KerbxError::NoContact),
+        }
+    }
+}
+
 pub struct PlanningServer {
     ip: String,
     port: String,
@@ -46,39 +94,117 @@ impl PlanningServer {
     }
 
     /// Creates a new instance of the Planning Server on the provided ip and port
-    pub async fn new(ip: String, port: String) -> Result<PlanningServer, Box<dyn Error>> {
+    pub async fn new(ip: String, port: String) -> Result<PlanningServer, KerbxError> {
         let listener = TcpListener::bind(format!("{}:{}", ip, port)).await?;
 
         Ok(PlanningServer { ip, port, listener })
     }
 
     /// Loop {} -- check all received packets, decode the sheats, and push them to a channel.
-    pub async fn recv_and_decode(server: PlanningServer, tx: Sender<Sheath>) {
+    /// Returns once every receiver has been dropped, or with an error if the link fails.
+    pub async fn recv_and_decode(
+        server: PlanningServer,
+        tx: Sender<Sheath>,
+    ) -> Result<(), KerbxError> {
         // Second Arg is a SocketAddr in case we wanted to implement an IP-based white list
-        let (stream, _) = server
-            .get_listener()
-            .accept()
-            .await
-            .expect("recv_and_decode TCPStream Error.");
+        let (stream, _) = server.get_listener().accept().await?;
 
         // The protobuf libraries methods do not work with non-blocking sockets and will not
         // accept raw Tokio sockets. This means we must convert the tokio stream to a stdio
         // library stream and then turn blocking on the socket so that it will wait for
         // a full message to arrive
-        let mut std_stream = stream
-            .into_std()
-            .expect("Error converting TCPStream to io stream.");
-        std_stream.set_nonblocking(false);
+        let mut std_stream = stream.into_std()?;
+        std_stream.set_nonblocking(false)?;
         let mut input = CodedInputStream::new(&mut std_stream);
 
         loop {
-            // TODO: Properly handle errors from read_message()
-            let message: Sheath = input
-                .read_message()
-                .expect("Error reading sheath from CodedInputStream");
+            let message: Sheath = input.read_message()?;
 
-            // Does not gurantee receivers will read the message on Ok()
-            tx.send(message).expect("Error sending sheath to channel.");
+            // Does not gurantee receivers will read the message on Ok(). An Err means nobody is
+            // listening anymore, so there is no one left to decode for.
+            if tx.send(message).is_err() {
+                return Ok(());
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telemetry(comms: Option<Comms>, buffered: bool) -> Telemetry {
+        let mut telemetry = Telemetry::new();
+        if let Some(comms) = comms {
+            telemetry.set_comms(comms);
+        }
+        telemetry.set_buffered(buffered);
+        telemetry
+    }
+
+    fn remote_tech(ground_station_link: bool, signal_delay: f64) -> Comms {
+        let mut comms = Comms::new();
+        comms.set_remote_tech(true);
+        comms.set_connected(ground_station_link);
+        comms.set_ground_station_link(ground_station_link);
+        comms.set_signal_delay(signal_delay);
+        comms.set_ground_station(String::from("Mission Control"));
+        comms
+    }
+
+    #[test]
+    fn buffered_telemetry_leaves_the_uplink_alone() {
+        let packet = telemetry(Some(remote_tech(false, 0.0)), true);
+        assert_eq!(Uplink::from_telemetry(&packet), None);
+    }
+
+    #[test]
+    fn uplink_is_immediate_without_remote_tech() {
+        assert_eq!(
+            Uplink::from_telemetry(&telemetry(None, false)),
+            Some(Uplink::Immediate)
+        );
+        let mut comms = Comms::new();
+        comms.set_remote_tech(false);
+        assert_eq!(
+            Uplink::from_telemetry(&telemetry(Some(comms), false)),
+            Some(Uplink::Immediate)
+        );
+        assert_eq!(
+            Uplink::Immediate.command_delay().unwrap(),
+            Duration::from_secs(0)
+        );
+    }
+
+    #[test]
+    fn uplink_is_down_without_a_ground_station() {
+        let uplink = Uplink::from_telemetry(&telemetry(Some(remote_tech(false, 0.0)), false));
+        assert_eq!(uplink, Some(Uplink::Down));
+        assert!(matches!(
+            uplink.unwrap().command_delay(),
+            Err(KerbxError::NoContact)
+        ));
+    }
+
+    #[test]
+    fn uplink_carries_the_signal_delay_and_station() {
+        let uplink = Uplink::from_telemetry(&telemetry(Some(remote_tech(true, 1.5)), false));
+        assert_eq!(
+            uplink,
+            Some(Uplink::Delayed {
+                delay: Duration::from_millis(1_500),
+                station: String::from("Mission Control"),
+            })
+        );
+        assert_eq!(
+            uplink.unwrap().command_delay().unwrap(),
+            Duration::from_millis(1_500)
+        );
+
+        // A link without delay is as good as no RemoteTech at all
+        assert_eq!(
+            Uplink::from_telemetry(&telemetry(Some(remote_tech(true, 0.0)), false)),
+            Some(Uplink::Immediate)
+        );
+    }
+}
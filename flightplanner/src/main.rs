@@ -33,7 +33,7 @@ use std::io::{stdout, Read, Write};
 use std::sync::Arc;
 use std::{error::Error, thread, time};
 
-use flightplanner::PlanningServer;
+use flightplanner::{PlanningServer, Uplink};
 use libkerbx::kerbx::{Sheath, Sheath_MessageType};
 use libkerbx::space_center::orbit_static_reference_plane_normal;
 use libkerbx::KerbxTransport;
@@ -42,6 +42,10 @@ const HORZ_BOUNDARY: &'static str = "─";
 const VERT_BOUNDARY: &'static str = "│";
 const WIN_TITLE: &'static str = "KerbX Flight Planner";
 const QUIT_MSG: &'static str = "Press 'q' to quit.";
+// Vacuum delta-v in m/s a typical ascent from the pad spends reaching a low Kerbin orbit
+const ORBIT_DELTA_V: f64 = 3_400.0;
+// Surface speed in m/s below which the craft is taken to be sitting on the pad
+const PAD_SPEED: f64 = 1.0;
 
 #[tokio::main]
 pub async fn main() -> Result<(), Box<dyn Error>> {
@@ -81,7 +85,7 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
         .get_matches();
 
     // Enter Raw Terminal mode for the app
-    let mut stdout = stdout().into_raw_mode().unwrap();
+    let mut stdout = stdout().into_raw_mode()?;
     let mut stdin = async_stdin().bytes();
 
     // Draw our border
@@ -111,7 +115,10 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
     // Create the communications channels between async processes
     let (tx, mut rx_gui) = broadcast::channel(32);
 
-    task::spawn(PlanningServer::recv_and_decode(plan_server, tx));
+    let receiver = task::spawn(PlanningServer::recv_and_decode(plan_server, tx));
+
+    // Commands are only as good as the link they go up on
+    let mut uplink = Uplink::Immediate;
 
     // Main loop for handling information from ksp
     loop {
@@ -129,12 +136,34 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
                         &mut stdout,
                         3,
                         10,
-                        format!("Watchdog sent at: {}.", watchdog.get_time().get_seconds())
-                            .as_str(),
+                        format!(
+                            "Watchdog sent at: {} ({:?}).",
+                            watchdog.get_time().get_seconds(),
+                            watchdog.get_status()
+                        )
+                        .as_str(),
                     )?;
                 }
                 Sheath_MessageType::TELEMETRY => {
                     let telemetry = message.get_telemetry();
+                    if let Some(status) = Uplink::from_telemetry(telemetry) {
+                        uplink = status;
+                    }
+                    mvaddstr(
+                        &mut stdout,
+                        3,
+                        17,
+                        format!(
+                            "Uplink: {:<40}",
+                            match &uplink {
+                                Uplink::Delayed { delay, station } =>
+                                    format!("DELAYED {:.1} s via {}", delay.as_secs_f64(), station),
+                                Uplink::Immediate => String::from("OK"),
+                                Uplink::Down => String::from("NO CONTACT"),
+                            }
+                        )
+                        .as_str(),
+                    )?;
                     mvaddstr(
                         &mut stdout,
                         3,
@@ -171,19 +200,103 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
                         8,
                         format!("Alt: {}", telemetry.get_alt()).as_str(),
                     )?;
-                }
-                Sheath_MessageType::EMPTY => {
+                    let orbit = telemetry.get_orbit();
                     mvaddstr(
                         &mut stdout,
                         3,
-                        11,
+                        9,
                         format!(
-                            "Empty Sheath Sent at {}",
-                            libkerbx::time().unwrap().get_seconds()
+                            "Ap: {} Pe: {}",
+                            orbit.get_apoapsis_altitude(),
+                            orbit.get_periapsis_altitude()
+                        )
+                        .as_str(),
+                    )?;
+                    // Rows 10 to 12 are taken by the watchdog and sheath messages
+                    let stage = telemetry.get_stage_resources();
+                    mvaddstr(
+                        &mut stdout,
+                        3,
+                        13,
+                        format!(
+                            "Stage LF: {:.0}/{:.0} Ox: {:.0}/{:.0} SF: {:.0}/{:.0}",
+                            stage.get_liquid_fuel().get_amount(),
+                            stage.get_liquid_fuel().get_max(),
+                            stage.get_oxidizer().get_amount(),
+                            stage.get_oxidizer().get_max(),
+                            stage.get_solid_fuel().get_amount(),
+                            stage.get_solid_fuel().get_max()
+                        )
+                        .as_str(),
+                    )?;
+                    let vessel = telemetry.get_vessel_resources();
+                    mvaddstr(
+                        &mut stdout,
+                        3,
+                        14,
+                        format!(
+                            "Vessel LF: {:.0}/{:.0} Ox: {:.0}/{:.0} MP: {:.0}/{:.0} EC: {:.0}/{:.0}",
+                            vessel.get_liquid_fuel().get_amount(),
+                            vessel.get_liquid_fuel().get_max(),
+                            vessel.get_oxidizer().get_amount(),
+                            vessel.get_oxidizer().get_max(),
+                            vessel.get_mono_propellant().get_amount(),
+                            vessel.get_mono_propellant().get_max(),
+                            vessel.get_electric_charge().get_amount(),
+                            vessel.get_electric_charge().get_max()
+                        )
+                        .as_str(),
+                    )?;
+                    let propulsion = telemetry.get_propulsion();
+                    mvaddstr(
+                        &mut stdout,
+                        3,
+                        15,
+                        format!(
+                            "Thrust: {:.0}/{:.0} kN TWR: {:.2}/{:.2} Mass: {:.0}/{:.0} kg",
+                            propulsion.get_thrust() / 1000.0,
+                            propulsion.get_available_thrust() / 1000.0,
+                            propulsion.get_twr(),
+                            propulsion.get_available_twr(),
+                            propulsion.get_mass(),
+                            propulsion.get_dry_mass()
+                        )
+                        .as_str(),
+                    )?;
+                    // ORBIT_DELTA_V covers the whole ascent, so the check only means anything
+                    // before liftoff. Padded so it is blanked out once the craft is moving.
+                    let pad_check = if telemetry.get_velocity() < PAD_SPEED {
+                        let shortfall = ORBIT_DELTA_V - propulsion.get_delta_v();
+                        if shortfall > 0.0 {
+                            format!(" Pad to orbit: SHORT {:.0} m/s", shortfall)
+                        } else {
+                            String::from(" Pad to orbit: GO")
+                        }
+                    } else {
+                        String::new()
+                    };
+                    mvaddstr(
+                        &mut stdout,
+                        3,
+                        16,
+                        format!(
+                            "dV stage: {:.0} total: {:.0} m/s{:<30}",
+                            propulsion.get_stage_delta_v(),
+                            propulsion.get_delta_v(),
+                            pad_check
                         )
                         .as_str(),
                     )?;
                 }
+                Sheath_MessageType::EMPTY => {
+                    mvaddstr(
+                        &mut stdout,
+                        3,
+                        11,
+                        format!("Empty Sheath Sent at {}", libkerbx::time()?.get_seconds())
+                            .as_str(),
+                    )?;
+                }
                 _ => {
                     mvaddstr(
                         &mut stdout,
@@ -191,7 +304,7 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
                         12,
                         format!(
                             "Unsupported Sheath Type Sent at {}",
-                            libkerbx::time().unwrap().get_seconds()
+                            libkerbx::time()?.get_seconds()
                         )
                         .as_str(),
                     )?;
@@ -206,25 +319,33 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
                 20,
                 format!(
                     "try_recv error at {}",
-                    libkerbx::time().unwrap().get_seconds()
+                    libkerbx::time()?.get_seconds()
                 )
                 .as_str(),
             )?;
              */
         }
-        stdout.flush();
+        stdout.flush()?;
+
+        // The receiver only stops while we are listening if the link to the avionics failed
+        if receiver.is_finished() {
+            write!(stdout, "{}", clear::All)?;
+            drop(stdout);
+            receiver.await??;
+            return Ok(());
+        }
         //thread::sleep(time::Duration::from_millis(100));
     }
 
     // Cleanup after ourselves
-    write!(stdout, "{}", clear::All).unwrap();
+    write!(stdout, "{}", clear::All)?;
 
     Ok(())
 }
 
 fn draw_window<W: Write>(term: &mut RawTerminal<W>) -> Result<(), std::io::Error> {
     // Clear the window
-    write!(term, "{}", clear::All).unwrap();
+    write!(term, "{}", clear::All)?;
 
     // Get window size
     let (cols, rows) = terminal_size()?;
@@ -0,0 +1,198 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! The three clocks a Time can be read from: the wall clock, KSP's Universal Time and the
+//! vessel's mission elapsed time. Time warp and pausing the game stretch game time against the
+//! wall clock, so a GameClock reads all three at once and converts between them through UT.
+
+use crate::kerbx::{Time, Time_Base};
+use crate::vehicle::VehicleSensors;
+use crate::{space_center, KerbxError, KerbxTransport};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: f64 = 1_000_000_000.0;
+
+/// The three clocks read at the same moment, in seconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GameClock {
+    /// Wall clock, since the UNIX epoch. Kept whole so wall clock times keep their nanoseconds.
+    pub wall: Duration,
+    /// KSP Universal Time
+    pub ut: f64,
+    /// Mission elapsed time, 0 until the vessel launches
+    pub met: f64,
+}
+
+impl GameClock {
+    /// Reads the wall clock alongside game time from the vehicle
+    pub fn new(ut: f64, met: f64) -> Result<GameClock, KerbxError> {
+        Ok(GameClock {
+            wall: SystemTime::now().duration_since(UNIX_EPOCH)?,
+            ut,
+            met,
+        })
+    }
+
+    /// Universal Time at which time falls. Wall clock times are carried over at the rate time
+    /// is passing right now, so they drift once warp changes.
+    pub fn to_ut(&self, time: &Time) -> f64 {
+        match time.get_base() {
+            Time_Base::WALLCLOCK => {
+                // Differences are taken before going to seconds, which would round off the
+                // nanoseconds of a time since the epoch
+                let at = duration(time);
+                let from_now = if at >= self.wall {
+                    (at - self.wall).as_secs_f64()
+                } else {
+                    -(self.wall - at).as_secs_f64()
+                };
+                self.ut + from_now
+            }
+            Time_Base::UNIVERSAL => seconds(time),
+            Time_Base::MISSION => self.ut - self.met + seconds(time),
+        }
+    }
+
+    /// Time in base at which a Universal Time falls
+    pub fn at_ut(&self, ut: f64, base: Time_Base) -> Time {
+        match base {
+            Time_Base::WALLCLOCK => {
+                let from_now = ut - self.ut;
+                let at = if from_now >= 0.0 {
+                    self.wall + Duration::from_secs_f64(from_now)
+                } else {
+                    self.wall
+                        .checked_sub(Duration::from_secs_f64(-from_now))
+                        .unwrap_or_default()
+                };
+                from_duration(at, base)
+            }
+            Time_Base::UNIVERSAL => from_seconds(ut, base),
+            Time_Base::MISSION => from_seconds(self.met + (ut - self.ut), base),
+        }
+    }
+
+    /// Time in another base at which time falls
+    pub fn convert(&self, time: &Time, base: Time_Base) -> Time {
+        self.at_ut(self.to_ut(time), base)
+    }
+
+    /// The moment the clocks were read, in base
+    pub fn now(&self, base: Time_Base) -> Time {
+        self.at_ut(self.ut, base)
+    }
+
+    /// Whether time has come, judged in game time
+    pub fn reached(&self, time: &Time) -> bool {
+        self.to_ut(time) <= self.ut
+    }
+}
+
+/// Seconds of time since the start of its base, nanoseconds included
+pub fn seconds(time: &Time) -> f64 {
+    time.get_seconds() as f64 + f64::from(time.get_nanos()) / NANOS_PER_SECOND
+}
+
+/// Time since the start of its base
+pub fn duration(time: &Time) -> Duration {
+    Duration::new(time.get_seconds(), time.get_nanos())
+}
+
+/// Time at seconds since the start of base. Times before the start of the base become its
+/// start.
+pub fn from_seconds(seconds: f64, base: Time_Base) -> Time {
+    from_duration(Duration::from_secs_f64(seconds.max(0.0)), base)
+}
+
+/// Time at a duration since the start of base
+pub fn from_duration(since: Duration, base: Time_Base) -> Time {
+    let mut time = Time::new();
+    time.set_seconds(since.as_secs());
+    time.set_nanos(since.subsec_nanos());
+    time.set_base(base);
+    time
+}
+
+impl KerbxTransport {
+    /// KSP Universal Time in seconds
+    pub fn ut(&self) -> Result<f64, KerbxError> {
+        self.sim_feed.mk_call(&space_center::get_ut())
+    }
+
+    /// Seconds since the vessel being flown launched
+    pub fn met(&self) -> Result<f64, KerbxError> {
+        self.sim_feed.mk_call(&self.vessel().get_met())
+    }
+
+    /// Time in another base at which time falls, as of now
+    pub fn convert_time(&self, time: &Time, base: Time_Base) -> Result<Time, KerbxError> {
+        Ok(self.get_clock()?.convert(time, base))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_between_bases_through_ut() {
+        let clock = GameClock {
+            wall: Duration::from_secs(1_600_000_000),
+            ut: 5_000.0,
+            met: 120.0,
+        };
+
+        let launch = from_seconds(0.0, Time_Base::MISSION);
+        assert_eq!(clock.to_ut(&launch), 4_880.0);
+        let later = clock.convert(
+            &from_seconds(5_030.5, Time_Base::UNIVERSAL),
+            Time_Base::MISSION,
+        );
+        assert_eq!(later.get_seconds(), 150);
+        assert_eq!(later.get_nanos(), 500_000_000);
+        let wall = clock.convert(&later, Time_Base::WALLCLOCK);
+        assert_eq!(wall.get_seconds(), 1_600_000_030);
+        assert_eq!(wall.get_nanos(), 500_000_000);
+
+        assert!(clock.reached(&launch));
+        assert!(!clock.reached(&later));
+        assert_eq!(seconds(&clock.now(Time_Base::MISSION)), 120.0);
+    }
+
+    #[test]
+    fn wall_clock_times_keep_their_nanoseconds() {
+        let clock = GameClock {
+            wall: Duration::new(1_600_000_000, 123),
+            ut: 5_000.0,
+            met: 0.0,
+        };
+
+        let due = from_duration(Duration::new(1_600_000_000, 124), Time_Base::WALLCLOCK);
+        assert!(!clock.reached(&due));
+        let past = from_duration(Duration::new(1_600_000_000, 122), Time_Base::WALLCLOCK);
+        assert!(clock.reached(&past));
+
+        let now = clock.now(Time_Base::WALLCLOCK);
+        assert_eq!((now.get_seconds(), now.get_nanos()), (1_600_000_000, 123));
+    }
+}
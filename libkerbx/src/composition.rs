@@ -0,0 +1,425 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Structure of a vessel as seen by the staging system. KSP counts stages down: the highest
+//! numbered stage fires first and stage 0 fires last.
+
+/// Resources burnt by engines or RCS. Any part holding one of these counts as a fuel tank.
+pub const FUEL_RESOURCES: [&str; 5] = [
+    "LiquidFuel",
+    "Oxidizer",
+    "SolidFuel",
+    "MonoPropellant",
+    "XenonGas",
+];
+
+/// Standard gravity in m/s^2. KSP uses it to turn specific impulse into exhaust velocity.
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
+/// Mass in kg of one unit of a resource, or 0 for massless resources such as ElectricCharge.
+pub fn resource_density(name: &str) -> f64 {
+    match name {
+        "LiquidFuel" | "Oxidizer" => 5.0,
+        "SolidFuel" => 7.5,
+        "MonoPropellant" => 4.0,
+        "XenonGas" => 0.1,
+        _ => 0.0,
+    }
+}
+
+/// A single part of the vessel.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PartInfo {
+    /// Internal name, e.g. "liquidEngine2.v2"
+    pub name: String,
+    /// Name shown in game, e.g. "LV-T45 \"Swivel\" Liquid Fuel Engine"
+    pub title: String,
+    /// Stage that activates the part, or -1 if staging never activates it
+    pub stage: i32,
+    /// Stage that separates the part from the vessel, or -1 if it is never separated
+    pub decouple_stage: i32,
+    /// Mass in kg including resources
+    pub mass: f64,
+    /// Mass in kg without resources
+    pub dry_mass: f64,
+}
+
+/// An engine and its performance at full throttle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EngineInfo {
+    pub part: PartInfo,
+    /// Thrust in N at the current atmospheric pressure
+    pub max_thrust: f64,
+    /// Thrust in N in vacuum
+    pub max_vacuum_thrust: f64,
+    /// Specific impulse in seconds at Kerbin sea level
+    pub sea_level_specific_impulse: f64,
+    /// Specific impulse in seconds in vacuum
+    pub vacuum_specific_impulse: f64,
+    /// Resources the engine burns, e.g. ["LiquidFuel", "Oxidizer"]
+    pub propellants: Vec<String>,
+}
+
+/// Amount of a single resource held by a part.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceAmount {
+    pub name: String,
+    /// Units currently held
+    pub amount: f64,
+    /// Units the part can hold
+    pub max: f64,
+}
+
+/// A part holding fuel. Only the resources in FUEL_RESOURCES are listed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TankInfo {
+    pub part: PartInfo,
+    pub resources: Vec<ResourceAmount>,
+}
+
+/// Everything tied to one stage. Parts that are activated by staging (engines, decouplers and
+/// parachutes) are listed under the stage that activates them. Parts and tanks are listed under
+/// the stage that separates them, which is what the vessel carries until that stage fires.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StageComposition {
+    /// Stage number, or -1 for the parts that are never separated
+    pub stage: i32,
+    /// Parts separated by this stage
+    pub parts: Vec<PartInfo>,
+    /// Engines ignited by this stage
+    pub engines: Vec<EngineInfo>,
+    /// Decouplers fired by this stage
+    pub decouplers: Vec<PartInfo>,
+    /// Parachutes deployed by this stage
+    pub parachutes: Vec<PartInfo>,
+    /// Fuel tanks separated by this stage
+    pub tanks: Vec<TankInfo>,
+}
+
+/// What a stage can do once it starts burning, assuming every engine burning in it runs until
+/// the tanks the next stage drops are empty. Figures are for vacuum.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StageDeltaV {
+    pub stage: i32,
+    /// Mass in kg when the stage starts burning
+    pub start_mass: f64,
+    /// Mass in kg once its propellant is spent
+    pub end_mass: f64,
+    /// Combined thrust in N of the engines burning in the stage
+    pub vacuum_thrust: f64,
+    /// Combined specific impulse in s of the engines burning in the stage
+    pub vacuum_specific_impulse: f64,
+    /// Resources the engines burning in the stage use
+    pub propellants: Vec<String>,
+    /// Change in velocity in m/s from the rocket equation
+    pub delta_v: f64,
+}
+
+impl StageDeltaV {
+    /// Recomputes the delta-v for different masses, e.g. as propellant is burnt.
+    pub fn with_masses(&self, start_mass: f64, end_mass: f64) -> StageDeltaV {
+        StageDeltaV {
+            start_mass,
+            end_mass,
+            delta_v: rocket_equation(self.vacuum_specific_impulse, start_mass, end_mass),
+            ..self.clone()
+        }
+    }
+
+    /// Seconds the stage can burn at full throttle
+    pub fn burn_time(&self) -> f64 {
+        if self.vacuum_thrust <= 0.0 {
+            return 0.0;
+        }
+        let flow = self.vacuum_thrust / (self.vacuum_specific_impulse * STANDARD_GRAVITY);
+        (self.start_mass - self.end_mass) / flow
+    }
+}
+
+/// Tsiolkovsky rocket equation. Returns the change in velocity in m/s.
+/// specific_impulse: Specific impulse in s
+/// start_mass, end_mass: Mass before and after the burn, in any unit
+pub fn rocket_equation(specific_impulse: f64, start_mass: f64, end_mass: f64) -> f64 {
+    if specific_impulse <= 0.0 || end_mass <= 0.0 || start_mass <= end_mass {
+        return 0.0;
+    }
+    specific_impulse * STANDARD_GRAVITY * (start_mass / end_mass).ln()
+}
+
+/// The vessel broken down by stage.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VesselComposition {
+    /// Stages in the order they fire, highest number first, ending with stage -1 if any part
+    /// is never separated. Only stages with something in them are listed.
+    pub stages: Vec<StageComposition>,
+}
+
+impl VesselComposition {
+    /// Sorts the parts of a vessel into stages.
+    /// parts: Every part of the vessel
+    /// engines, decouplers, parachutes, tanks: The parts of each kind, which also appear in parts
+    pub fn new(
+        parts: Vec<PartInfo>,
+        engines: Vec<EngineInfo>,
+        decouplers: Vec<PartInfo>,
+        parachutes: Vec<PartInfo>,
+        tanks: Vec<TankInfo>,
+    ) -> VesselComposition {
+        let mut composition = VesselComposition::default();
+        for part in parts {
+            composition.stage_mut(part.decouple_stage).parts.push(part);
+        }
+        for engine in engines {
+            composition
+                .stage_mut(engine.part.stage)
+                .engines
+                .push(engine);
+        }
+        for decoupler in decouplers {
+            composition
+                .stage_mut(decoupler.stage)
+                .decouplers
+                .push(decoupler);
+        }
+        for parachute in parachutes {
+            composition
+                .stage_mut(parachute.stage)
+                .parachutes
+                .push(parachute);
+        }
+        for tank in tanks {
+            composition
+                .stage_mut(tank.part.decouple_stage)
+                .tanks
+                .push(tank);
+        }
+        composition
+    }
+
+    /// Returns the given stage, or None if nothing is tied to it.
+    pub fn stage(&self, stage: i32) -> Option<&StageComposition> {
+        self.stages
+            .iter()
+            .find(|composition| composition.stage == stage)
+    }
+
+    /// Delta-v of the stage currently burning and every stage after it, in firing order.
+    /// current_stage: Stage of the craft as the in-game UI shows it
+    pub fn delta_v(&self, current_stage: i32) -> Vec<StageDeltaV> {
+        (0..=current_stage)
+            .rev()
+            .map(|stage| self.stage_delta_v(stage))
+            .collect()
+    }
+
+    /// Delta-v of a single stage. The engines burning are those already ignited and not yet
+    /// dropped; they burn what is in the tanks the next stage drops.
+    fn stage_delta_v(&self, stage: i32) -> StageDeltaV {
+        let engines: Vec<&EngineInfo> = self
+            .stages
+            .iter()
+            .flat_map(|composition| &composition.engines)
+            .filter(|engine| engine.part.stage >= stage && engine.part.decouple_stage < stage)
+            .collect();
+
+        let vacuum_thrust: f64 = engines.iter().map(|engine| engine.max_vacuum_thrust).sum();
+        let flow: f64 = engines
+            .iter()
+            .filter(|engine| engine.vacuum_specific_impulse > 0.0)
+            .map(|engine| engine.max_vacuum_thrust / engine.vacuum_specific_impulse)
+            .sum();
+        let vacuum_specific_impulse = if flow > 0.0 {
+            vacuum_thrust / flow
+        } else {
+            0.0
+        };
+
+        let mut propellants: Vec<String> = Vec::new();
+        for name in engines.iter().flat_map(|engine| &engine.propellants) {
+            if !propellants.contains(name) {
+                propellants.push(name.clone());
+            }
+        }
+
+        let start_mass: f64 = self
+            .stages
+            .iter()
+            .filter(|composition| composition.stage < stage)
+            .flat_map(|composition| &composition.parts)
+            .map(|part| part.mass)
+            .sum();
+        let propellant_mass: f64 = self.stage(stage - 1).map_or(0.0, |dropped| {
+            dropped
+                .tanks
+                .iter()
+                .flat_map(|tank| &tank.resources)
+                .filter(|resource| propellants.contains(&resource.name))
+                .map(|resource| resource.amount * resource_density(&resource.name))
+                .sum()
+        });
+
+        StageDeltaV {
+            stage,
+            vacuum_thrust,
+            vacuum_specific_impulse,
+            propellants,
+            ..Default::default()
+        }
+        .with_masses(start_mass, start_mass - propellant_mass)
+    }
+
+    /// Finds the given stage, adding it in firing order if it is not listed yet.
+    fn stage_mut(&mut self, stage: i32) -> &mut StageComposition {
+        let index = match self
+            .stages
+            .binary_search_by(|composition| stage.cmp(&composition.stage))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.stages.insert(
+                    index,
+                    StageComposition {
+                        stage,
+                        ..Default::default()
+                    },
+                );
+                index
+            }
+        };
+        &mut self.stages[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(name: &str, stage: i32, decouple_stage: i32) -> PartInfo {
+        PartInfo {
+            name: String::from(name),
+            stage,
+            decouple_stage,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parts_are_sorted_into_stages_in_firing_order() {
+        let pod = part("pod", -1, -1);
+        let chute = part("chute", 0, -1);
+        let decoupler = part("decoupler", 1, 1);
+        let tank = part("tank", -1, 1);
+        let engine = part("engine", 2, 1);
+
+        let composition = VesselComposition::new(
+            vec![
+                pod.clone(),
+                chute.clone(),
+                decoupler.clone(),
+                tank.clone(),
+                engine.clone(),
+            ],
+            vec![EngineInfo {
+                part: engine.clone(),
+                ..Default::default()
+            }],
+            vec![decoupler.clone()],
+            vec![chute.clone()],
+            vec![TankInfo {
+                part: tank.clone(),
+                ..Default::default()
+            }],
+        );
+
+        let stages: Vec<i32> = composition.stages.iter().map(|s| s.stage).collect();
+        assert_eq!(stages, vec![2, 1, 0, -1]);
+
+        assert_eq!(composition.stage(2).unwrap().engines[0].part, engine);
+        assert!(composition.stage(2).unwrap().parts.is_empty());
+        assert_eq!(
+            composition.stage(1).unwrap().decouplers,
+            vec![decoupler.clone()]
+        );
+        assert_eq!(
+            composition.stage(1).unwrap().parts,
+            vec![decoupler, tank.clone(), engine]
+        );
+        assert_eq!(composition.stage(1).unwrap().tanks[0].part, tank);
+        assert_eq!(
+            composition.stage(0).unwrap().parachutes,
+            vec![chute.clone()]
+        );
+        assert_eq!(composition.stage(-1).unwrap().parts, vec![pod, chute]);
+        assert!(composition.stage(3).is_none());
+    }
+
+    #[test]
+    fn delta_v_of_each_stage() {
+        let pod = PartInfo {
+            mass: 1_000.0,
+            ..part("pod", -1, -1)
+        };
+        let tank = PartInfo {
+            mass: 5_000.0,
+            ..part("tank", -1, 1)
+        };
+        let engine = EngineInfo {
+            part: PartInfo {
+                mass: 1_500.0,
+                ..part("engine", 2, 1)
+            },
+            max_vacuum_thrust: 200_000.0,
+            vacuum_specific_impulse: 300.0,
+            propellants: vec![String::from("LiquidFuel"), String::from("Oxidizer")],
+            ..Default::default()
+        };
+        let fuel = |name: &str, amount: f64| ResourceAmount {
+            name: String::from(name),
+            amount,
+            max: amount,
+        };
+        let composition = VesselComposition::new(
+            vec![pod, tank.clone(), engine.part.clone()],
+            vec![engine],
+            vec![],
+            vec![],
+            vec![TankInfo {
+                part: tank,
+                resources: vec![fuel("LiquidFuel", 405.0), fuel("Oxidizer", 495.0)],
+            }],
+        );
+
+        let delta_v = composition.delta_v(2);
+        let stages: Vec<i32> = delta_v.iter().map(|stage| stage.stage).collect();
+        assert_eq!(stages, vec![2, 1, 0]);
+
+        let first = &delta_v[0];
+        assert_eq!((first.start_mass, first.end_mass), (7_500.0, 3_000.0));
+        assert!((first.delta_v - 300.0 * STANDARD_GRAVITY * 2.5_f64.ln()).abs() < 1e-9);
+        assert!((first.burn_time() - 4_500.0 * 300.0 * STANDARD_GRAVITY / 200_000.0).abs() < 1e-9);
+
+        // Nothing is left to burn once the engine is dropped
+        assert_eq!(delta_v[1].delta_v, 0.0);
+        assert_eq!(delta_v[2].delta_v, 0.0);
+    }
+}
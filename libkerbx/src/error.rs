@@ -0,0 +1,126 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+use std::fmt;
+
+/// Every error KerbX can run into. Functions across the workspace return this rather than
+/// panicking so the flight software decides how to handle a failure.
+#[derive(Debug)]
+pub enum KerbxError {
+    /// A kRPC call or stream failed
+    Rpc(krpc_mars::error::Error),
+    /// The connection to the kRPC server dropped and has not been re-established yet
+    LinkDown,
+    /// The vessel a transport was asked to fly is not in the game
+    VesselNotFound(String),
+    /// A KSP mod the call relies on is not installed, e.g. Kerbal Alarm Clock
+    ModUnavailable(String),
+    /// The vehicle is out of radio contact with mission control, so a command would not reach it
+    NoContact,
+    /// A file or socket could not be read or written
+    Io(std::io::Error),
+    /// A flight plan could not be parsed or serialized
+    Plan(serde_json::Error),
+    /// A flight plan is not safe to fly
+    Validation(String),
+    /// A KerbX protocol message could not be encoded or decoded
+    Protocol(protobuf::ProtobufError),
+    /// A function was called when its precondition did not hold, e.g. a state transition out of
+    /// order
+    Contract(String),
+    /// The system clock is set before the UNIX epoch
+    Clock(std::time::SystemTimeError),
+}
+
+impl fmt::Display for KerbxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KerbxError::Rpc(error) => write!(f, "kRPC error: {:?}", error),
+            KerbxError::LinkDown => write!(f, "kRPC link is down"),
+            KerbxError::VesselNotFound(which) => write!(f, "No vessel {}", which),
+            KerbxError::ModUnavailable(name) => write!(f, "{} is not installed", name),
+            KerbxError::NoContact => write!(f, "Vehicle is out of radio contact"),
+            KerbxError::Io(error) => write!(f, "I/O error: {}", error),
+            KerbxError::Plan(error) => write!(f, "Flight plan error: {}", error),
+            KerbxError::Validation(message) => write!(f, "Invalid flight plan: {}", message),
+            KerbxError::Protocol(error) => write!(f, "Protocol error: {}", error),
+            KerbxError::Contract(message) => write!(f, "Contract violation: {}", message),
+            KerbxError::Clock(error) => write!(f, "Clock error: {}", error),
+        }
+    }
+}
+
+impl KerbxError {
+    /// True if the error means the connection to the kRPC server was lost, rather than a single
+    /// call failing
+    pub fn is_link_failure(&self) -> bool {
+        matches!(
+            self,
+            KerbxError::LinkDown
+                | KerbxError::Rpc(krpc_mars::error::Error::Io(_))
+                | KerbxError::Rpc(krpc_mars::error::Error::Protobuf(_))
+        )
+    }
+}
+
+impl std::error::Error for KerbxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KerbxError::Io(error) => Some(error),
+            KerbxError::Plan(error) => Some(error),
+            KerbxError::Protocol(error) => Some(error),
+            KerbxError::Clock(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<krpc_mars::error::Error> for KerbxError {
+    fn from(error: krpc_mars::error::Error) -> Self {
+        KerbxError::Rpc(error)
+    }
+}
+
+impl From<std::io::Error> for KerbxError {
+    fn from(error: std::io::Error) -> Self {
+        KerbxError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for KerbxError {
+    fn from(error: serde_json::Error) -> Self {
+        KerbxError::Plan(error)
+    }
+}
+
+impl From<protobuf::ProtobufError> for KerbxError {
+    fn from(error: protobuf::ProtobufError) -> Self {
+        KerbxError::Protocol(error)
+    }
+}
+
+impl From<std::time::SystemTimeError> for KerbxError {
+    fn from(error: std::time::SystemTimeError) -> Self {
+        KerbxError::Clock(error)
+    }
+}
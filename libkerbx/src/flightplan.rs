@@ -21,34 +21,60 @@
 * =================================================================================================
 */
 
+use crate::clock;
 use crate::kerbx::*;
+use crate::KerbxError;
 use protobuf::RepeatedField;
-use serde::{Deserialize, Serialize};
-use serde_json::{Result, Value};
+use serde_json::{json, Value};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 
-//TODO: Handling with proper custom Error type
-pub fn load_from_string(flightplan: &str) -> FlightPlan {
-    serde_json::from_str(flightplan).expect("Error loading flight plan.")
+/// Parses a flight plan from its JSON form
+pub fn load_from_string(flightplan: &str) -> Result<FlightPlan, KerbxError> {
+    upgrade(serde_json::from_str(flightplan)?)
 }
 
-//TODO: Handling with proper custom Error type
 /// Loads a flight plan from a specified file
 /// filename: Full canonical path to the flightplan to load
-pub fn load_from_file(filename: &str) -> FlightPlan {
-    let mut file = File::open(filename).expect("Could not open flight plan file.");
+pub fn load_from_file(filename: &str) -> Result<FlightPlan, KerbxError> {
+    let file = File::open(filename)?;
 
-    serde_json::from_reader(BufReader::new(file)).expect("Error loading flight plan from file.")
+    upgrade(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Fills in what plans saved by older versions leave out before reading the plan. Time triggers
+/// written before times had nanoseconds and a base were whole seconds of wall clock time, and
+/// throttle steps written before a TWR could be held set the throttle itself.
+fn upgrade(mut plan: Value) -> Result<FlightPlan, KerbxError> {
+    if let Some(steps) = plan.get_mut("steps").and_then(Value::as_array_mut) {
+        for step in steps {
+            let time = step
+                .pointer_mut("/trigger/trigger_condition/time")
+                .and_then(Value::as_object_mut);
+            if let Some(time) = time {
+                time.entry("nanos").or_insert_with(|| json!(0));
+                time.entry("base").or_insert_with(|| json!("WALLCLOCK"));
+            }
+            let throttle = step
+                .pointer_mut("/action/throttle")
+                .and_then(Value::as_object_mut);
+            if let Some(throttle) = throttle {
+                throttle.entry("twr").or_insert_with(|| json!(0.0));
+            }
+        }
+    }
+    Ok(serde_json::from_value(plan)?)
 }
 
 /// Writes a flight plan from memory to specified file.
 /// filename: File to write to.
 /// &plan: Reference to flight plan object in memory
-pub fn write_to_file(filename: &str, plan: &FlightPlan) {
-    let mut file = File::create(filename).expect("Could not open flight plan file.");
+pub fn write_to_file(filename: &str, plan: &FlightPlan) -> Result<(), KerbxError> {
+    let mut writer = BufWriter::new(File::create(filename)?);
 
-    serde_json::to_writer(BufWriter::new(file), &plan);
+    serde_json::to_writer(&mut writer, &plan)?;
+    writer.flush()?;
+    Ok(())
 }
 
 /// Generates a step that tells the craft to change the throttle level to a certain value between
@@ -74,6 +100,23 @@ pub fn gen_throttle_step(count: u32, throttle_level: f32, trigger: Trigger) -> S
     step
 }
 
+/// Generates a step that tells the craft to hold its throttle at whatever gives the wanted
+/// thrust-to-weight ratio. The throttle follows the craft as it burns off mass, and is released
+/// by the next throttle step.
+pub fn gen_twr_step(count: u32, twr: f32, trigger: Trigger) -> Step {
+    let mut step = Step::new();
+    step.set_field_type(Step_ActionType::THROTTLELEVEL);
+
+    let mut throttle = ThrottleLevel::new();
+    throttle.set_twr(twr.max(0.0));
+
+    step.set_count(count);
+    step.set_throttle(throttle);
+    step.set_trigger(trigger);
+
+    step
+}
+
 /// Generates a step that tells the craft to reorient to a certain roll, pitch, and yaw.
 pub fn gen_reorient_step(count: u32, roll: f32, pitch: f32, yaw: f32, trigger: Trigger) -> Step {
     let mut step = Step::new();
@@ -91,18 +134,185 @@ pub fn gen_reorient_step(count: u32, roll: f32, pitch: f32, yaw: f32, trigger: T
     step
 }
 
-/// Generates one of the other step types (COAST, IGNITE, NEXTSTAGE). These do not have associated
-/// arguments and thus can all be set with the same function
-pub fn gen_other_step(count: u32, actiontype: Step_ActionType, trigger: Trigger) -> Step {
+/// Generates a step that turns the autopilot off and holds the craft along prograde or
+/// retrograde with SAS, measured against the surface or orbital velocity.
+pub fn gen_hold_step(
+    count: u32,
+    direction: Hold_Direction,
+    speed_mode: Hold_SpeedMode,
+    trigger: Trigger,
+) -> Step {
     let mut step = Step::new();
-    if let Step_ActionType::REORIENT | Step_ActionType::IGNITE | Step_ActionType::COAST = actiontype
+    step.set_field_type(Step_ActionType::HOLD);
+
+    let mut hold = Hold::new();
+    hold.set_direction(direction);
+    hold.set_speed_mode(speed_mode);
+
+    step.set_count(count);
+    step.set_hold(hold);
+    step.set_trigger(trigger);
+
+    step
+}
+
+/// Generates a step that time warps until the avionics drops out ahead of the next step.
+/// factor: 1 to 7 on rails, 1 to 3 for physics warp; 0 ends any warp
+pub fn gen_warp_step(count: u32, mode: Warp_Mode, factor: u32, trigger: Trigger) -> Step {
+    let mut step = Step::new();
+    step.set_field_type(Step_ActionType::WARP);
+
+    let mut warp = Warp::new();
+    warp.set_mode(mode);
+    warp.set_factor(factor);
+
+    step.set_count(count);
+    step.set_warp(warp);
+    step.set_trigger(trigger);
+
+    step
+}
+
+/// Generates one of the other step types (COAST, IGNITE, NEXTSTAGE, ABORT, DEPLOYPARACHUTES,
+/// EXECUTENODE). These do not have associated arguments and thus can all be set with the same
+/// function
+pub fn gen_other_step(
+    count: u32,
+    actiontype: Step_ActionType,
+    trigger: Trigger,
+) -> Result<Step, KerbxError> {
+    let mut step = Step::new();
+    if let Step_ActionType::NEXTSTAGE
+    | Step_ActionType::IGNITE
+    | Step_ActionType::COAST
+    | Step_ActionType::ABORT
+    | Step_ActionType::DEPLOYPARACHUTES
+    | Step_ActionType::EXECUTENODE = actiontype
     {
         step.set_count(count);
         step.set_field_type(actiontype);
         step.set_trigger(trigger);
     } else {
-        panic!("Flightplan Compilation Error. THROTTLELEVEL or REORIENT used with gen_other_step.")
+        return Err(KerbxError::Validation(String::from(
+            "Action with arguments used with gen_other_step.",
+        )));
     }
+    Ok(step)
+}
+
+/// Generates a step that switches one of the on/off part groups (GEAR, BRAKES, LIGHTS,
+/// SOLARPANELS, ANTENNAS) on or off.
+pub fn gen_toggle_step(
+    count: u32,
+    actiontype: Step_ActionType,
+    on: bool,
+    trigger: Trigger,
+) -> Result<Step, KerbxError> {
+    let mut step = Step::new();
+    if let Step_ActionType::GEAR
+    | Step_ActionType::BRAKES
+    | Step_ActionType::LIGHTS
+    | Step_ActionType::SOLARPANELS
+    | Step_ActionType::ANTENNAS = actiontype
+    {
+        let mut toggle = Toggle::new();
+        toggle.set_on(on);
+
+        step.set_count(count);
+        step.set_field_type(actiontype);
+        step.set_toggle(toggle);
+        step.set_trigger(trigger);
+    } else {
+        return Err(KerbxError::Validation(String::from(
+            "Action without an on/off setting used with gen_toggle_step.",
+        )));
+    }
+    Ok(step)
+}
+
+/// Generates a step that sets custom action group 1 to 10.
+pub fn gen_action_group_step(count: u32, group: u32, on: bool, trigger: Trigger) -> Step {
+    let mut step = Step::new();
+    step.set_field_type(Step_ActionType::ACTIONGROUP);
+
+    let mut action_group = ActionGroup::new();
+    action_group.set_group(group);
+    action_group.set_on(on);
+
+    step.set_count(count);
+    step.set_action_group(action_group);
+    step.set_trigger(trigger);
+
+    step
+}
+
+/// Generates a step that arms the parachutes to open by themselves below deploy_altitude in m,
+/// once the outside pressure is at least min_pressure in atm.
+pub fn gen_parachute_step(
+    count: u32,
+    deploy_altitude: f32,
+    min_pressure: f32,
+    trigger: Trigger,
+) -> Step {
+    let mut step = Step::new();
+    step.set_field_type(Step_ActionType::ARMPARACHUTES);
+
+    let mut parachutes = ParachuteSettings::new();
+    parachutes.set_deploy_altitude(deploy_altitude);
+    parachutes.set_min_pressure(min_pressure);
+
+    step.set_count(count);
+    step.set_parachutes(parachutes);
+    step.set_trigger(trigger);
+
+    step
+}
+
+/// Generates a step that moves the Infernal Robotics servo with the given name to position, at
+/// speed times its configured speed.
+pub fn gen_servo_step(
+    count: u32,
+    servo: &str,
+    position: f32,
+    speed: f32,
+    trigger: Trigger,
+) -> Step {
+    let mut servo_move = ServoMove::new();
+    servo_move.set_servo(String::from(servo));
+    gen_servo_move_step(count, servo_move, position, speed, trigger)
+}
+
+/// Generates a step that moves every servo of the Infernal Robotics servo group with the given
+/// name to position, at speed times their configured speed.
+pub fn gen_servo_group_step(
+    count: u32,
+    group: &str,
+    position: f32,
+    speed: f32,
+    trigger: Trigger,
+) -> Step {
+    let mut servo_move = ServoMove::new();
+    servo_move.set_group(String::from(group));
+    gen_servo_move_step(count, servo_move, position, speed, trigger)
+}
+
+fn gen_servo_move_step(
+    count: u32,
+    mut servo_move: ServoMove,
+    position: f32,
+    speed: f32,
+    trigger: Trigger,
+) -> Step {
+    let mut step = Step::new();
+    step.set_field_type(Step_ActionType::SERVO);
+
+    servo_move.set_position(position);
+    servo_move.set_speed(speed);
+
+    step.set_count(count);
+    step.set_servo(servo_move);
+    step.set_trigger(trigger);
+
     step
 }
 
@@ -120,6 +330,22 @@ pub fn gen_time_trigger(seconds_after_epoch: u64) -> Trigger {
     trigger
 }
 
+/// Generates a trigger that will occur when KSP's Universal Time reaches ut seconds. Time warp
+/// brings it closer; pausing the game holds it off.
+pub fn gen_ut_trigger(ut: f64) -> Trigger {
+    let mut trigger = Trigger::new();
+    trigger.set_time(clock::from_seconds(ut, Time_Base::UNIVERSAL));
+    trigger
+}
+
+/// Generates a trigger that will occur seconds of mission elapsed time after launch, counted in
+/// game time.
+pub fn gen_met_trigger(seconds: f64) -> Trigger {
+    let mut trigger = Trigger::new();
+    trigger.set_time(clock::from_seconds(seconds, Time_Base::MISSION));
+    trigger
+}
+
 /// Generates a trigger that will occur when the craft reaches a certain altitude on kerbin.
 /// altitude: distance in km to trigger.
 pub fn gen_alt_trigger(altitude: f64) -> Trigger {
@@ -142,6 +368,30 @@ pub fn gen_pos_trigger(lat: f64, lon: f64) -> Trigger {
     trigger
 }
 
+/// Generates a trigger that will occur once the apoapsis has risen to a certain altitude.
+/// altitude: meters above sea level
+pub fn gen_apoapsis_trigger(altitude: f64) -> Trigger {
+    let mut trigger = Trigger::new();
+    trigger.set_apoapsis(altitude);
+    trigger
+}
+
+/// Generates a trigger that will occur once the periapsis has risen to a certain altitude.
+/// altitude: meters above sea level
+pub fn gen_periapsis_trigger(altitude: f64) -> Trigger {
+    let mut trigger = Trigger::new();
+    trigger.set_periapsis(altitude);
+    trigger
+}
+
+/// Generates a trigger that will occur a certain number of seconds before reaching apoapsis,
+/// e.g. to start a circularization burn.
+pub fn gen_time_to_apoapsis_trigger(seconds: f64) -> Trigger {
+    let mut trigger = Trigger::new();
+    trigger.set_time_to_apoapsis(seconds);
+    trigger
+}
+
 pub fn gen_flightplan_from_steps(steps: Vec<Step>) -> FlightPlan {
     let steps = RepeatedField::from_vec(steps);
 
@@ -151,3 +401,45 @@ pub fn gen_flightplan_from_steps(steps: Vec<Step>) -> FlightPlan {
 
     plan
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_plans_saved_before_times_had_a_base() {
+        let filename = std::env::temp_dir().join("kerbx-plan-before-time-base.json");
+        std::fs::write(
+            &filename,
+            r#"{"step_count":3,"steps":[{"count":1,"field_type":"IGNITE","trigger":{"trigger_condition":{"time":{"seconds":0}}},"action":null},{"count":2,"field_type":"NEXTSTAGE","trigger":{"trigger_condition":{"time":{"seconds":1600000000}}},"action":null},{"count":3,"field_type":"THROTTLELEVEL","trigger":{"trigger_condition":{"time":{"seconds":1600000010}}},"action":{"throttle":{"throttle":0.5}}}]}"#,
+        )
+        .unwrap();
+        let plan = load_from_file(filename.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&filename).unwrap();
+
+        assert_eq!(plan.steps.len(), 3);
+        let time = plan.steps[1].get_trigger().get_time();
+        assert_eq!(time.get_seconds(), 1_600_000_000);
+        assert_eq!(time.get_nanos(), 0);
+        assert_eq!(time.get_base(), Time_Base::WALLCLOCK);
+
+        // Throttle steps from before TWR holds set the throttle directly
+        let throttle = plan.steps[2].get_throttle();
+        assert_eq!(throttle.get_throttle(), 0.5);
+        assert_eq!(throttle.get_twr(), 0.0);
+    }
+
+    #[test]
+    fn written_plans_load_unchanged() {
+        let plan = gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+            gen_throttle_step(2, 0.5, gen_met_trigger(12.5)),
+        ]);
+        let filename = std::env::temp_dir().join("kerbx-plan-round-trip.json");
+        write_to_file(filename.to_str().unwrap(), &plan).unwrap();
+        let loaded = load_from_file(filename.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&filename).unwrap();
+
+        assert_eq!(loaded, plan);
+    }
+}
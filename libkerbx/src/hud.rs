@@ -0,0 +1,233 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! On-screen messages and a status panel posted through the UI service, so what the avionics is
+//! doing can be followed from inside KSP. Messages show state changes, the countdown, executed
+//! steps and errors; the panel keeps the last step executed and the trigger the next one waits
+//! for in view.
+
+use crate::clock;
+use crate::kerbx::{Step, Time_Base, Trigger, Trigger_oneof_trigger_condition};
+use crate::ui::{self, Canvas, MessagePosition, RectTransform, Text};
+use crate::{KerbxError, KerbxTransport};
+
+/// Seconds a message stays on screen
+const MESSAGE_DURATION: f32 = 4.0;
+const MESSAGE_SIZE: f32 = 20.0;
+/// Width and height of the status panel in pixels
+const PANEL_SIZE: (f64, f64) = (320.0, 60.0);
+/// Gap in pixels between the status panel and the left edge of the screen
+const PANEL_MARGIN: f64 = 10.0;
+const TEXT_SIZE: i32 = 14;
+
+const STATE_COLOR: (f64, f64, f64) = (1.0, 1.0, 1.0);
+const COUNTDOWN_COLOR: (f64, f64, f64) = (1.0, 0.92, 0.016);
+const STEP_COLOR: (f64, f64, f64) = (0.0, 1.0, 0.0);
+const ERROR_COLOR: (f64, f64, f64) = (1.0, 0.0, 0.0);
+
+/// What the flight plan says a step waits for, e.g. "altitude 1000 m"
+pub fn describe_trigger(trigger: &Trigger) -> String {
+    match &trigger.trigger_condition {
+        Some(Trigger_oneof_trigger_condition::position(position)) => format!(
+            "position {:.2}, {:.2}",
+            position.get_lat(),
+            position.get_lon()
+        ),
+        Some(Trigger_oneof_trigger_condition::time(time)) => {
+            let seconds = clock::seconds(time);
+            match time.get_base() {
+                Time_Base::WALLCLOCK => format!("UNIX time {} s", seconds),
+                Time_Base::UNIVERSAL => format!("UT {} s", seconds),
+                Time_Base::MISSION => format!("T+{} s", seconds),
+            }
+        }
+        Some(Trigger_oneof_trigger_condition::alt(alt)) => format!("altitude {} m", alt),
+        Some(Trigger_oneof_trigger_condition::apoapsis(alt)) => format!("apoapsis {} m", alt),
+        Some(Trigger_oneof_trigger_condition::periapsis(alt)) => format!("periapsis {} m", alt),
+        Some(Trigger_oneof_trigger_condition::time_to_apoapsis(seconds)) => {
+            format!("{} s to apoapsis", seconds)
+        }
+        None => String::from("no trigger"),
+    }
+}
+
+/// Number, action and trigger of a step, e.g. "Step 2 IGNITE at altitude 1000 m"
+pub fn describe_step(step: &Step) -> String {
+    format!(
+        "Step {} {:?} at {}",
+        step.get_count(),
+        step.get_field_type(),
+        describe_trigger(step.get_trigger())
+    )
+}
+
+/// Messages and panel shown for one vessel. The panel lives on a canvas of the HUD's own, which
+/// kRPC removes when the HUD's connection closes.
+pub struct Hud {
+    transport: KerbxTransport,
+    panel: Option<StatusPanel>,
+}
+
+/// Panel of two lines at the left edge of the screen
+struct StatusPanel {
+    canvas: Canvas,
+    current: Text,
+    next: Text,
+}
+
+impl Hud {
+    /// Shows what the avionics flying the transport's vessel is doing. The status panel is left
+    /// for the first update_panel to build.
+    pub fn new(transport: KerbxTransport) -> Hud {
+        Hud {
+            transport,
+            panel: None,
+        }
+    }
+
+    /// Announces the state the avionics has entered, e.g. "READY"
+    pub fn state(&self, state: &str) -> Result<(), KerbxError> {
+        self.message(&format!("Avionics {}", state), STATE_COLOR)
+    }
+
+    /// Announces the seconds left before launch
+    pub fn countdown(&self, seconds: u32) -> Result<(), KerbxError> {
+        self.message(&format!("Launching in {}", seconds), COUNTDOWN_COLOR)
+    }
+
+    /// Announces a step that has just been executed
+    pub fn step_executed(&self, step: &Step) -> Result<(), KerbxError> {
+        self.message(&describe_step(step), STEP_COLOR)
+    }
+
+    /// Announces why the avionics went to ERROR
+    pub fn error(&self, message: &str) -> Result<(), KerbxError> {
+        self.message(&format!("Avionics ERROR: {}", message), ERROR_COLOR)
+    }
+
+    /// Shows the last step executed and the trigger of the step after it in the panel, building
+    /// the panel the first time.
+    /// current: Step last executed, None before the first
+    /// next: Step waiting on its trigger, None once the plan is done
+    pub fn update_panel(
+        &mut self,
+        current: Option<&Step>,
+        next: Option<&Step>,
+    ) -> Result<(), KerbxError> {
+        if self.panel.is_none() {
+            self.panel = Some(self.add_panel()?);
+        }
+        let panel = self.panel.as_ref().unwrap();
+
+        let current = match current {
+            Some(step) => format!("Step {} {:?}", step.get_count(), step.get_field_type()),
+            None => String::from("No step executed"),
+        };
+        let next = match next {
+            Some(step) => format!("Next: {}", describe_trigger(step.get_trigger())),
+            None => String::from("Flight plan complete"),
+        };
+        let link = &self.transport.sim_feed;
+        link.mk_call(&panel.current.set_content(&current))?;
+        link.mk_call(&panel.next.set_content(&next))?;
+        Ok(())
+    }
+
+    /// Removes the panel. Messages fade on their own.
+    pub fn clear(&mut self) -> Result<(), KerbxError> {
+        if let Some(panel) = self.panel.take() {
+            self.transport.sim_feed.mk_call(&panel.canvas.remove())?;
+        }
+        Ok(())
+    }
+
+    fn message(&self, content: &str, color: (f64, f64, f64)) -> Result<(), KerbxError> {
+        self.transport.sim_feed.mk_call(&ui::message(
+            content,
+            MESSAGE_DURATION,
+            MessagePosition::TopCenter,
+            color,
+            MESSAGE_SIZE,
+        ))
+    }
+
+    fn add_panel(&self) -> Result<StatusPanel, KerbxError> {
+        let link = &self.transport.sim_feed;
+        let canvas = link.mk_call(&ui::add_canvas())?;
+        let panel = link.mk_call(&canvas.add_panel(true))?;
+
+        // Anchored to the middle of the left edge, so it stays put whatever the screen size
+        let rect = link.mk_call(&panel.get_rect_transform())?;
+        link.mk_call(&rect.set_anchor((0.0, 0.5)))?;
+        link.mk_call(&rect.set_size(PANEL_SIZE))?;
+        link.mk_call(&rect.set_position((PANEL_SIZE.0 / 2.0 + PANEL_MARGIN, 0.0)))?;
+
+        let current = link.mk_call(&panel.add_text("", true))?;
+        self.place(&link.mk_call(&current.get_rect_transform())?, 1.0)?;
+        let next = link.mk_call(&panel.add_text("", true))?;
+        self.place(&link.mk_call(&next.get_rect_transform())?, -1.0)?;
+        for text in [&current, &next].iter() {
+            link.mk_call(&text.set_size(TEXT_SIZE))?;
+            link.mk_call(&text.set_color(STATE_COLOR))?;
+        }
+
+        Ok(StatusPanel {
+            canvas,
+            current,
+            next,
+        })
+    }
+
+    /// Lays a line of text across the upper (row 1) or lower (row -1) half of the panel
+    fn place(&self, rect: &RectTransform, row: f64) -> Result<(), KerbxError> {
+        let link = &self.transport.sim_feed;
+        let height = PANEL_SIZE.1 / 2.0;
+        link.mk_call(&rect.set_size((PANEL_SIZE.0 - 2.0 * PANEL_MARGIN, height)))?;
+        link.mk_call(&rect.set_position((0.0, row * height / 2.0)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flightplan::*;
+    use crate::kerbx::Step_ActionType;
+
+    #[test]
+    fn describes_steps_by_action_and_trigger() {
+        let ignite = gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap();
+        assert_eq!(describe_step(&ignite), "Step 1 IGNITE at UNIX time 0 s");
+
+        let turn = gen_reorient_step(2, 0.0, 45.0, 90.0, gen_pos_trigger(-0.1, -74.0));
+        assert_eq!(
+            describe_step(&turn),
+            "Step 2 REORIENT at position -0.10, -74.00"
+        );
+        assert_eq!(
+            describe_trigger(&gen_apoapsis_trigger(80_000.0)),
+            "apoapsis 80000 m"
+        );
+        assert_eq!(describe_trigger(&gen_met_trigger(90.5)), "T+90.5 s");
+    }
+}
@@ -33,195 +33,1440 @@ pub mod ui;
 pub mod kerbx;
 
 // Bespoke code
+pub mod clock;
+pub mod composition;
+mod error;
 pub mod flightplan;
+pub mod hud;
+mod link;
+pub mod maneuver;
+pub mod overlay;
+pub mod robotics;
+pub mod sim;
+pub mod timeline;
+pub mod vehicle;
+pub mod warp;
 
 // Library Modules
-use crate::kerbx::Time;
-use contracts::*;
-use krpc_mars::{error::Error, RPCClient};
-use nalgebra::Vector3;
-use space_center::{CelestialBody, ReferenceFrame, Vessel};
+use crate::kerbx::{Time, Time_Base};
+use clock::GameClock;
+use composition::{
+    resource_density, EngineInfo, PartInfo, ResourceAmount, StageDeltaV, TankInfo,
+    VesselComposition, FUEL_RESOURCES,
+};
+pub use error::KerbxError;
+use krpc_mars::codec::{RPCEncodable, RPCExtractable};
+use krpc_mars::{RPCClient, StreamClient, StreamHandle};
+use link::Link;
+pub use link::{INITIAL_BACKOFF, MAX_BACKOFF};
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+use space_center::{
+    CelestialBody, Flight, Orbit, Parachute, Part, ReferenceFrame, Resources, SASMode, Vessel,
+};
+use std::cell::{Cell, RefCell};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::SystemTime;
+use vehicle::{
+    CommsStatus, ControlInputs, EulerAngles, LinkStatus, ManeuverNode, OrbitalElements, Propulsion,
+    ResourceLevel, ResourceLevels, SasMode, Servos, SpeedMode, VehicleActuators, VehicleSensors,
+    VehicleState, VesselResources, WarpMode, WarpState, ACTION_GROUPS, MONITORED_RESOURCES,
+};
 
-pub fn time() -> Result<Time, std::time::SystemTimeError> {
-    let mut time = Time::new();
-    time.seconds = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)?
-        .as_secs();
-    Ok(time)
+/// Gravitational acceleration in m/s^2 at radius meters from the center of a body with the given
+/// GM
+fn gravity(gravitational_parameter: f64, radius: f64) -> f64 {
+    if radius > 0.0 {
+        gravitational_parameter / (radius * radius)
+    } else {
+        0.0
+    }
+}
+
+/// kRPC object id behind a remote object handle
+fn object_id<T: RPCEncodable>(object: &T) -> u64 {
+    // Handles go over the wire as their id, so decoding one as a u64 recovers it
+    u64::extract(&object.encode()).unwrap_or(0)
+}
+
+/// Resolves address to the first socket address it names
+fn resolve<A: ToSocketAddrs>(address: A) -> Result<SocketAddr, KerbxError> {
+    address.to_socket_addrs()?.next().ok_or_else(|| {
+        KerbxError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Address does not resolve.",
+        ))
+    })
+}
+
+/// The wall clock now, to the nanosecond
+pub fn time() -> Result<Time, KerbxError> {
+    Ok(clock::from_duration(
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?,
+        Time_Base::WALLCLOCK,
+    ))
+}
+
+/// Which vessel in the game a transport flies
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VesselTarget {
+    /// The vessel the player is controlling, looked up again on every reconnect
+    Active,
+    /// The first vessel with the given name
+    Name(String),
+    /// The vessel with the given kRPC object id, as reported by `list_vessels`
+    Id(u64),
+}
+
+/// A vessel in the game, as reported by `list_vessels`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VesselListing {
+    /// kRPC object id, for VesselTarget::Id
+    pub id: u64,
+    pub name: String,
 }
 
 /// Abstraction of our KerbX Vessel within the KSP Simulator
 pub struct KerbxTransport {
-    sim_feed: RPCClient,
-    vessel_obj: Vessel,
-    vessel_ref_frame: ReferenceFrame,
+    sim_feed: Link,
+    /// Which vessel to bind to
+    target: RefCell<VesselTarget>,
+    /// The vessel being flown. Resolved again whenever the link is re-established.
+    bound: RefCell<BoundVessel>,
+    /// The body the transport is orbiting as of the last read
+    orbited: RefCell<OrbitedBody>,
+    /// Address of the stream server, once streams are enabled
+    stream_address: Cell<Option<SocketAddr>>,
+    /// Stream subscriptions, once streams are enabled
+    streamed: RefCell<Option<Arc<Mutex<Streams>>>>,
+    /// Composition read for the stage the craft was in, kept for delta-v estimates. Only the
+    /// current stage's propellant changes until the next stage fires.
+    composition: RefCell<Option<(i32, VesselComposition)>>,
+    /// Vessels that split off when the last stage fired
+    separated: RefCell<Vec<Vessel>>,
+}
+
+/// The vessel being flown together with its own reference frames
+struct BoundVessel {
+    vessel: Vessel,
     surf_ref_frame: ReferenceFrame,
-    /// The ID of the planet the transport is orbiting
-    orbiting_obj: CelestialBody,
     orb_ref_frame: ReferenceFrame,
-    north: Vector3<f64>,
-    up: Vector3<f64>,
+}
+
+/// A body the craft can orbit, with the reference frames tied to it. The vessel's own frames
+/// follow the craft from one sphere of influence to the next, these have to be rebuilt.
+#[derive(Clone)]
+struct OrbitedBody {
+    body: CelestialBody,
+    /// Moves with the body's surface but has the axes of surf_ref_frame, so velocities measured
+    /// in it are surface velocities along up, north and east
+    surf_velocity_frame: ReferenceFrame,
+    /// As surf_velocity_frame, but moving with the body's center without rotating
+    orb_velocity_frame: ReferenceFrame,
+    /// GM in m^3/s^2
+    gravitational_parameter: f64,
+}
+
+impl OrbitedBody {
+    fn new(
+        connection: &Link,
+        surf_ref_frame: &ReferenceFrame,
+        body: CelestialBody,
+    ) -> Result<OrbitedBody, KerbxError> {
+        // Flight data is measured relative to the frame it is requested in. The vessel's own
+        // frames move with it, so velocities have to be taken relative to the body.
+        let body_frame = connection.mk_call(&body.get_reference_frame())?;
+        let surf_velocity_frame =
+            connection.mk_call(&space_center::reference_frame_static_create_hybrid(
+                &body_frame,
+                surf_ref_frame,
+                &body_frame,
+                &body_frame,
+            ))?;
+        let inertial_frame = connection.mk_call(&body.get_non_rotating_reference_frame())?;
+        let orb_velocity_frame =
+            connection.mk_call(&space_center::reference_frame_static_create_hybrid(
+                &inertial_frame,
+                surf_ref_frame,
+                &inertial_frame,
+                &inertial_frame,
+            ))?;
+
+        let gravitational_parameter = connection
+            .mk_call(&body.get_gravitational_parameter())?
+            .into();
+
+        Ok(OrbitedBody {
+            body,
+            surf_velocity_frame,
+            orb_velocity_frame,
+            gravitational_parameter,
+        })
+    }
+}
+
+/// Stream subscriptions together with the latest values they delivered
+struct Streams {
+    handles: StreamHandles,
+    values: StreamedValues,
+    /// Body the body relative streams were subscribed for
+    body: CelestialBody,
+    /// GM of body in m^3/s^2, for local gravity
+    gravitational_parameter: f64,
+    /// Stage the stage resource streams were subscribed for
+    stage: i32,
+    /// Cleared when the stream connection closes, after which values stop updating
+    connected: bool,
+}
+
+/// Raw values `snapshot` is built from. Every field is updated from the same stream update so
+/// they all come from the same physics frame.
+#[derive(Clone, Debug, Default)]
+struct StreamedValues {
+    /// The body being orbited, so a change of sphere of influence can be noticed
+    body: Option<CelestialBody>,
+    rotation: (f64, f64, f64, f64),
+    lat: f64,
+    lon: f64,
+    alt: f64,
+    velocity: f64,
+    stage: i32,
+    orbit: OrbitalElements,
+    resources: VesselResources,
+    /// Gravity is left at zero; it is worked out from radius when a snapshot is taken
+    propulsion: Propulsion,
+    /// Distance in m from the center of the body
+    radius: f64,
+}
+
+struct StreamHandles {
+    body: StreamHandle<CelestialBody>,
+    rotation: StreamHandle<(f64, f64, f64, f64)>,
+    lat: StreamHandle<f64>,
+    lon: StreamHandle<f64>,
+    alt: StreamHandle<f64>,
+    velocity: StreamHandle<f64>,
+    stage: StreamHandle<i32>,
+    orbit: OrbitStreams,
+    vessel_resources: ResourceStreams,
+    stage_resources: ResourceStreams,
+    propulsion: PropulsionStreams,
+}
+
+struct PropulsionStreams {
+    thrust: StreamHandle<f32>,
+    available_thrust: StreamHandle<f32>,
+    max_thrust: StreamHandle<f32>,
+    mass: StreamHandle<f32>,
+    dry_mass: StreamHandle<f32>,
+    radius: StreamHandle<f64>,
+}
+
+struct OrbitStreams {
+    apoapsis_altitude: StreamHandle<f64>,
+    periapsis_altitude: StreamHandle<f64>,
+    time_to_apoapsis: StreamHandle<f64>,
+    time_to_periapsis: StreamHandle<f64>,
+    eccentricity: StreamHandle<f64>,
+    inclination: StreamHandle<f64>,
+    semi_major_axis: StreamHandle<f64>,
+    period: StreamHandle<f64>,
+    longitude_of_ascending_node: StreamHandle<f64>,
+    argument_of_periapsis: StreamHandle<f64>,
+}
+
+/// Amount and capacity streams of each of the MONITORED_RESOURCES held by one set of parts
+struct ResourceStreams {
+    levels: Vec<(StreamHandle<f32>, StreamHandle<f32>)>,
+}
+
+impl StreamHandles {
+    /// Unsubscribes from every stream
+    fn remove(self, connection: &Link) -> Result<(), KerbxError> {
+        connection.mk_call(&self.body.remove())?;
+        connection.mk_call(&self.rotation.remove())?;
+        for handle in vec![self.lat, self.lon, self.alt, self.velocity] {
+            connection.mk_call(&handle.remove())?;
+        }
+        connection.mk_call(&self.stage.remove())?;
+        self.orbit.remove(connection)?;
+        self.vessel_resources.remove(connection)?;
+        self.stage_resources.remove(connection)?;
+        self.propulsion.remove(connection)
+    }
+
+    /// Copies every value present in the update into values. kRPC only sends values that have
+    /// changed, so anything missing keeps its previous value.
+    fn apply(&self, update: &krpc_mars::StreamUpdate, values: &mut StreamedValues) {
+        if let Ok(body) = update.get_result(&self.body) {
+            values.body = Some(body);
+        }
+        if let Ok(rotation) = update.get_result(&self.rotation) {
+            values.rotation = rotation;
+        }
+        if let Ok(lat) = update.get_result(&self.lat) {
+            values.lat = lat;
+        }
+        if let Ok(lon) = update.get_result(&self.lon) {
+            values.lon = lon;
+        }
+        if let Ok(alt) = update.get_result(&self.alt) {
+            values.alt = alt;
+        }
+        if let Ok(velocity) = update.get_result(&self.velocity) {
+            values.velocity = velocity;
+        }
+        if let Ok(stage) = update.get_result(&self.stage) {
+            values.stage = stage;
+        }
+        self.orbit.apply(update, &mut values.orbit);
+        self.vessel_resources
+            .apply(update, &mut values.resources.vessel);
+        self.stage_resources
+            .apply(update, &mut values.resources.stage);
+        self.propulsion.apply(update, values);
+    }
+}
+
+impl PropulsionStreams {
+    /// Unsubscribes from every stream
+    fn remove(self, connection: &Link) -> Result<(), KerbxError> {
+        for handle in vec![
+            self.thrust,
+            self.available_thrust,
+            self.max_thrust,
+            self.mass,
+            self.dry_mass,
+        ] {
+            connection.mk_call(&handle.remove())?;
+        }
+        connection.mk_call(&self.radius.remove())?;
+        Ok(())
+    }
+
+    /// As StreamHandles::apply
+    fn apply(&self, update: &krpc_mars::StreamUpdate, values: &mut StreamedValues) {
+        let propulsion = &mut values.propulsion;
+        if let Ok(value) = update.get_result(&self.thrust) {
+            propulsion.thrust = value.into();
+        }
+        if let Ok(value) = update.get_result(&self.available_thrust) {
+            propulsion.available_thrust = value.into();
+        }
+        if let Ok(value) = update.get_result(&self.max_thrust) {
+            propulsion.max_thrust = value.into();
+        }
+        if let Ok(value) = update.get_result(&self.mass) {
+            propulsion.mass = value.into();
+        }
+        if let Ok(value) = update.get_result(&self.dry_mass) {
+            propulsion.dry_mass = value.into();
+        }
+        if let Ok(value) = update.get_result(&self.radius) {
+            values.radius = value;
+        }
+    }
+}
+
+impl ResourceStreams {
+    /// Subscribes to the levels of the monitored resources held by resources
+    fn new(connection: &Link, resources: &Resources) -> Result<ResourceStreams, KerbxError> {
+        let mut levels = Vec::new();
+        for name in MONITORED_RESOURCES.iter() {
+            levels.push((
+                connection.mk_call(&resources.amount(name).to_stream())?,
+                connection.mk_call(&resources.max(name).to_stream())?,
+            ));
+        }
+        Ok(ResourceStreams { levels })
+    }
+
+    /// As StreamHandles::apply
+    fn apply(&self, update: &krpc_mars::StreamUpdate, levels: &mut ResourceLevels) {
+        for (name, (amount, max)) in MONITORED_RESOURCES.iter().zip(&self.levels) {
+            if let Some(level) = levels.get_mut(name) {
+                if let Ok(value) = update.get_result(amount) {
+                    level.amount = value.into();
+                }
+                if let Ok(value) = update.get_result(max) {
+                    level.max = value.into();
+                }
+            }
+        }
+    }
+
+    /// Unsubscribes from every stream
+    fn remove(self, connection: &Link) -> Result<(), KerbxError> {
+        for (amount, max) in self.levels {
+            connection.mk_call(&amount.remove())?;
+            connection.mk_call(&max.remove())?;
+        }
+        Ok(())
+    }
+}
+
+impl OrbitStreams {
+    /// Unsubscribes from every stream
+    fn remove(self, connection: &Link) -> Result<(), KerbxError> {
+        for handle in vec![
+            self.apoapsis_altitude,
+            self.periapsis_altitude,
+            self.time_to_apoapsis,
+            self.time_to_periapsis,
+            self.eccentricity,
+            self.inclination,
+            self.semi_major_axis,
+            self.period,
+            self.longitude_of_ascending_node,
+            self.argument_of_periapsis,
+        ] {
+            connection.mk_call(&handle.remove())?;
+        }
+        Ok(())
+    }
+
+    /// As StreamHandles::apply. kRPC reports angles in radians.
+    fn apply(&self, update: &krpc_mars::StreamUpdate, orbit: &mut OrbitalElements) {
+        if let Ok(value) = update.get_result(&self.apoapsis_altitude) {
+            orbit.apoapsis_altitude = value;
+        }
+        if let Ok(value) = update.get_result(&self.periapsis_altitude) {
+            orbit.periapsis_altitude = value;
+        }
+        if let Ok(value) = update.get_result(&self.time_to_apoapsis) {
+            orbit.time_to_apoapsis = value;
+        }
+        if let Ok(value) = update.get_result(&self.time_to_periapsis) {
+            orbit.time_to_periapsis = value;
+        }
+        if let Ok(value) = update.get_result(&self.eccentricity) {
+            orbit.eccentricity = value;
+        }
+        if let Ok(value) = update.get_result(&self.inclination) {
+            orbit.inclination = value.to_degrees();
+        }
+        if let Ok(value) = update.get_result(&self.semi_major_axis) {
+            orbit.semi_major_axis = value;
+        }
+        if let Ok(value) = update.get_result(&self.period) {
+            orbit.period = value;
+        }
+        if let Ok(value) = update.get_result(&self.longitude_of_ascending_node) {
+            orbit.longitude_of_ascending_node = value.to_degrees();
+        }
+        if let Ok(value) = update.get_result(&self.argument_of_periapsis) {
+            orbit.argument_of_periapsis = value.to_degrees();
+        }
+    }
 }
 
 impl KerbxTransport {
-    pub fn new(connection: RPCClient) -> Result<KerbxTransport, Error> {
-        let vessel = connection.mk_call(&space_center::get_active_vessel())?;
+    /// Wraps an existing connection and flies the active vessel. The transport cannot
+    /// re-establish the connection if it drops; use `connect` for that.
+    pub fn new(connection: RPCClient) -> Result<KerbxTransport, KerbxError> {
+        KerbxTransport::with_target(connection, VesselTarget::Active)
+    }
+
+    /// As new, but flies the given vessel.
+    pub fn with_target(
+        connection: RPCClient,
+        target: VesselTarget,
+    ) -> Result<KerbxTransport, KerbxError> {
+        KerbxTransport::bind(Link::new(connection, None), target)
+    }
+
+    /// Connects to the kRPC server. If the connection later drops, `check_link` re-establishes
+    /// it with backoff.
+    /// client_name: Name the server shows for this client
+    /// address: Address of the kRPC RPC server (port 50000 by default)
+    pub fn connect<A: ToSocketAddrs>(
+        client_name: &str,
+        address: A,
+    ) -> Result<KerbxTransport, KerbxError> {
+        KerbxTransport::connect_to(client_name, address, VesselTarget::Active)
+    }
+
+    /// As connect, but flies the given vessel. Several transports, each flying its own vessel,
+    /// can share one kRPC server as long as each uses its own client name.
+    pub fn connect_to<A: ToSocketAddrs>(
+        client_name: &str,
+        address: A,
+        target: VesselTarget,
+    ) -> Result<KerbxTransport, KerbxError> {
+        let address = resolve(address)?;
+        let connection = RPCClient::connect(client_name, address)?;
+        KerbxTransport::bind(
+            Link::new(connection, Some((String::from(client_name), address))),
+            target,
+        )
+    }
+
+    fn bind(connection: Link, target: VesselTarget) -> Result<KerbxTransport, KerbxError> {
+        let (bound, orbited) = KerbxTransport::resolve_vessel(&connection, &target)?;
+        Ok(KerbxTransport {
+            sim_feed: connection,
+            target: RefCell::new(target),
+            bound: RefCell::new(bound),
+            orbited: RefCell::new(orbited),
+            stream_address: Cell::new(None),
+            streamed: RefCell::new(None),
+            composition: RefCell::new(None),
+            separated: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Looks up the target vessel, its frames and the body it is orbiting
+    fn resolve_vessel(
+        connection: &Link,
+        target: &VesselTarget,
+    ) -> Result<(BoundVessel, OrbitedBody), KerbxError> {
+        let vessel = match target {
+            VesselTarget::Active => connection.mk_call(&space_center::get_active_vessel())?,
+            VesselTarget::Name(name) => {
+                let mut found = None;
+                for vessel in connection.mk_call(&space_center::get_vessels())? {
+                    if connection.mk_call(&vessel.get_name())? == *name {
+                        found = Some(vessel);
+                        break;
+                    }
+                }
+                found.ok_or_else(|| KerbxError::VesselNotFound(format!("named {}", name)))?
+            }
+            VesselTarget::Id(id) => connection
+                .mk_call(&space_center::get_vessels())?
+                .into_iter()
+                .find(|vessel| object_id(vessel) == *id)
+                .ok_or_else(|| KerbxError::VesselNotFound(format!("with id {}", id)))?,
+        };
 
-        // Given travel is terrestrial only on Kerbin, we are assuming these reference frames are
-        // constant across the life of the vehicle
+        // The vessel's frames follow it wherever it goes. Only the frames of the body it is
+        // orbiting have to be rebuilt, which happens on the first read after leaving its sphere
+        // of influence.
         let surf_ref_frame = connection.mk_call(&vessel.get_surface_reference_frame())?;
-        let vessel_ref_frame = connection.mk_call(&vessel.get_reference_frame())?;
         let orb_ref_frame = connection.mk_call(&vessel.get_orbital_reference_frame())?;
 
         let orbit = connection.mk_call(&vessel.get_orbit())?;
         let planet = connection.mk_call(&orbit.get_body())?;
+        let orbited = OrbitedBody::new(connection, &surf_ref_frame, planet)?;
 
-        Ok(KerbxTransport {
-            sim_feed: connection,
-            vessel_obj: vessel,
-            vessel_ref_frame,
-            surf_ref_frame,
-            orbiting_obj: planet,
-            orb_ref_frame,
-            north: Vector3::new(0.0, 1.0, 0.0),
-            up: Vector3::new(1.0, 0.0, 0.0),
+        Ok((
+            BoundVessel {
+                vessel,
+                surf_ref_frame,
+                orb_ref_frame,
+            },
+            orbited,
+        ))
+    }
+
+    /// Resolves every object handle again after the link has been re-established, as handles
+    /// from the old connection may no longer be valid, and resubscribes to the streams.
+    fn rebind(&self) -> Result<(), KerbxError> {
+        let resolved = KerbxTransport::resolve_vessel(&self.sim_feed, &self.target.borrow())?;
+        self.install(resolved);
+        if let Some(address) = self.stream_address.get() {
+            self.subscribe(address)?;
+        }
+        Ok(())
+    }
+
+    /// Switches to flying another vessel, e.g. one that separated when a stage fired. The
+    /// transport keeps flying the current vessel if the target cannot be found.
+    pub fn follow(&self, target: VesselTarget) -> Result<(), KerbxError> {
+        let resolved = KerbxTransport::resolve_vessel(&self.sim_feed, &target)?;
+        self.target.replace(target);
+        self.separated.replace(Vec::new());
+        self.install(resolved);
+        self.restream_vessel()
+    }
+
+    /// Every vessel in the game
+    pub fn list_vessels(&self) -> Result<Vec<VesselListing>, KerbxError> {
+        let vessels = self.sim_feed.mk_call(&space_center::get_vessels())?;
+        self.listings(&vessels)
+    }
+
+    /// Vessels that split off the vessel being flown when its last stage fired. Any of them can
+    /// be flown with `follow(VesselTarget::Id(..))`.
+    pub fn separated_vessels(&self) -> Result<Vec<VesselListing>, KerbxError> {
+        self.listings(&self.separated.borrow())
+    }
+
+    /// Which vessel the transport is flying
+    pub fn target(&self) -> VesselTarget {
+        self.target.borrow().clone()
+    }
+
+    fn listings(&self, vessels: &[Vessel]) -> Result<Vec<VesselListing>, KerbxError> {
+        vessels
+            .iter()
+            .map(|vessel| {
+                Ok(VesselListing {
+                    id: object_id(vessel),
+                    name: self.sim_feed.mk_call(&vessel.get_name())?,
+                })
+            })
+            .collect()
+    }
+
+    /// Starts flying a resolved vessel, dropping everything read for the previous one
+    fn install(&self, (bound, orbited): (BoundVessel, OrbitedBody)) {
+        self.bound.replace(bound);
+        self.orbited.replace(orbited);
+        self.composition.replace(None);
+    }
+
+    fn vessel(&self) -> Vessel {
+        self.bound.borrow().vessel.clone()
+    }
+
+    fn surf_ref_frame(&self) -> ReferenceFrame {
+        self.bound.borrow().surf_ref_frame.clone()
+    }
+
+    fn orb_ref_frame(&self) -> ReferenceFrame {
+        self.bound.borrow().orb_ref_frame.clone()
+    }
+
+    /// Health of the connection as of the last call, without trying to re-establish it
+    pub fn link_status(&self) -> LinkStatus {
+        self.sim_feed.status()
+    }
+
+    /// Subscribes to everything `snapshot` reports through the kRPC stream server. Afterwards
+    /// `snapshot` returns the latest values pushed by the server without making any RPCs.
+    /// stream_address: Address of the kRPC stream server (port 50001 by default)
+    pub fn enable_streams<A: ToSocketAddrs>(
+        &mut self,
+        stream_address: A,
+    ) -> Result<(), KerbxError> {
+        let address = resolve(stream_address)?;
+        self.subscribe(address)?;
+        self.stream_address.set(Some(address));
+        Ok(())
+    }
+
+    /// True unless streams were enabled and their connection has since closed
+    fn streams_connected(&self) -> bool {
+        match &*self.streamed.borrow() {
+            Some(streamed) => streamed.lock().unwrap().connected,
+            None => true,
+        }
+    }
+
+    fn subscribe(&self, stream_address: SocketAddr) -> Result<(), KerbxError> {
+        let mut stream_client = StreamClient::connect(&self.sim_feed.client(), stream_address)?;
+        let mut streams = self.add_streams()?;
+
+        // Anything already waiting is newer than the seed values
+        if let Ok(update) = stream_client.recv_update() {
+            let Streams {
+                handles, values, ..
+            } = &mut streams;
+            handles.apply(&update, values);
+        }
+
+        let streamed = Arc::new(Mutex::new(streams));
+        let latest = streamed.clone();
+        thread::spawn(move || {
+            // Runs until the stream connection is closed
+            while let Ok(update) = stream_client.recv_update() {
+                let mut streams = latest.lock().unwrap();
+                let Streams {
+                    handles, values, ..
+                } = &mut *streams;
+                handles.apply(&update, values);
+            }
+            latest.lock().unwrap().connected = false;
+        });
+
+        self.streamed.replace(Some(streamed));
+        Ok(())
+    }
+
+    /// Moves the streams over to the vessel now bound, keeping the stream connection open
+    fn restream_vessel(&self) -> Result<(), KerbxError> {
+        let streamed = match &*self.streamed.borrow() {
+            Some(streamed) => streamed.clone(),
+            None => return Ok(()),
+        };
+        let fresh = self.add_streams()?;
+        let stale = std::mem::replace(&mut *streamed.lock().unwrap(), fresh);
+        stale.handles.remove(&self.sim_feed)
+    }
+
+    /// Adds a stream for every value a snapshot is built from. Values are seeded with a direct
+    /// read so the first snapshot is valid before any update arrives.
+    fn add_streams(&self) -> Result<Streams, KerbxError> {
+        // Lat, lon and alt come from the flight object as position based lookups cannot be
+        // streamed: their position argument is fixed when the stream is created.
+        let orbited = self.orbited_body()?;
+        let flight = self
+            .sim_feed
+            .mk_call(&self.vessel().flight(&orbited.surf_velocity_frame))?;
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        let orbit = self.orbit()?;
+        let stage = self.sim_feed.mk_call(&control.get_current_stage())?;
+        let vessel_resources = self.sim_feed.mk_call(&self.vessel().get_resources())?;
+        let stage_resources = self.stage_resources(stage)?;
+
+        let values = StreamedValues {
+            body: Some(orbited.body.clone()),
+            rotation: self
+                .sim_feed
+                .mk_call(&self.vessel().rotation(&self.surf_ref_frame()))?,
+            lat: self.sim_feed.mk_call(&flight.get_latitude())?,
+            lon: self.sim_feed.mk_call(&flight.get_longitude())?,
+            alt: self.sim_feed.mk_call(&flight.get_mean_altitude())?,
+            velocity: self.sim_feed.mk_call(&flight.get_speed())?,
+            stage,
+            orbit: self.read_orbit(&orbit)?,
+            resources: VesselResources {
+                vessel: self.read_resources(&vessel_resources)?,
+                stage: self.read_resources(&stage_resources)?,
+            },
+            propulsion: self.read_propulsion()?,
+            radius: self.sim_feed.mk_call(&orbit.get_radius())?,
+        };
+
+        let handles = StreamHandles {
+            body: self.sim_feed.mk_call(&orbit.get_body().to_stream())?,
+            rotation: self
+                .sim_feed
+                .mk_call(&self.vessel().rotation(&self.surf_ref_frame()).to_stream())?,
+            lat: self.sim_feed.mk_call(&flight.get_latitude().to_stream())?,
+            lon: self.sim_feed.mk_call(&flight.get_longitude().to_stream())?,
+            alt: self
+                .sim_feed
+                .mk_call(&flight.get_mean_altitude().to_stream())?,
+            velocity: self.sim_feed.mk_call(&flight.get_speed().to_stream())?,
+            stage: self
+                .sim_feed
+                .mk_call(&control.get_current_stage().to_stream())?,
+            orbit: OrbitStreams {
+                apoapsis_altitude: self
+                    .sim_feed
+                    .mk_call(&orbit.get_apoapsis_altitude().to_stream())?,
+                periapsis_altitude: self
+                    .sim_feed
+                    .mk_call(&orbit.get_periapsis_altitude().to_stream())?,
+                time_to_apoapsis: self
+                    .sim_feed
+                    .mk_call(&orbit.get_time_to_apoapsis().to_stream())?,
+                time_to_periapsis: self
+                    .sim_feed
+                    .mk_call(&orbit.get_time_to_periapsis().to_stream())?,
+                eccentricity: self
+                    .sim_feed
+                    .mk_call(&orbit.get_eccentricity().to_stream())?,
+                inclination: self
+                    .sim_feed
+                    .mk_call(&orbit.get_inclination().to_stream())?,
+                semi_major_axis: self
+                    .sim_feed
+                    .mk_call(&orbit.get_semi_major_axis().to_stream())?,
+                period: self.sim_feed.mk_call(&orbit.get_period().to_stream())?,
+                longitude_of_ascending_node: self
+                    .sim_feed
+                    .mk_call(&orbit.get_longitude_of_ascending_node().to_stream())?,
+                argument_of_periapsis: self
+                    .sim_feed
+                    .mk_call(&orbit.get_argument_of_periapsis().to_stream())?,
+            },
+            vessel_resources: ResourceStreams::new(&self.sim_feed, &vessel_resources)?,
+            stage_resources: ResourceStreams::new(&self.sim_feed, &stage_resources)?,
+            propulsion: PropulsionStreams {
+                thrust: self
+                    .sim_feed
+                    .mk_call(&self.vessel().get_thrust().to_stream())?,
+                available_thrust: self
+                    .sim_feed
+                    .mk_call(&self.vessel().get_available_thrust().to_stream())?,
+                max_thrust: self
+                    .sim_feed
+                    .mk_call(&self.vessel().get_max_thrust().to_stream())?,
+                mass: self
+                    .sim_feed
+                    .mk_call(&self.vessel().get_mass().to_stream())?,
+                dry_mass: self
+                    .sim_feed
+                    .mk_call(&self.vessel().get_dry_mass().to_stream())?,
+                radius: self.sim_feed.mk_call(&orbit.get_radius().to_stream())?,
+            },
+        };
+
+        Ok(Streams {
+            handles,
+            values,
+            body: orbited.body,
+            gravitational_parameter: orbited.gravitational_parameter,
+            stage,
+            connected: true,
         })
     }
-    pub fn get_direction(&self) -> Result<Vector3<f64>, Error> {
+
+    /// Unit vector along the vessel's nose in the surface reference frame
+    pub fn get_direction(&self) -> Result<Vector3<f64>, KerbxError> {
         // Get current vessel direction
         let direction = self
             .sim_feed
-            .mk_call(&self.vessel_obj.direction(&self.surf_ref_frame))?;
+            .mk_call(&self.vessel().direction(&self.surf_ref_frame()))?;
 
         Ok(Vector3::new(direction.0, direction.1, direction.2))
     }
 
-    pub fn get_horizon(&self) -> Result<Vector3<f64>, Error> {
-        let direction = self.get_direction()?;
-        Ok(Vector3::new(0.0, direction[1], direction[2]))
+    /// Moves the body relative streams over to the body the craft is now orbiting
+    fn restream_body(&self, streams: &mut Streams) -> Result<(), KerbxError> {
+        let orbited = self.orbited_body()?;
+        let flight = self
+            .sim_feed
+            .mk_call(&self.vessel().flight(&orbited.surf_velocity_frame))?;
+
+        let velocity = self.sim_feed.mk_call(&flight.get_speed().to_stream())?;
+        let stale = std::mem::replace(&mut streams.handles.velocity, velocity);
+        self.sim_feed.mk_call(&stale.remove())?;
+
+        streams.values.velocity = self.sim_feed.mk_call(&flight.get_speed())?;
+        streams.body = orbited.body;
+        streams.gravitational_parameter = orbited.gravitational_parameter;
+        Ok(())
     }
 
-    /// m/s in reference to the nearest orbiting object
-    /// NOTE: Currently bugged and will always return 0 for some reason.
-    pub fn get_velocity(&self) -> Result<f64, Error> {
-        // velocity rpc call is returning 0
-        let flight = self
+    /// Moves the stage resource streams over to the parts the craft's new stage will drop
+    fn restream_stage(&self, streams: &mut Streams) -> Result<(), KerbxError> {
+        let stage = streams.values.stage;
+        let resources = self.stage_resources(stage)?;
+
+        let fresh = ResourceStreams::new(&self.sim_feed, &resources)?;
+        let stale = std::mem::replace(&mut streams.handles.stage_resources, fresh);
+        stale.remove(&self.sim_feed)?;
+
+        streams.values.resources.stage = self.read_resources(&resources)?;
+        streams.stage = stage;
+        Ok(())
+    }
+
+    /// Resources held by the parts the next decoupling will drop
+    /// stage: Current stage of the craft
+    fn stage_resources(&self, stage: i32) -> Result<Resources, KerbxError> {
+        self.sim_feed
+            .mk_call(&self.vessel().resources_in_decouple_stage(stage - 1, false))
+    }
+
+    /// Every parachute still attached to the vessel
+    fn parachutes(&self) -> Result<Vec<Parachute>, KerbxError> {
+        let parts = self.sim_feed.mk_call(&self.vessel().get_parts())?;
+        self.sim_feed.mk_call(&parts.get_parachutes())
+    }
+
+    /// Reads thrust and mass. Gravity is left at zero.
+    fn read_propulsion(&self) -> Result<Propulsion, KerbxError> {
+        let vessel = self.vessel();
+        Ok(Propulsion {
+            thrust: self.sim_feed.mk_call(&vessel.get_thrust())?.into(),
+            available_thrust: self
+                .sim_feed
+                .mk_call(&vessel.get_available_thrust())?
+                .into(),
+            max_thrust: self.sim_feed.mk_call(&vessel.get_max_thrust())?.into(),
+            mass: self.sim_feed.mk_call(&vessel.get_mass())?.into(),
+            dry_mass: self.sim_feed.mk_call(&vessel.get_dry_mass())?.into(),
+            gravity: 0.0,
+        })
+    }
+
+    /// Reads the levels of the monitored resources held by resources
+    fn read_resources(&self, resources: &Resources) -> Result<ResourceLevels, KerbxError> {
+        let mut levels = ResourceLevels::default();
+        for name in MONITORED_RESOURCES.iter() {
+            if let Some(level) = levels.get_mut(name) {
+                *level = ResourceLevel {
+                    amount: self.sim_feed.mk_call(&resources.amount(name))?.into(),
+                    max: self.sim_feed.mk_call(&resources.max(name))?.into(),
+                };
+            }
+        }
+        Ok(levels)
+    }
+
+    /// The body the craft is orbiting. Its frames are rebuilt if the craft has moved into
+    /// another sphere of influence since the last call.
+    fn orbited_body(&self) -> Result<OrbitedBody, KerbxError> {
+        let body = self.sim_feed.mk_call(&self.orbit()?.get_body())?;
+        if body != self.orbited.borrow().body {
+            let orbited = OrbitedBody::new(&self.sim_feed, &self.surf_ref_frame(), body)?;
+            self.orbited.replace(orbited);
+        }
+        Ok(self.orbited.borrow().clone())
+    }
+
+    /// Name of the body the craft is orbiting, e.g. "Kerbin" or "Mun"
+    pub fn get_body_name(&self) -> Result<String, KerbxError> {
+        self.sim_feed.mk_call(&self.orbited_body()?.body.get_name())
+    }
+
+    /// Equatorial radius in meters of the body the craft is orbiting
+    pub fn get_body_radius(&self) -> Result<f64, KerbxError> {
+        let body = self.orbited_body()?.body;
+        Ok(self.sim_feed.mk_call(&body.get_equatorial_radius())?.into())
+    }
+
+    /// Gravitational parameter (GM) in m^3/s^2 of the body the craft is orbiting
+    pub fn get_body_gravitational_parameter(&self) -> Result<f64, KerbxError> {
+        let body = self.orbited_body()?.body;
+        Ok(self
             .sim_feed
-            .mk_call(&self.vessel_obj.flight(&self.surf_ref_frame))?;
-
-        // True Air Speed using the surface reference frame provides velocity in m/s based on
-        // the surface
-        Ok(self.sim_feed.mk_call(&flight.get_true_air_speed())?.into())
-
-        // Returning true air speed as the following rpc calls fail to return data:
-        // flight.get_speed(), flight.get_velocity, vessel.get_velocity
-        // If you can ever get velocity to return a three-tuple, you can use the following to calculate
-        // the linear velocity:
-        //Ok((velocity.0.powi(2) + velocity.1.powi(2) + velocity.2.powi(2)).sqrt())
-    }
-
-    #[ensures(ret.is_ok() ->  (*ret.as_ref().unwrap() > -180.0 && *ret.as_ref().unwrap() <= 180.0), "Roll must be -180 < x <= +180 degrees." )]
-    pub fn get_roll(&self) -> Result<f64, Error> {
-        let vessel_up = self.sim_feed.mk_call(&space_center::transform_direction(
-            (0.0, 0.0, -1.0),
-            &self.vessel_ref_frame,
-            &self.surf_ref_frame,
-        ))?;
-        let vessel_up = Vector3::new(vessel_up.0, vessel_up.1, vessel_up.2);
-
-        let plane_normal = self.get_direction()?.cross(&self.up);
-        let roll = self.to_degrees(vessel_up.angle(&plane_normal));
-        let roll = if vessel_up[0] > 0.0 {
-            roll * -1.0
-        } else {
-            if roll < 0.0 {
-                roll + 180.0
-            } else {
-                roll - 180.0
+            .mk_call(&body.get_gravitational_parameter())?
+            .into())
+    }
+
+    /// Height in meters of the atmosphere of the body the craft is orbiting. Zero for bodies
+    /// without one.
+    pub fn get_body_atmosphere_depth(&self) -> Result<f64, KerbxError> {
+        let body = self.orbited_body()?.body;
+        Ok(self.sim_feed.mk_call(&body.get_atmosphere_depth())?.into())
+    }
+
+    /// Breaks the craft down by stage. Takes several calls per part, so this is meant for checks
+    /// made before flight rather than for the control loop.
+    fn read_composition(&self) -> Result<VesselComposition, KerbxError> {
+        let parts = self.sim_feed.mk_call(&self.vessel().get_parts())?;
+
+        let mut all = Vec::new();
+        let mut tanks = Vec::new();
+        for part in self.sim_feed.mk_call(&parts.get_all())? {
+            let info = self.part_info(&part)?;
+            let resources = self.sim_feed.mk_call(&part.get_resources())?;
+            let mut fuels = Vec::new();
+            for name in self.sim_feed.mk_call(&resources.get_names())? {
+                if FUEL_RESOURCES.contains(&name.as_str()) {
+                    fuels.push(ResourceAmount {
+                        amount: self.sim_feed.mk_call(&resources.amount(&name))?.into(),
+                        max: self.sim_feed.mk_call(&resources.max(&name))?.into(),
+                        name,
+                    });
+                }
             }
-        };
-        Ok(roll)
+            if !fuels.is_empty() {
+                tanks.push(TankInfo {
+                    part: info.clone(),
+                    resources: fuels,
+                });
+            }
+            all.push(info);
+        }
+
+        let mut engines = Vec::new();
+        for engine in self.sim_feed.mk_call(&parts.get_engines())? {
+            let part = self.sim_feed.mk_call(&engine.get_part())?;
+            engines.push(EngineInfo {
+                part: self.part_info(&part)?,
+                max_thrust: self.sim_feed.mk_call(&engine.get_max_thrust())?.into(),
+                max_vacuum_thrust: self
+                    .sim_feed
+                    .mk_call(&engine.get_max_vacuum_thrust())?
+                    .into(),
+                sea_level_specific_impulse: self
+                    .sim_feed
+                    .mk_call(&engine.get_kerbin_sea_level_specific_impulse())?
+                    .into(),
+                vacuum_specific_impulse: self
+                    .sim_feed
+                    .mk_call(&engine.get_vacuum_specific_impulse())?
+                    .into(),
+                propellants: self.sim_feed.mk_call(&engine.get_propellant_names())?,
+            });
+        }
+
+        let mut decouplers = Vec::new();
+        for decoupler in self.sim_feed.mk_call(&parts.get_decouplers())? {
+            let part = self.sim_feed.mk_call(&decoupler.get_part())?;
+            decouplers.push(self.part_info(&part)?);
+        }
+
+        let mut parachutes = Vec::new();
+        for parachute in self.sim_feed.mk_call(&parts.get_parachutes())? {
+            let part = self.sim_feed.mk_call(&parachute.get_part())?;
+            parachutes.push(self.part_info(&part)?);
+        }
+
+        Ok(VesselComposition::new(
+            all, engines, decouplers, parachutes, tanks,
+        ))
+    }
+
+    /// Reads the details common to every part.
+    fn part_info(&self, part: &Part) -> Result<PartInfo, KerbxError> {
+        Ok(PartInfo {
+            name: self.sim_feed.mk_call(&part.get_name())?,
+            title: self.sim_feed.mk_call(&part.get_title())?,
+            stage: self.sim_feed.mk_call(&part.get_stage())?,
+            decouple_stage: self.sim_feed.mk_call(&part.get_decouple_stage())?,
+            mass: self.sim_feed.mk_call(&part.get_mass())?,
+            dry_mass: self.sim_feed.mk_call(&part.get_dry_mass())?,
+        })
+    }
+
+    /// Flight data relative to the surface of the body, with vectors along up, north and east
+    fn surface_flight(&self) -> Result<Flight, KerbxError> {
+        let orbited = self.orbited_body()?;
+        self.sim_feed
+            .mk_call(&self.vessel().flight(&orbited.surf_velocity_frame))
+    }
+
+    /// Flight data relative to the center of the body, with vectors along up, north and east
+    fn orbital_flight(&self) -> Result<Flight, KerbxError> {
+        let orbited = self.orbited_body()?;
+        self.sim_feed
+            .mk_call(&self.vessel().flight(&orbited.orb_velocity_frame))
+    }
+
+    /// Velocity in m/s relative to the surface, as (up, north, east) in the surface frame
+    pub fn get_surface_velocity(&self) -> Result<Vector3<f64>, KerbxError> {
+        let flight = self.surface_flight()?;
+        let velocity = self.sim_feed.mk_call(&flight.get_velocity())?;
+        Ok(Vector3::new(velocity.0, velocity.1, velocity.2))
+    }
+
+    /// Velocity in m/s relative to the body's center, as (up, north, east) in the surface frame
+    pub fn get_orbital_velocity(&self) -> Result<Vector3<f64>, KerbxError> {
+        let flight = self.orbital_flight()?;
+        let velocity = self.sim_feed.mk_call(&flight.get_velocity())?;
+        Ok(Vector3::new(velocity.0, velocity.1, velocity.2))
+    }
+
+    /// Speed in m/s relative to the surface
+    pub fn get_surface_speed(&self) -> Result<f64, KerbxError> {
+        let flight = self.surface_flight()?;
+        self.sim_feed.mk_call(&flight.get_speed())
+    }
+
+    /// Speed in m/s relative to the body's center
+    pub fn get_orbital_speed(&self) -> Result<f64, KerbxError> {
+        let flight = self.orbital_flight()?;
+        self.sim_feed.mk_call(&flight.get_speed())
+    }
+
+    /// Rate of climb in m/s, negative when descending
+    pub fn get_vertical_speed(&self) -> Result<f64, KerbxError> {
+        let flight = self.surface_flight()?;
+        self.sim_feed.mk_call(&flight.get_vertical_speed())
+    }
+
+    /// Speed in m/s over the ground
+    pub fn get_horizontal_speed(&self) -> Result<f64, KerbxError> {
+        let flight = self.surface_flight()?;
+        self.sim_feed.mk_call(&flight.get_horizontal_speed())
+    }
+
+    /// Speed relative to the speed of sound in the surrounding air
+    pub fn get_mach(&self) -> Result<f64, KerbxError> {
+        let flight = self.surface_flight()?;
+        Ok(self.sim_feed.mk_call(&flight.get_mach())?.into())
+    }
+
+    /// Dynamic pressure in Pa, i.e. q in max-Q
+    pub fn get_dynamic_pressure(&self) -> Result<f64, KerbxError> {
+        let flight = self.surface_flight()?;
+        Ok(self
+            .sim_feed
+            .mk_call(&flight.get_dynamic_pressure())?
+            .into())
+    }
+
+    /// The craft's current orbit
+    fn orbit(&self) -> Result<Orbit, KerbxError> {
+        self.sim_feed.mk_call(&self.vessel().get_orbit())
+    }
+
+    /// Reads every element of orbit, converting angles to degrees
+    fn read_orbit(&self, orbit: &Orbit) -> Result<OrbitalElements, KerbxError> {
+        Ok(OrbitalElements {
+            apoapsis_altitude: self.sim_feed.mk_call(&orbit.get_apoapsis_altitude())?,
+            periapsis_altitude: self.sim_feed.mk_call(&orbit.get_periapsis_altitude())?,
+            time_to_apoapsis: self.sim_feed.mk_call(&orbit.get_time_to_apoapsis())?,
+            time_to_periapsis: self.sim_feed.mk_call(&orbit.get_time_to_periapsis())?,
+            eccentricity: self.sim_feed.mk_call(&orbit.get_eccentricity())?,
+            inclination: self
+                .sim_feed
+                .mk_call(&orbit.get_inclination())?
+                .to_degrees(),
+            semi_major_axis: self.sim_feed.mk_call(&orbit.get_semi_major_axis())?,
+            period: self.sim_feed.mk_call(&orbit.get_period())?,
+            longitude_of_ascending_node: self
+                .sim_feed
+                .mk_call(&orbit.get_longitude_of_ascending_node())?
+                .to_degrees(),
+            argument_of_periapsis: self
+                .sim_feed
+                .mk_call(&orbit.get_argument_of_periapsis())?
+                .to_degrees(),
+        })
     }
-    pub fn get_pitch(&self) -> Result<f64, Error> {
-        // Calculate pitch
+
+    /// Apoapsis in meters above sea level
+    pub fn get_apoapsis_altitude(&self) -> Result<f64, KerbxError> {
+        self.sim_feed
+            .mk_call(&self.orbit()?.get_apoapsis_altitude())
+    }
+
+    /// Periapsis in meters above sea level
+    pub fn get_periapsis_altitude(&self) -> Result<f64, KerbxError> {
+        self.sim_feed
+            .mk_call(&self.orbit()?.get_periapsis_altitude())
+    }
+
+    /// Seconds until the craft reaches apoapsis
+    pub fn get_time_to_apoapsis(&self) -> Result<f64, KerbxError> {
+        self.sim_feed.mk_call(&self.orbit()?.get_time_to_apoapsis())
+    }
+
+    /// Seconds until the craft reaches periapsis
+    pub fn get_time_to_periapsis(&self) -> Result<f64, KerbxError> {
+        self.sim_feed
+            .mk_call(&self.orbit()?.get_time_to_periapsis())
+    }
+
+    pub fn get_eccentricity(&self) -> Result<f64, KerbxError> {
+        self.sim_feed.mk_call(&self.orbit()?.get_eccentricity())
+    }
+
+    /// Inclination in degrees
+    pub fn get_inclination(&self) -> Result<f64, KerbxError> {
+        Ok(self
+            .sim_feed
+            .mk_call(&self.orbit()?.get_inclination())?
+            .to_degrees())
+    }
+
+    /// Semi-major axis in meters
+    pub fn get_semi_major_axis(&self) -> Result<f64, KerbxError> {
+        self.sim_feed.mk_call(&self.orbit()?.get_semi_major_axis())
+    }
+
+    /// Orbital period in seconds
+    pub fn get_orbital_period(&self) -> Result<f64, KerbxError> {
+        self.sim_feed.mk_call(&self.orbit()?.get_period())
+    }
+
+    /// Longitude of the ascending node in degrees
+    pub fn get_longitude_of_ascending_node(&self) -> Result<f64, KerbxError> {
+        Ok(self
+            .sim_feed
+            .mk_call(&self.orbit()?.get_longitude_of_ascending_node())?
+            .to_degrees())
+    }
+
+    /// Argument of periapsis in degrees
+    pub fn get_argument_of_periapsis(&self) -> Result<f64, KerbxError> {
+        Ok(self
+            .sim_feed
+            .mk_call(&self.orbit()?.get_argument_of_periapsis())?
+            .to_degrees())
+    }
+
+    pub fn get_horizon(&self) -> Result<Vector3<f64>, KerbxError> {
         let direction = self.get_direction()?;
-        let pitch = if direction[0] < 0.0 {
-            -self.to_degrees(self.get_horizon()?.angle(&direction))
-        } else {
-            self.to_degrees(self.get_horizon()?.angle(&direction))
-        };
-        Ok(pitch)
+        Ok(Vector3::new(0.0, direction[1], direction[2]))
+    }
+
+    /// Rotation taking vectors in the vessel frame (x right, y nose, z bottom) to the surface
+    /// frame (x up, y north, z east)
+    pub fn get_attitude(&self) -> Result<UnitQuaternion<f64>, KerbxError> {
+        let rotation = self
+            .sim_feed
+            .mk_call(&self.vessel().rotation(&self.surf_ref_frame()))?;
+        Ok(self.to_attitude(rotation))
+    }
+
+    /// Roll, pitch and heading derived from a single attitude reading
+    pub fn get_euler_angles(&self) -> Result<EulerAngles, KerbxError> {
+        Ok(EulerAngles::from_attitude(&self.get_attitude()?))
     }
-    pub fn get_heading(&self) -> Result<f64, Error> {
-        let horizon = self.get_horizon()?;
-        let heading = if horizon[2] < 0.0 {
-            360.0 - self.to_degrees(horizon.angle(&self.north))
+
+    /// Angular velocity in rad/s relative to the surface, along the vessel's own axes: x is the
+    /// pitch rate, y the roll rate and z the yaw rate.
+    pub fn get_angular_velocity(&self) -> Result<Vector3<f64>, KerbxError> {
+        let rate = self
+            .sim_feed
+            .mk_call(&self.vessel().angular_velocity(&self.surf_ref_frame()))?;
+        let rate = Vector3::new(rate.0, rate.1, rate.2);
+        Ok(self.get_attitude()?.inverse_transform_vector(&rate))
+    }
+
+    /// kRPC sends quaternions as (x, y, z, w)
+    fn to_attitude(&self, rotation: (f64, f64, f64, f64)) -> UnitQuaternion<f64> {
+        UnitQuaternion::from_quaternion(Quaternion::new(
+            rotation.3, rotation.0, rotation.1, rotation.2,
+        ))
+    }
+}
+
+impl VehicleSensors for KerbxTransport {
+    /// m/s relative to the surface of the body being orbited
+    fn get_velocity(&self) -> Result<f64, KerbxError> {
+        self.get_surface_speed()
+    }
+
+    fn get_roll(&self) -> Result<f64, KerbxError> {
+        let roll = self.get_euler_angles()?.roll;
+        if roll > -180.0 && roll <= 180.0 {
+            Ok(roll)
         } else {
-            self.to_degrees(horizon.angle(&self.north))
-        };
-        Ok(heading)
+            Err(KerbxError::Contract(String::from(
+                "Roll must be -180 < x <= +180 degrees.",
+            )))
+        }
     }
-    pub fn get_lat(&self) -> Result<f64, Error> {
+    fn get_pitch(&self) -> Result<f64, KerbxError> {
+        Ok(self.get_euler_angles()?.pitch)
+    }
+    fn get_heading(&self) -> Result<f64, KerbxError> {
+        Ok(self.get_euler_angles()?.heading)
+    }
+    fn get_lat(&self) -> Result<f64, KerbxError> {
         let position = self
             .sim_feed
-            .mk_call(&self.vessel_obj.position(&self.orb_ref_frame))?;
-        let lat = self.sim_feed.mk_call(
-            &self
-                .orbiting_obj
-                .latitude_at_position(position, &self.orb_ref_frame),
-        )?;
+            .mk_call(&self.vessel().position(&self.orb_ref_frame()))?;
+        let body = self.orbited_body()?.body;
+        let lat = self
+            .sim_feed
+            .mk_call(&body.latitude_at_position(position, &self.orb_ref_frame()))?;
         Ok(lat)
     }
-    pub fn get_lon(&self) -> Result<f64, Error> {
+    fn get_lon(&self) -> Result<f64, KerbxError> {
         let position = self
             .sim_feed
-            .mk_call(&self.vessel_obj.position(&self.orb_ref_frame))?;
-        let lon = self.sim_feed.mk_call(
-            &self
-                .orbiting_obj
-                .longitude_at_position(position, &self.orb_ref_frame),
-        )?;
+            .mk_call(&self.vessel().position(&self.orb_ref_frame()))?;
+        let body = self.orbited_body()?.body;
+        let lon = self
+            .sim_feed
+            .mk_call(&body.longitude_at_position(position, &self.orb_ref_frame()))?;
         Ok(lon)
     }
-    pub fn get_alt(&self) -> Result<f64, Error> {
+    fn get_alt(&self) -> Result<f64, KerbxError> {
         let position = self
             .sim_feed
-            .mk_call(&self.vessel_obj.position(&self.orb_ref_frame))?;
-        let alt = self.sim_feed.mk_call(
-            &self
-                .orbiting_obj
-                .altitude_at_position(position, &self.orb_ref_frame),
-        )?;
+            .mk_call(&self.vessel().position(&self.orb_ref_frame()))?;
+        let body = self.orbited_body()?.body;
+        let alt = self
+            .sim_feed
+            .mk_call(&body.altitude_at_position(position, &self.orb_ref_frame()))?;
         Ok(alt)
     }
 
-    // TODO: Error passing
-    pub fn trigger_stage(&self) -> Result<(), Error> {
-        let control = self.sim_feed.mk_call(&self.vessel_obj.get_control())?;
-        let result = self.sim_feed.mk_call(&control.activate_next_stage())?;
-        Ok(())
-    }
-
     /// Returns the current stage of the craft as reflected by the in-game UI
-    pub fn get_stage(&self) -> Result<i32, Error> {
-        let control = self.sim_feed.mk_call(&self.vessel_obj.get_control())?;
+    fn get_stage(&self) -> Result<i32, KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
         let stage = self.sim_feed.mk_call(&control.get_current_stage())?;
         Ok(stage)
     }
 
+    fn get_orbit(&self) -> Result<OrbitalElements, KerbxError> {
+        self.read_orbit(&self.orbit()?)
+    }
+
+    fn get_propulsion(&self) -> Result<Propulsion, KerbxError> {
+        let radius = self.sim_feed.mk_call(&self.orbit()?.get_radius())?;
+        Ok(Propulsion {
+            gravity: gravity(self.orbited_body()?.gravitational_parameter, radius),
+            ..self.read_propulsion()?
+        })
+    }
+
+    /// The composition is only read again when the stage changes. The current stage is then
+    /// brought up to date with the vessel's mass and the propellant left in it.
+    fn get_delta_v(&self) -> Result<Vec<StageDeltaV>, KerbxError> {
+        let stage = self.get_stage()?;
+        let stale = match &*self.composition.borrow() {
+            Some((read_at, _)) => *read_at != stage,
+            None => true,
+        };
+        if stale {
+            let composition = self.read_composition()?;
+            self.composition.replace(Some((stage, composition)));
+        }
+
+        let mut delta_v = match &*self.composition.borrow() {
+            Some((_, composition)) => composition.delta_v(stage),
+            None => Vec::new(),
+        };
+        if let Some(current) = delta_v.first_mut() {
+            let mass: f64 = self.sim_feed.mk_call(&self.vessel().get_mass())?.into();
+            let left = self.read_resources(&self.stage_resources(stage)?)?;
+            let propellant: f64 = current
+                .propellants
+                .iter()
+                .filter_map(|name| {
+                    left.get(name)
+                        .map(|level| level.amount * resource_density(name))
+                })
+                .sum();
+            *current = current.with_masses(mass, mass - propellant);
+        }
+        Ok(delta_v)
+    }
+
+    fn get_clock(&self) -> Result<GameClock, KerbxError> {
+        GameClock::new(self.ut()?, self.met()?)
+    }
+
+    fn get_warp(&self) -> Result<WarpState, KerbxError> {
+        let mode = self.sim_feed.mk_call(&space_center::get_warp_mode())?;
+        let mode = if matches!(mode, space_center::WarpMode::Rails) {
+            WarpMode::Rails
+        } else if matches!(mode, space_center::WarpMode::Physics) {
+            WarpMode::Physics
+        } else {
+            WarpMode::None
+        };
+        Ok(WarpState {
+            mode,
+            factor: self.warp_factor()?,
+            rate: self.warp_rate()?,
+        })
+    }
+
+    fn get_maneuver_node(&self) -> Result<Option<ManeuverNode>, KerbxError> {
+        match self.nodes()?.first() {
+            Some(node) => Ok(Some(self.read_node(node)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_composition(&self) -> Result<Option<VesselComposition>, KerbxError> {
+        Ok(Some(self.read_composition()?))
+    }
+
+    fn get_resources(&self) -> Result<VesselResources, KerbxError> {
+        let vessel = self.sim_feed.mk_call(&self.vessel().get_resources())?;
+        let stage = self.stage_resources(self.get_stage()?)?;
+        Ok(VesselResources {
+            vessel: self.read_resources(&vessel)?,
+            stage: self.read_resources(&stage)?,
+        })
+    }
+
+    /// Uses the latest streamed values when streams are enabled, so every field comes from the
+    /// same physics frame and no RPC is made. Otherwise falls back to calling each getter.
+    fn snapshot(&self) -> Result<VehicleState, KerbxError> {
+        // Streamed values stop updating when the link drops, so they must not be reported
+        if self.sim_feed.status() == LinkStatus::Degraded || !self.streams_connected() {
+            self.sim_feed.mark_down();
+            return Err(KerbxError::LinkDown);
+        }
+
+        let (streamed, gravitational_parameter) = match &*self.streamed.borrow() {
+            Some(streamed) => {
+                let mut streams = streamed.lock().unwrap();
+                if streams.values.body.as_ref() != Some(&streams.body) {
+                    self.restream_body(&mut streams)?;
+                }
+                if streams.values.stage != streams.stage {
+                    self.restream_stage(&mut streams)?;
+                }
+                (streams.values.clone(), streams.gravitational_parameter)
+            }
+            None => return vehicle::read_each(self),
+        };
+
+        let angles = EulerAngles::from_attitude(&self.to_attitude(streamed.rotation));
+        Ok(VehicleState {
+            lat: streamed.lat,
+            lon: streamed.lon,
+            alt: streamed.alt,
+            roll: angles.roll,
+            pitch: angles.pitch,
+            heading: angles.heading,
+            velocity: streamed.velocity,
+            stage: streamed.stage,
+            orbit: streamed.orbit,
+            resources: streamed.resources,
+            propulsion: Propulsion {
+                gravity: gravity(gravitational_parameter, streamed.radius),
+                ..streamed.propulsion
+            },
+        })
+    }
+
+    /// Re-establishes a dropped link once its backoff has elapsed, then resolves the vessel
+    /// and its frames again and resubscribes to the streams.
+    fn check_link(&self) -> LinkStatus {
+        if !self.streams_connected() {
+            self.sim_feed.mark_down();
+        }
+        if self.sim_feed.reconnect() {
+            match self.rebind() {
+                Ok(()) => self.sim_feed.confirm(),
+                // e.g. KSP is still loading the flight scene. Try again after a longer backoff.
+                Err(_) => self.sim_feed.mark_rebind_failed(),
+            }
+        }
+        self.sim_feed.status()
+    }
+
+    /// Reads the RemoteTech link. The ground station is the one targeted by the first
+    /// connected antenna; omnidirectional antennas have no target of their own.
+    fn get_comms(&self) -> Result<CommsStatus, KerbxError> {
+        if !self.sim_feed.mk_call(&remote_tech::get_available())? {
+            return Ok(CommsStatus::default());
+        }
+        let comms = self.sim_feed.mk_call(&remote_tech::comms(&self.vessel()))?;
+        let mut ground_station = None;
+        for antenna in self.sim_feed.mk_call(&comms.get_antennas())? {
+            if self.sim_feed.mk_call(&antenna.get_has_connection())?
+                && matches!(
+                    self.sim_feed.mk_call(&antenna.get_target())?,
+                    remote_tech::Target::GroundStation
+                )
+            {
+                ground_station = Some(
+                    self.sim_feed
+                        .mk_call(&antenna.get_target_ground_station())?,
+                );
+                break;
+            }
+        }
+        Ok(CommsStatus {
+            remote_tech: true,
+            connected: self.sim_feed.mk_call(&comms.get_has_connection())?,
+            ground_station_link: self
+                .sim_feed
+                .mk_call(&comms.get_has_connection_to_ground_station())?,
+            local_control: self.sim_feed.mk_call(&comms.get_has_local_control())?,
+            signal_delay: self
+                .sim_feed
+                .mk_call(&comms.get_signal_delay_to_ground_station())?,
+            ground_station,
+        })
+    }
+}
+
+impl VehicleActuators for KerbxTransport {
+    fn trigger_stage(&self) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        let separated = self.sim_feed.mk_call(&control.activate_next_stage())?;
+        self.separated.replace(separated);
+        Ok(())
+    }
+
     /// percent must be a value between 0 and 1.
-    #[requires(percent >= 0.0 && percent <= 1.0, "Throttle percent only valid between 0 and 1.")]
-    pub fn set_throttle(&self, percent: f32) -> Result<(), Error> {
-        let control = self.sim_feed.mk_call(&self.vessel_obj.get_control())?;
+    fn set_throttle(&self, percent: f32) -> Result<(), KerbxError> {
+        if !(0.0..=1.0).contains(&percent) {
+            return Err(KerbxError::Contract(String::from(
+                "Throttle percent only valid between 0 and 1.",
+            )));
+        }
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
         self.sim_feed.mk_call(&control.set_throttle(percent))?;
         Ok(())
     }
 
     /// Turn on/off autopilot.
     /// setting: if true, turns on autopilot; if false, turns off autopilot
-    pub fn set_auto_pilot(&self, setting: bool) -> Result<(), Error> {
-        let autopilot = self.sim_feed.mk_call(&self.vessel_obj.get_auto_pilot())?;
+    fn set_auto_pilot(&self, setting: bool) -> Result<(), KerbxError> {
+        let autopilot = self.sim_feed.mk_call(&self.vessel().get_auto_pilot())?;
         if setting {
             self.sim_feed.mk_call(&autopilot.engage())?;
         } else {
@@ -234,31 +1479,176 @@ impl KerbxTransport {
     /// stabilizes flight.
     /// pitch: Wanted pitch
     /// heading: Wanted heading
-    pub fn set_auto_pilot_direction(&self, pitch: f32, heading: f32) -> Result<(), Error> {
-        let autopilot = self.sim_feed.mk_call(&self.vessel_obj.get_auto_pilot())?;
+    fn set_auto_pilot_direction(&self, pitch: f32, heading: f32) -> Result<(), KerbxError> {
+        let autopilot = self.sim_feed.mk_call(&self.vessel().get_auto_pilot())?;
         self.sim_feed
             .mk_call(&autopilot.target_pitch_and_heading(pitch, heading))?;
         Ok(())
     }
 
+    /// Sets the six pilot inputs on the vessel's control, one call each.
+    fn set_control_inputs(&self, inputs: ControlInputs) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_pitch(inputs.pitch))?;
+        self.sim_feed.mk_call(&control.set_yaw(inputs.yaw))?;
+        self.sim_feed.mk_call(&control.set_roll(inputs.roll))?;
+        self.sim_feed
+            .mk_call(&control.set_forward(inputs.forward))?;
+        self.sim_feed.mk_call(&control.set_up(inputs.up))?;
+        self.sim_feed.mk_call(&control.set_right(inputs.right))?;
+        Ok(())
+    }
+
     /// Turn on/off sas on craft.
     /// setting: if true, turns on sas; if false, turns off sas
-    pub fn set_sas(&self, setting: bool) -> Result<(), Error> {
-        let control = self.sim_feed.mk_call(&self.vessel_obj.get_control())?;
+    fn set_sas(&self, setting: bool) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
         self.sim_feed.mk_call(&control.set_sas(setting))?;
         Ok(())
     }
 
+    fn set_sas_mode(&self, mode: SasMode) -> Result<(), KerbxError> {
+        let mode = match mode {
+            SasMode::StabilityAssist => SASMode::StabilityAssist,
+            SasMode::Maneuver => SASMode::Maneuver,
+            SasMode::Prograde => SASMode::Prograde,
+            SasMode::Retrograde => SASMode::Retrograde,
+            SasMode::Normal => SASMode::Normal,
+            SasMode::AntiNormal => SASMode::AntiNormal,
+            SasMode::Radial => SASMode::Radial,
+            SasMode::AntiRadial => SASMode::AntiRadial,
+            SasMode::Target => SASMode::Target,
+            SasMode::AntiTarget => SASMode::AntiTarget,
+        };
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_sas_mode(mode))?;
+        Ok(())
+    }
+
+    fn set_speed_mode(&self, mode: SpeedMode) -> Result<(), KerbxError> {
+        let mode = match mode {
+            SpeedMode::Orbit => space_center::SpeedMode::Orbit,
+            SpeedMode::Surface => space_center::SpeedMode::Surface,
+            SpeedMode::Target => space_center::SpeedMode::Target,
+        };
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_speed_mode(mode))?;
+        Ok(())
+    }
+
     /// Turn on/off rcs on craft.
     /// /// setting: if true, turns on rcs; if false, turns off rcs
-    pub fn set_rcs(&self, setting: bool) -> Result<(), Error> {
-        let control = self.sim_feed.mk_call(&self.vessel_obj.get_control())?;
+    fn set_rcs(&self, setting: bool) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
         self.sim_feed.mk_call(&control.set_rcs(setting))?;
         Ok(())
     }
 
-    fn to_degrees(&self, val: f64) -> f64 {
-        val * (180.0 / std::f64::consts::PI)
+    /// kRPC numbers the groups by their keys, so group 10 is group 0 there.
+    fn set_action_group(&self, group: u32, setting: bool) -> Result<(), KerbxError> {
+        if !(1..=ACTION_GROUPS).contains(&group) {
+            return Err(KerbxError::Contract(format!(
+                "Action group {} does not exist; groups run from 1 to {}.",
+                group, ACTION_GROUPS
+            )));
+        }
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed
+            .mk_call(&control.set_action_group(group % ACTION_GROUPS, setting))?;
+        Ok(())
+    }
+
+    fn set_gear(&self, setting: bool) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_gear(setting))?;
+        Ok(())
+    }
+
+    fn set_brakes(&self, setting: bool) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_brakes(setting))?;
+        Ok(())
+    }
+
+    fn set_lights(&self, setting: bool) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_lights(setting))?;
+        Ok(())
+    }
+
+    fn abort(&self) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_abort(true))?;
+        Ok(())
+    }
+
+    fn arm_parachutes(&self, deploy_altitude: f32, min_pressure: f32) -> Result<(), KerbxError> {
+        for parachute in self.parachutes()? {
+            self.sim_feed
+                .mk_call(&parachute.set_deploy_altitude(deploy_altitude))?;
+            self.sim_feed
+                .mk_call(&parachute.set_deploy_min_pressure(min_pressure))?;
+            self.sim_feed.mk_call(&parachute.arm())?;
+        }
+        Ok(())
+    }
+
+    fn deploy_parachutes(&self) -> Result<(), KerbxError> {
+        for parachute in self.parachutes()? {
+            self.sim_feed.mk_call(&parachute.deploy())?;
+        }
+        Ok(())
+    }
+
+    fn set_solar_panels(&self, setting: bool) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_solar_panels(setting))?;
+        Ok(())
+    }
+
+    fn set_antennas(&self, setting: bool) -> Result<(), KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        self.sim_feed.mk_call(&control.set_antennas(setting))?;
+        Ok(())
+    }
+
+    fn move_servos(&self, servos: &Servos, position: f32, speed: f32) -> Result<(), KerbxError> {
+        for servo in self.find_servos(servos)? {
+            self.sim_feed.mk_call(&servo.move_to(position, speed))?;
+        }
+        Ok(())
+    }
+
+    /// Rails warp is capped at the highest factor KSP allows where the vessel is, so low in the
+    /// atmosphere it may not warp at all.
+    fn set_warp(&self, mode: WarpMode, factor: u32) -> Result<(), KerbxError> {
+        let (rails, physics) = match mode {
+            WarpMode::Rails => (factor.min(self.max_rails_warp_factor()?), 0),
+            WarpMode::Physics => (0, factor.min(mode.rates().len() as u32 - 1)),
+            WarpMode::None => (0, 0),
+        };
+        // Only one kind of warp runs at a time, so the other is ended first
+        if rails == 0 {
+            self.sim_feed
+                .mk_call(&space_center::set_rails_warp_factor(0))?;
+            self.sim_feed
+                .mk_call(&space_center::set_physics_warp_factor(physics as i32))?;
+        } else {
+            self.sim_feed
+                .mk_call(&space_center::set_physics_warp_factor(0))?;
+            self.sim_feed
+                .mk_call(&space_center::set_rails_warp_factor(rails as i32))?;
+        }
+        Ok(())
+    }
+
+    fn remove_maneuver_node(&self) -> Result<(), KerbxError> {
+        match self.nodes()?.first() {
+            Some(node) => self.remove_node(node),
+            None => Err(KerbxError::Contract(String::from(
+                "Vessel has no maneuver node to remove.",
+            ))),
+        }
     }
 }
 
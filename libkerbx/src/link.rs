@@ -0,0 +1,138 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+use crate::vehicle::LinkStatus;
+use crate::KerbxError;
+use krpc_mars::codec::RPCExtractable;
+use krpc_mars::{CallHandle, RPCClient};
+use std::cell::{Cell, Ref, RefCell};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Wait before the first attempt to re-establish a dropped link
+pub const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Longest wait between attempts to re-establish a dropped link
+pub const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// Connection to the kRPC server. Once the connection drops every call fails fast with
+/// `KerbxError::LinkDown` until `reconnect` has re-established it.
+pub(crate) struct Link {
+    client: RefCell<RPCClient>,
+    /// Client name and server address to reconnect with. None when the link was made from an
+    /// existing client, in which case it cannot be re-established.
+    endpoint: Option<(String, SocketAddr)>,
+    status: Cell<LinkStatus>,
+    /// Wait before the next attempt, doubled after each failed one
+    backoff: Cell<Duration>,
+    retry_at: Cell<Instant>,
+}
+
+impl Link {
+    pub(crate) fn new(client: RPCClient, endpoint: Option<(String, SocketAddr)>) -> Link {
+        Link {
+            client: RefCell::new(client),
+            endpoint,
+            status: Cell::new(LinkStatus::Nominal),
+            backoff: Cell::new(INITIAL_BACKOFF),
+            retry_at: Cell::new(Instant::now()),
+        }
+    }
+
+    /// As RPCClient::mk_call. A call that fails because the connection dropped marks the link
+    /// Degraded.
+    pub(crate) fn mk_call<T: RPCExtractable>(&self, call: &CallHandle<T>) -> Result<T, KerbxError> {
+        if self.status.get() == LinkStatus::Degraded {
+            return Err(KerbxError::LinkDown);
+        }
+
+        let result = self.client.borrow().mk_call(call);
+        result.map_err(|error| {
+            let error = KerbxError::from(error);
+            if error.is_link_failure() {
+                self.mark_down();
+            }
+            error
+        })
+    }
+
+    /// The underlying client, e.g. to open a stream connection with
+    pub(crate) fn client(&self) -> Ref<'_, RPCClient> {
+        self.client.borrow()
+    }
+
+    pub(crate) fn status(&self) -> LinkStatus {
+        self.status.get()
+    }
+
+    /// Marks the link Degraded, scheduling a reconnection attempt after the current backoff
+    pub(crate) fn mark_down(&self) {
+        if self.status.get() == LinkStatus::Nominal {
+            self.status.set(LinkStatus::Degraded);
+            self.retry_at.set(Instant::now() + self.backoff.get());
+        }
+    }
+
+    /// Attempts to re-establish a Degraded link if its backoff has elapsed. Returns true only
+    /// when a new connection was made, in which case every object handle from the old one must
+    /// be resolved again. The backoff is kept until the caller confirms the link.
+    pub(crate) fn reconnect(&self) -> bool {
+        let (name, address) = match &self.endpoint {
+            Some(endpoint) if self.status.get() == LinkStatus::Degraded => endpoint,
+            _ => return false,
+        };
+        if Instant::now() < self.retry_at.get() {
+            return false;
+        }
+
+        match RPCClient::connect(name, address) {
+            Ok(client) => {
+                self.client.replace(client);
+                self.status.set(LinkStatus::Nominal);
+                true
+            }
+            Err(_) => {
+                self.retry_later();
+                false
+            }
+        }
+    }
+
+    /// Resets the backoff once the handles from a reconnect have been resolved again
+    pub(crate) fn confirm(&self) {
+        self.backoff.set(INITIAL_BACKOFF);
+    }
+
+    /// Marks a reconnected link Degraded again because its handles could not be resolved, e.g.
+    /// KSP is still loading the flight scene, backing off further before the next attempt
+    pub(crate) fn mark_rebind_failed(&self) {
+        self.status.set(LinkStatus::Degraded);
+        self.retry_later();
+    }
+
+    fn retry_later(&self) {
+        let backoff = (self.backoff.get() * 2).min(MAX_BACKOFF);
+        self.backoff.set(backoff);
+        self.retry_at.set(Instant::now() + backoff);
+    }
+}
@@ -0,0 +1,113 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Maneuver nodes of the vessel being flown, through the SpaceCenter service. A node is a burn
+//! planned for a Universal Time, given as delta-v along the prograde, normal and radial
+//! directions of the orbit there. The avionics flies the next node on an EXECUTENODE step
+//! through `VehicleSensors::get_maneuver_node`; the calls here are for tools planning burns.
+
+use crate::space_center::{Control, Node};
+use crate::vehicle::{ManeuverNode, VehicleSensors};
+use crate::{KerbxError, KerbxTransport};
+use nalgebra::Vector3;
+
+impl KerbxTransport {
+    /// Adds a maneuver node at Universal Time ut with the given delta-v in m/s
+    pub fn add_node(
+        &self,
+        ut: f64,
+        prograde: f64,
+        normal: f64,
+        radial: f64,
+    ) -> Result<Node, KerbxError> {
+        self.sim_feed.mk_call(&self.control()?.add_node(
+            ut,
+            prograde as f32,
+            normal as f32,
+            radial as f32,
+        ))
+    }
+
+    /// Maneuver nodes of the vessel, soonest first
+    pub fn nodes(&self) -> Result<Vec<Node>, KerbxError> {
+        self.sim_feed.mk_call(&self.control()?.get_nodes())
+    }
+
+    /// Moves node to Universal Time ut
+    pub fn set_node_ut(&self, node: &Node, ut: f64) -> Result<(), KerbxError> {
+        self.sim_feed.mk_call(&node.set_ut(ut))
+    }
+
+    /// Replaces the delta-v of node, in m/s
+    pub fn set_node_delta_v(
+        &self,
+        node: &Node,
+        prograde: f64,
+        normal: f64,
+        radial: f64,
+    ) -> Result<(), KerbxError> {
+        self.sim_feed.mk_call(&node.set_prograde(prograde))?;
+        self.sim_feed.mk_call(&node.set_normal(normal))?;
+        self.sim_feed.mk_call(&node.set_radial(radial))
+    }
+
+    /// Deletes node
+    pub fn remove_node(&self, node: &Node) -> Result<(), KerbxError> {
+        self.sim_feed.mk_call(&node.remove())
+    }
+
+    /// Removes every maneuver node of the vessel
+    pub fn remove_nodes(&self) -> Result<(), KerbxError> {
+        self.sim_feed.mk_call(&self.control()?.remove_nodes())
+    }
+
+    /// Delta-v in m/s still to burn for node, in the surface frame (x up, y north, z east)
+    pub fn remaining_burn_vector(&self, node: &Node) -> Result<Vector3<f64>, KerbxError> {
+        let burn = self
+            .sim_feed
+            .mk_call(&node.remaining_burn_vector(&self.surf_ref_frame()))?;
+        Ok(Vector3::new(burn.0, burn.1, burn.2))
+    }
+
+    /// Seconds at full throttle to burn what is left of node, at the thrust and mass the
+    /// vessel has now
+    pub fn burn_time(&self, node: &Node) -> Result<f64, KerbxError> {
+        let remaining = self.sim_feed.mk_call(&node.get_remaining_delta_v())?;
+        Ok(self.get_propulsion()?.burn_time(remaining))
+    }
+
+    /// Reads the time, planned delta-v and remaining burn of node
+    pub fn read_node(&self, node: &Node) -> Result<ManeuverNode, KerbxError> {
+        Ok(ManeuverNode {
+            ut: self.sim_feed.mk_call(&node.get_ut())?,
+            prograde: self.sim_feed.mk_call(&node.get_prograde())?,
+            normal: self.sim_feed.mk_call(&node.get_normal())?,
+            radial: self.sim_feed.mk_call(&node.get_radial())?,
+            remaining: self.remaining_burn_vector(node)?,
+        })
+    }
+
+    fn control(&self) -> Result<Control, KerbxError> {
+        self.sim_feed.mk_call(&self.vessel().get_control())
+    }
+}
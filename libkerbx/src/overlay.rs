@@ -0,0 +1,324 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Lines drawn in the KSP scene through the Drawing service, so the flight plan and how well it
+//! is being flown can be seen in game. The plan's waypoints are drawn once, fixed to the surface
+//! of the body; the velocity, heading and commanded vectors follow the vessel and are redrawn on
+//! every update.
+
+use crate::drawing::{self, Line};
+use crate::kerbx::{FlightPlan, Trigger_oneof_trigger_condition};
+use crate::space_center::{CelestialBody, ReferenceFrame};
+use crate::vehicle::VehicleSensors;
+use crate::{KerbxError, KerbxTransport};
+use nalgebra::Vector3;
+use std::time::{Duration, Instant};
+
+/// How often the vectors are redrawn. Each redraw takes several calls.
+const REFRESH: Duration = Duration::from_millis(100);
+/// Length in m of the vectors drawn from the vessel
+const VECTOR_LENGTH: f32 = 20.0;
+/// Height in m of the poles marking position triggers
+const MARKER_HEIGHT: f64 = 500.0;
+
+const PATH_COLOR: (f64, f64, f64) = (0.0, 0.6, 1.0);
+const MARKER_COLOR: (f64, f64, f64) = (1.0, 0.0, 1.0);
+const VELOCITY_COLOR: (f64, f64, f64) = (1.0, 1.0, 0.0);
+const HEADING_COLOR: (f64, f64, f64) = (0.0, 1.0, 0.0);
+const COMMANDED_COLOR: (f64, f64, f64) = (1.0, 0.0, 0.0);
+
+/// A point of the planned path on the body's surface
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Waypoint {
+    pub lat: f64,
+    pub lon: f64,
+    /// Meters above sea level
+    pub alt: f64,
+}
+
+/// Waypoints the triggers of the plan describe, starting from launch. Position triggers move the
+/// path over the ground and altitude triggers move it up or down, so this is the route the plan
+/// asks for rather than a prediction of the trajectory. Other triggers add no waypoint.
+pub fn planned_path(plan: &FlightPlan, launch: Waypoint) -> Vec<Waypoint> {
+    let mut path = vec![launch];
+    let mut at = launch;
+    for step in plan.steps.iter() {
+        match &step.get_trigger().trigger_condition {
+            Some(Trigger_oneof_trigger_condition::position(position)) => {
+                at.lat = position.get_lat();
+                at.lon = position.get_lon();
+            }
+            Some(Trigger_oneof_trigger_condition::alt(alt)) => at.alt = *alt,
+            _ => continue,
+        }
+        path.push(at);
+    }
+    path
+}
+
+/// Lines drawn for one vessel. Dropping the overlay leaves the lines in the scene; call clear
+/// to remove them.
+pub struct Overlay {
+    transport: KerbxTransport,
+    /// Planned path and position trigger markers
+    plan: Vec<Line>,
+    vectors: Option<Vectors>,
+    updated: Option<Instant>,
+}
+
+/// Lines drawn from the vessel along its velocity, its nose and the commanded direction
+struct Vectors {
+    velocity: Line,
+    heading: Line,
+    commanded: Line,
+}
+
+impl Overlay {
+    /// Draws for the vessel the transport flies. Nothing is drawn until the plan or vectors are.
+    pub fn new(transport: KerbxTransport) -> Overlay {
+        Overlay {
+            transport,
+            plan: Vec::new(),
+            vectors: None,
+            updated: None,
+        }
+    }
+
+    /// Draws the path the plan describes from where the vessel is now, along with a pole at each
+    /// position trigger. Replaces any plan drawn before.
+    pub fn draw_flight_plan(&mut self, plan: &FlightPlan) -> Result<(), KerbxError> {
+        self.remove_plan()?;
+        let transport = &self.transport;
+        let launch = Waypoint {
+            lat: transport.get_lat()?,
+            lon: transport.get_lon()?,
+            alt: transport.get_alt()?,
+        };
+
+        let path = planned_path(plan, launch);
+        for leg in path.windows(2) {
+            let line = self.line_between(&leg[0], &leg[1])?;
+            self.style(&line, PATH_COLOR)?;
+            self.plan.push(line);
+        }
+
+        for step in plan.steps.iter() {
+            let trigger = step.get_trigger();
+            if !trigger.has_position() {
+                continue;
+            }
+            let position = trigger.get_position();
+            let (lat, lon) = (position.get_lat(), position.get_lon());
+            let ground = self
+                .transport
+                .sim_feed
+                .mk_call(&self.body().surface_height(lat, lon))?;
+            let marker = self.line_between(
+                &Waypoint {
+                    lat,
+                    lon,
+                    alt: ground,
+                },
+                &Waypoint {
+                    lat,
+                    lon,
+                    alt: ground + MARKER_HEIGHT,
+                },
+            )?;
+            self.style(&marker, MARKER_COLOR)?;
+            self.plan.push(marker);
+        }
+        Ok(())
+    }
+
+    /// Redraws the vectors from the vessel. commanded: Pitch and heading in degrees the
+    /// avionics is steering for, if any; the commanded line is hidden otherwise. Calls more
+    /// often than every REFRESH do nothing, so this can be called every tick.
+    pub fn update(&mut self, commanded: Option<(f32, f32)>) -> Result<(), KerbxError> {
+        if let Some(updated) = self.updated {
+            if updated.elapsed() < REFRESH {
+                return Ok(());
+            }
+        }
+        self.updated = Some(Instant::now());
+
+        if self.vectors.is_none() {
+            self.vectors = Some(Vectors {
+                velocity: self.add_vector(VELOCITY_COLOR)?,
+                heading: self.add_vector(HEADING_COLOR)?,
+                commanded: self.add_vector(COMMANDED_COLOR)?,
+            });
+        }
+        let vectors = self.vectors.as_ref().unwrap();
+        let link = &self.transport.sim_feed;
+
+        // A vessel at rest has no direction of travel
+        let velocity = self.transport.get_surface_velocity()?;
+        let moving = velocity.norm() > 0.1;
+        link.mk_call(&vectors.velocity.set_visible(moving))?;
+        if moving {
+            link.mk_call(&vectors.velocity.set_end(end(&velocity.normalize())))?;
+        }
+
+        let heading = self.transport.get_direction()?;
+        link.mk_call(&vectors.heading.set_end(end(&heading)))?;
+
+        link.mk_call(&vectors.commanded.set_visible(commanded.is_some()))?;
+        if let Some((pitch, heading)) = commanded {
+            link.mk_call(&vectors.commanded.set_end(end(&direction(pitch, heading))))?;
+        }
+        Ok(())
+    }
+
+    /// Removes every line the overlay drew
+    pub fn clear(&mut self) -> Result<(), KerbxError> {
+        self.remove_plan()?;
+        if let Some(vectors) = self.vectors.take() {
+            for line in [vectors.velocity, vectors.heading, vectors.commanded].iter() {
+                self.transport.sim_feed.mk_call(&line.remove())?;
+            }
+        }
+        self.updated = None;
+        Ok(())
+    }
+
+    fn remove_plan(&mut self) -> Result<(), KerbxError> {
+        for line in self.plan.drain(..) {
+            self.transport.sim_feed.mk_call(&line.remove())?;
+        }
+        Ok(())
+    }
+
+    /// Line from the vessel in its surface frame, so it moves along with it
+    fn add_vector(&self, color: (f64, f64, f64)) -> Result<Line, KerbxError> {
+        let line = self.transport.sim_feed.mk_call(&drawing::add_direction(
+            (1.0, 0.0, 0.0),
+            &self.transport.surf_ref_frame(),
+            VECTOR_LENGTH,
+            true,
+        ))?;
+        self.style(&line, color)?;
+        Ok(line)
+    }
+
+    fn line_between(&self, from: &Waypoint, to: &Waypoint) -> Result<Line, KerbxError> {
+        let frame = self.body_frame()?;
+        let start = self.position(from)?;
+        let end = self.position(to)?;
+        self.transport
+            .sim_feed
+            .mk_call(&drawing::add_line(start, end, &frame, true))
+    }
+
+    fn style(&self, line: &Line, color: (f64, f64, f64)) -> Result<(), KerbxError> {
+        self.transport.sim_feed.mk_call(&line.set_color(color))?;
+        self.transport.sim_feed.mk_call(&line.set_thickness(0.5))?;
+        Ok(())
+    }
+
+    /// Position of a waypoint in the body's rotating frame
+    fn position(&self, waypoint: &Waypoint) -> Result<(f64, f64, f64), KerbxError> {
+        self.transport
+            .sim_feed
+            .mk_call(&self.body().position_at_altitude(
+                waypoint.lat,
+                waypoint.lon,
+                waypoint.alt,
+                &self.body_frame()?,
+            ))
+    }
+
+    /// Frame fixed to the surface of the body being orbited, so the plan turns with it
+    fn body_frame(&self) -> Result<ReferenceFrame, KerbxError> {
+        self.transport
+            .sim_feed
+            .mk_call(&self.body().get_reference_frame())
+    }
+
+    fn body(&self) -> CelestialBody {
+        self.transport.orbited.borrow().body.clone()
+    }
+}
+
+/// Far end of a vector from the vessel, scaled to VECTOR_LENGTH
+fn end(direction: &Vector3<f64>) -> (f64, f64, f64) {
+    let tip = direction * f64::from(VECTOR_LENGTH);
+    (tip[0], tip[1], tip[2])
+}
+
+/// Unit vector in the surface frame (x up, y north, z east) for a pitch and heading in degrees
+fn direction(pitch: f32, heading: f32) -> Vector3<f64> {
+    let pitch = f64::from(pitch).to_radians();
+    let heading = f64::from(heading).to_radians();
+    Vector3::new(
+        pitch.sin(),
+        pitch.cos() * heading.cos(),
+        pitch.cos() * heading.sin(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flightplan::*;
+    use crate::kerbx::Step_ActionType;
+
+    #[test]
+    fn path_follows_position_and_altitude_triggers() {
+        let launch = Waypoint {
+            lat: -0.1,
+            lon: -74.6,
+            alt: 70.0,
+        };
+        let plan = gen_flightplan_from_steps(vec![
+            gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+            gen_reorient_step(2, 0.0, 80.0, 90.0, gen_alt_trigger(1_000.0)),
+            gen_throttle_step(3, 0.5, gen_apoapsis_trigger(80_000.0)),
+            gen_reorient_step(4, 0.0, 45.0, 90.0, gen_pos_trigger(-0.1, -74.0)),
+        ]);
+
+        let path = planned_path(&plan, launch);
+        assert_eq!(
+            path,
+            vec![
+                launch,
+                Waypoint {
+                    alt: 1_000.0,
+                    ..launch
+                },
+                Waypoint {
+                    lon: -74.0,
+                    ..path[1]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn commanded_direction_points_along_pitch_and_heading() {
+        let east = direction(0.0, 90.0);
+        assert!((east - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-9);
+        let up = direction(90.0, 0.0);
+        assert!((up - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-9);
+    }
+}
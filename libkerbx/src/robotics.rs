@@ -0,0 +1,136 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Infernal Robotics servos of the vessel being flown. Servos and servo groups are found by the
+//! name shown in game, so flight plans can refer to them without kRPC handles.
+
+use crate::infernal_robotics::{self, Servo, ServoGroup};
+use crate::vehicle::Servos;
+use crate::{KerbxError, KerbxTransport};
+
+/// A servo as last read from the game
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServoReading {
+    pub name: String,
+    /// Degrees for rotatrons, meters for extendatrons
+    pub position: f32,
+    /// Travel limits, in the units of position
+    pub min_position: f32,
+    pub max_position: f32,
+    /// Speed the servo was configured with in the editor
+    pub config_speed: f32,
+    pub moving: bool,
+    pub locked: bool,
+}
+
+/// A servo group and the servos in it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServoGroupReading {
+    pub name: String,
+    pub servos: Vec<ServoReading>,
+}
+
+impl KerbxTransport {
+    /// True if Infernal Robotics is installed and reachable through kRPC
+    pub fn robotics_available(&self) -> Result<bool, KerbxError> {
+        self.sim_feed.mk_call(&infernal_robotics::get_available())
+    }
+
+    /// Lists the servo groups of the vessel being flown and every servo in them
+    pub fn servo_groups(&self) -> Result<Vec<ServoGroupReading>, KerbxError> {
+        let mut groups = Vec::new();
+        for group in self.groups()? {
+            let mut servos = Vec::new();
+            for servo in self.sim_feed.mk_call(&group.get_servos())? {
+                servos.push(self.read_servo(&servo)?);
+            }
+            groups.push(ServoGroupReading {
+                name: self.sim_feed.mk_call(&group.get_name())?,
+                servos,
+            });
+        }
+        Ok(groups)
+    }
+
+    /// Reads the servo with the given name. Returns None if the vessel has none.
+    pub fn servo(&self, name: &str) -> Result<Option<ServoReading>, KerbxError> {
+        for group in self.groups()? {
+            for servo in self.sim_feed.mk_call(&group.get_servos())? {
+                if self.sim_feed.mk_call(&servo.get_name())? == name {
+                    return self.read_servo(&servo).map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every servo the move applies to. Returns a Contract error if the vessel has no servo or
+    /// servo group with that name.
+    pub(crate) fn find_servos(&self, servos: &Servos) -> Result<Vec<Servo>, KerbxError> {
+        let mut found = Vec::new();
+        for group in self.groups()? {
+            let group_name = self.sim_feed.mk_call(&group.get_name())?;
+            for servo in self.sim_feed.mk_call(&group.get_servos())? {
+                let wanted = match servos {
+                    Servos::Group(name) => *name == group_name,
+                    Servos::Servo(name) => *name == self.sim_feed.mk_call(&servo.get_name())?,
+                };
+                if wanted {
+                    found.push(servo);
+                }
+            }
+        }
+
+        if found.is_empty() {
+            return Err(KerbxError::Contract(match servos {
+                Servos::Servo(name) => format!("No servo {} on the vessel.", name),
+                Servos::Group(name) => format!("No servo group {} on the vessel.", name),
+            }));
+        }
+        Ok(found)
+    }
+
+    /// Servo groups of the vessel being flown. Returns a ModUnavailable error if Infernal
+    /// Robotics is missing.
+    fn groups(&self) -> Result<Vec<ServoGroup>, KerbxError> {
+        if !self.robotics_available()? {
+            return Err(KerbxError::ModUnavailable(String::from(
+                "Infernal Robotics",
+            )));
+        }
+        self.sim_feed
+            .mk_call(&infernal_robotics::servo_groups(&self.vessel()))
+    }
+
+    fn read_servo(&self, servo: &Servo) -> Result<ServoReading, KerbxError> {
+        Ok(ServoReading {
+            name: self.sim_feed.mk_call(&servo.get_name())?,
+            position: self.sim_feed.mk_call(&servo.get_position())?,
+            min_position: self.sim_feed.mk_call(&servo.get_min_position())?,
+            max_position: self.sim_feed.mk_call(&servo.get_max_position())?,
+            config_speed: self.sim_feed.mk_call(&servo.get_config_speed())?,
+            moving: self.sim_feed.mk_call(&servo.get_is_moving())?,
+            locked: self.sim_feed.mk_call(&servo.get_is_locked())?,
+        })
+    }
+}
@@ -0,0 +1,234 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Mission timeline kept in Kerbal Alarm Clock. Alarms are set for upcoming flight plan steps,
+//! maneuver nodes and sphere of influence changes so the in-game clock, and time warp, stop for
+//! them. Only alarms KerbX made are listed or removed; they are told apart by their name prefix.
+
+use crate::kerbal_alarm_clock::{self, AlarmAction, AlarmType};
+use crate::kerbx::FlightPlan;
+use crate::vehicle::VehicleSensors;
+use crate::{space_center, KerbxError, KerbxTransport};
+
+/// Start of the name of every alarm KerbX makes
+pub const ALARM_PREFIX: &str = "KerbX: ";
+
+/// What a mission alarm marks
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AlarmKind {
+    /// A flight plan step with a time trigger
+    Step,
+    Maneuver,
+    SoiChange,
+}
+
+impl AlarmKind {
+    fn alarm_type(self) -> AlarmType {
+        match self {
+            AlarmKind::Step => AlarmType::Raw,
+            AlarmKind::Maneuver => AlarmType::Maneuver,
+            AlarmKind::SoiChange => AlarmType::SOIChange,
+        }
+    }
+
+    fn from_alarm_type(alarm_type: AlarmType) -> Option<AlarmKind> {
+        match alarm_type {
+            AlarmType::Raw => Some(AlarmKind::Step),
+            AlarmType::Maneuver | AlarmType::ManeuverAuto => Some(AlarmKind::Maneuver),
+            AlarmType::SOIChange | AlarmType::SOIChangeAuto => Some(AlarmKind::SoiChange),
+            _ => None,
+        }
+    }
+}
+
+/// An alarm on the mission timeline
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissionAlarm {
+    /// Kerbal Alarm Clock's own identifier
+    pub id: String,
+    /// Name without ALARM_PREFIX
+    pub name: String,
+    pub kind: AlarmKind,
+    /// Universal time of the event in seconds
+    pub ut: f64,
+}
+
+impl KerbxTransport {
+    /// True if Kerbal Alarm Clock is installed and reachable through kRPC
+    pub fn alarm_clock_available(&self) -> Result<bool, KerbxError> {
+        self.sim_feed.mk_call(&kerbal_alarm_clock::get_available())
+    }
+
+    /// Lists the alarms KerbX has set, soonest first
+    pub fn mission_alarms(&self) -> Result<Vec<MissionAlarm>, KerbxError> {
+        let mut alarms = Vec::new();
+        for alarm in self.kerbx_alarms()? {
+            let name = self.sim_feed.mk_call(&alarm.get_name())?;
+            let alarm_type = self.sim_feed.mk_call(&alarm.get_type())?;
+            if let Some(kind) = AlarmKind::from_alarm_type(alarm_type) {
+                alarms.push(MissionAlarm {
+                    id: self.sim_feed.mk_call(&alarm.get_id())?,
+                    name: String::from(name.trim_start_matches(ALARM_PREFIX)),
+                    kind,
+                    ut: self.sim_feed.mk_call(&alarm.get_time())?,
+                });
+            }
+        }
+        alarms.sort_by(|a, b| a.ut.total_cmp(&b.ut));
+        Ok(alarms)
+    }
+
+    /// Sets an alarm for the vessel being flown that stops time warp margin seconds before ut.
+    /// name: Shown in game after ALARM_PREFIX
+    /// ut: Universal time of the event in seconds
+    /// margin: Seconds of warning before the event
+    pub fn add_alarm(
+        &self,
+        kind: AlarmKind,
+        name: &str,
+        ut: f64,
+        margin: f64,
+    ) -> Result<MissionAlarm, KerbxError> {
+        self.require_alarm_clock()?;
+        let alarm = self.sim_feed.mk_call(&kerbal_alarm_clock::create_alarm(
+            kind.alarm_type(),
+            &format!("{}{}", ALARM_PREFIX, name),
+            ut,
+        ))?;
+        self.sim_feed.mk_call(&alarm.set_vessel(&self.vessel()))?;
+        self.sim_feed.mk_call(&alarm.set_margin(margin))?;
+        self.sim_feed
+            .mk_call(&alarm.set_action(AlarmAction::KillWarp))?;
+
+        Ok(MissionAlarm {
+            id: self.sim_feed.mk_call(&alarm.get_id())?,
+            name: String::from(name),
+            kind,
+            ut,
+        })
+    }
+
+    /// Removes the KerbX alarm with the given id. Returns false if there was none.
+    pub fn remove_alarm(&self, id: &str) -> Result<bool, KerbxError> {
+        for alarm in self.kerbx_alarms()? {
+            if self.sim_feed.mk_call(&alarm.get_id())? == id {
+                self.sim_feed.mk_call(&alarm.remove())?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Removes every alarm KerbX has set, returning how many there were
+    pub fn clear_alarms(&self) -> Result<usize, KerbxError> {
+        let alarms = self.kerbx_alarms()?;
+        for alarm in alarms.iter() {
+            self.sim_feed.mk_call(&alarm.remove())?;
+        }
+        Ok(alarms.len())
+    }
+
+    /// Sets an alarm for every step of the plan with a time trigger still in the future.
+    /// margin: Seconds of warning before each step
+    pub fn alarm_flight_plan(
+        &self,
+        plan: &FlightPlan,
+        margin: f64,
+    ) -> Result<Vec<MissionAlarm>, KerbxError> {
+        // Wall clock and mission times are carried over to universal time from now
+        let clock = self.get_clock()?;
+
+        let mut alarms = Vec::new();
+        for step in plan.steps.iter() {
+            let trigger = step.get_trigger();
+            if !trigger.has_time() {
+                continue;
+            }
+            let ut = clock.to_ut(trigger.get_time());
+            if ut > clock.ut {
+                let name = format!("step {} {:?}", step.get_count(), step.get_field_type());
+                alarms.push(self.add_alarm(AlarmKind::Step, &name, ut, margin)?);
+            }
+        }
+        Ok(alarms)
+    }
+
+    /// Sets an alarm for each maneuver node of the vessel being flown.
+    /// margin: Seconds of warning before each node, e.g. half the burn time
+    pub fn alarm_maneuver_nodes(&self, margin: f64) -> Result<Vec<MissionAlarm>, KerbxError> {
+        let control = self.sim_feed.mk_call(&self.vessel().get_control())?;
+        let mut alarms = Vec::new();
+        for (index, node) in self
+            .sim_feed
+            .mk_call(&control.get_nodes())?
+            .iter()
+            .enumerate()
+        {
+            let ut = self.sim_feed.mk_call(&node.get_ut())?;
+            let name = format!("maneuver {}", index + 1);
+            alarms.push(self.add_alarm(AlarmKind::Maneuver, &name, ut, margin)?);
+        }
+        Ok(alarms)
+    }
+
+    /// Sets an alarm for the next sphere of influence change, if the current orbit leaves the
+    /// body it is around.
+    /// margin: Seconds of warning before the change
+    pub fn alarm_soi_change(&self, margin: f64) -> Result<Option<MissionAlarm>, KerbxError> {
+        let orbit = self.sim_feed.mk_call(&self.vessel().get_orbit())?;
+        let from_now = self.sim_feed.mk_call(&orbit.get_time_to_soi_change())?;
+        if !from_now.is_finite() {
+            return Ok(None);
+        }
+        let ut = self.sim_feed.mk_call(&space_center::get_ut())? + from_now;
+        self.add_alarm(AlarmKind::SoiChange, "SOI change", ut, margin)
+            .map(Some)
+    }
+
+    /// Returns a ModUnavailable error if Kerbal Alarm Clock is missing
+    fn require_alarm_clock(&self) -> Result<(), KerbxError> {
+        if self.alarm_clock_available()? {
+            Ok(())
+        } else {
+            Err(KerbxError::ModUnavailable(String::from(
+                "Kerbal Alarm Clock",
+            )))
+        }
+    }
+
+    /// Every alarm in the game that KerbX made
+    fn kerbx_alarms(&self) -> Result<Vec<kerbal_alarm_clock::Alarm>, KerbxError> {
+        self.require_alarm_clock()?;
+        let mut alarms = Vec::new();
+        for alarm in self.sim_feed.mk_call(&kerbal_alarm_clock::get_alarms())? {
+            if self
+                .sim_feed
+                .mk_call(&alarm.get_name())?
+                .starts_with(ALARM_PREFIX)
+            {
+                alarms.push(alarm);
+            }
+        }
+        Ok(alarms)
+    }
+}
@@ -0,0 +1,646 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+use crate::clock::GameClock;
+use crate::composition::{StageDeltaV, VesselComposition};
+use crate::KerbxError;
+use nalgebra::{UnitQuaternion, Vector3};
+
+/// Every sensor value reported in telemetry, sampled together.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VehicleState {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    pub roll: f64,
+    pub pitch: f64,
+    pub heading: f64,
+    pub velocity: f64,
+    pub stage: i32,
+    pub orbit: OrbitalElements,
+    pub resources: VesselResources,
+    pub propulsion: Propulsion,
+}
+
+/// Health of the connection to the vehicle
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinkStatus {
+    /// Readings and commands reach the vehicle
+    Nominal,
+    /// The connection dropped. Readings and commands fail until it is re-established.
+    Degraded,
+}
+
+/// Keplerian elements of the orbit around the body the craft is in. Altitudes are above sea
+/// level, times are in seconds from now and angles are in degrees.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OrbitalElements {
+    pub apoapsis_altitude: f64,
+    pub periapsis_altitude: f64,
+    pub time_to_apoapsis: f64,
+    pub time_to_periapsis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    /// In meters from the center of the body
+    pub semi_major_axis: f64,
+    pub period: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+}
+
+/// Resources reported in telemetry, as KSP names them.
+pub const MONITORED_RESOURCES: [&str; 5] = [
+    "LiquidFuel",
+    "Oxidizer",
+    "SolidFuel",
+    "MonoPropellant",
+    "ElectricCharge",
+];
+
+/// Amount of a resource on board and the most that fits, in the resource's own units.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceLevel {
+    pub amount: f64,
+    pub max: f64,
+}
+
+/// Levels of each of the MONITORED_RESOURCES.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceLevels {
+    pub liquid_fuel: ResourceLevel,
+    pub oxidizer: ResourceLevel,
+    pub solid_fuel: ResourceLevel,
+    pub mono_propellant: ResourceLevel,
+    pub electric_charge: ResourceLevel,
+}
+
+impl ResourceLevels {
+    /// Level of the resource with the given KSP name, or None if it is not monitored.
+    pub fn get(&self, name: &str) -> Option<&ResourceLevel> {
+        match name {
+            "LiquidFuel" => Some(&self.liquid_fuel),
+            "Oxidizer" => Some(&self.oxidizer),
+            "SolidFuel" => Some(&self.solid_fuel),
+            "MonoPropellant" => Some(&self.mono_propellant),
+            "ElectricCharge" => Some(&self.electric_charge),
+            _ => None,
+        }
+    }
+
+    /// As get, but mutable.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ResourceLevel> {
+        match name {
+            "LiquidFuel" => Some(&mut self.liquid_fuel),
+            "Oxidizer" => Some(&mut self.oxidizer),
+            "SolidFuel" => Some(&mut self.solid_fuel),
+            "MonoPropellant" => Some(&mut self.mono_propellant),
+            "ElectricCharge" => Some(&mut self.electric_charge),
+            _ => None,
+        }
+    }
+}
+
+/// Resources on board the craft.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VesselResources {
+    /// Everything the vessel holds
+    pub vessel: ResourceLevels,
+    /// What is held by the parts the next decoupling will drop, i.e. what the current stage
+    /// burns before it is separated
+    pub stage: ResourceLevels,
+}
+
+/// Thrust and mass of the vessel as it is now.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Propulsion {
+    /// Thrust in N the engines are producing
+    pub thrust: f64,
+    /// Thrust in N the active engines would produce at full throttle at the current pressure,
+    /// taking thrust limiters into account
+    pub available_thrust: f64,
+    /// As available_thrust, but ignoring thrust limiters
+    pub max_thrust: f64,
+    /// Mass in kg
+    pub mass: f64,
+    /// Mass in kg without resources
+    pub dry_mass: f64,
+    /// Local gravitational acceleration in m/s^2
+    pub gravity: f64,
+}
+
+impl Propulsion {
+    /// Current thrust-to-weight ratio
+    pub fn twr(&self) -> f64 {
+        self.ratio(self.thrust)
+    }
+
+    /// Thrust-to-weight ratio at full throttle
+    pub fn available_twr(&self) -> f64 {
+        self.ratio(self.available_thrust)
+    }
+
+    /// Throttle setting that gives the wanted thrust-to-weight ratio, limited to 0..=1. Zero if
+    /// no engine is active.
+    pub fn throttle_for_twr(&self, twr: f64) -> f32 {
+        let available = self.available_twr();
+        if available <= 0.0 {
+            return 0.0;
+        }
+        (twr / available).clamp(0.0, 1.0) as f32
+    }
+
+    /// Seconds at full throttle to change speed by delta_v in m/s. Takes the mass as fixed, so
+    /// long burns come out a little longer than they are. Infinite if no engine is active.
+    pub fn burn_time(&self, delta_v: f64) -> f64 {
+        if self.available_thrust <= 0.0 {
+            return f64::INFINITY;
+        }
+        delta_v * self.mass / self.available_thrust
+    }
+
+    fn ratio(&self, thrust: f64) -> f64 {
+        let weight = self.mass * self.gravity;
+        if weight > 0.0 {
+            thrust / weight
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Direction SAS holds the craft in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SasMode {
+    /// Holds whatever attitude the craft has
+    StabilityAssist,
+    /// Along the burn vector of the next maneuver node
+    Maneuver,
+    Prograde,
+    Retrograde,
+    Normal,
+    AntiNormal,
+    Radial,
+    AntiRadial,
+    /// Toward or away from the target
+    Target,
+    AntiTarget,
+}
+
+/// Which velocity the prograde, retrograde and other SAS modes are measured against
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SpeedMode {
+    Orbit,
+    /// Relative to the ground, which is what matters in the atmosphere
+    Surface,
+    Target,
+}
+
+/// Game seconds per wall clock second at each rails warp factor, as KSP offers them
+pub const RAILS_WARP_RATES: [f64; 8] = [1.0, 5.0, 10.0, 50.0, 100.0, 1_000.0, 10_000.0, 100_000.0];
+/// Game seconds per wall clock second at each physics warp factor
+pub const PHYSICS_WARP_RATES: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+
+/// How game time is being sped up
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WarpMode {
+    None,
+    /// The vessel coasts along its orbit and nothing else is simulated, engines included
+    Rails,
+    /// Everything is still simulated, only faster
+    Physics,
+}
+
+impl WarpMode {
+    /// Game seconds per wall clock second at each factor of the mode
+    pub fn rates(self) -> &'static [f64] {
+        match self {
+            WarpMode::None => &[1.0],
+            WarpMode::Rails => &RAILS_WARP_RATES,
+            WarpMode::Physics => &PHYSICS_WARP_RATES,
+        }
+    }
+}
+
+/// Time warp as the game has it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WarpState {
+    pub mode: WarpMode,
+    /// Index into RAILS_WARP_RATES or PHYSICS_WARP_RATES, 0 when not warping
+    pub factor: u32,
+    /// Game seconds passing per wall clock second. Runs behind the factor while the game
+    /// changes warp.
+    pub rate: f64,
+}
+
+impl Default for WarpState {
+    fn default() -> Self {
+        WarpState {
+            mode: WarpMode::None,
+            factor: 0,
+            rate: 1.0,
+        }
+    }
+}
+
+/// A planned burn of the vessel, as the game has it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ManeuverNode {
+    /// Universal Time of the burn in seconds
+    pub ut: f64,
+    /// Delta-v planned along the prograde, normal and radial directions of the orbit at the
+    /// node, in m/s
+    pub prograde: f64,
+    pub normal: f64,
+    pub radial: f64,
+    /// Delta-v still to burn in m/s, in the surface frame (x up, y north, z east). Shrinks as
+    /// the burn goes on and turns around once it overshoots.
+    pub remaining: Vector3<f64>,
+}
+
+impl ManeuverNode {
+    /// Delta-v still to burn in m/s
+    pub fn remaining_delta_v(&self) -> f64 {
+        self.remaining.norm()
+    }
+
+    /// Pitch and heading in degrees of the remaining burn vector
+    pub fn direction(&self) -> (f64, f64) {
+        let burn = self.remaining.normalize();
+        (
+            burn[0].clamp(-1.0, 1.0).asin().to_degrees(),
+            burn[2].atan2(burn[1]).to_degrees().rem_euclid(360.0),
+        )
+    }
+}
+
+/// Radio link between the vessel and mission control as RemoteTech sees it. Without RemoteTech
+/// the vessel is always in contact and signals arrive instantly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommsStatus {
+    /// False when RemoteTech is not installed
+    pub remote_tech: bool,
+    /// Linked to anything at all, a ground station or another vessel
+    pub connected: bool,
+    /// Linked to a ground station, directly or through relays, so telemetry gets home
+    pub ground_station_link: bool,
+    /// Crew or a flight computer on board can fly the vessel without a link
+    pub local_control: bool,
+    /// One way delay to the nearest ground station in seconds
+    pub signal_delay: f64,
+    /// Ground station one of the connected antennas points at
+    pub ground_station: Option<String>,
+}
+
+impl CommsStatus {
+    /// Whether telemetry sent now reaches mission control.
+    pub fn in_contact(&self) -> bool {
+        self.ground_station_link
+    }
+}
+
+impl Default for CommsStatus {
+    fn default() -> Self {
+        CommsStatus {
+            remote_tech: false,
+            connected: true,
+            ground_station_link: true,
+            local_control: false,
+            signal_delay: 0.0,
+            ground_station: None,
+        }
+    }
+}
+
+/// Raw pilot inputs, each from -1 to 1. Rotation inputs drive the control surfaces, reaction
+/// wheels and gimbals; translation inputs fire the RCS thrusters when RCS is on.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ControlInputs {
+    /// Nose up (+) or down (-)
+    pub pitch: f32,
+    /// Nose right (+) or left (-)
+    pub yaw: f32,
+    /// Clockwise (+) or anticlockwise (-) seen from behind
+    pub roll: f32,
+    pub forward: f32,
+    pub up: f32,
+    pub right: f32,
+}
+
+/// Orientation of the vessel relative to the surface, in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EulerAngles {
+    /// Rotation about the nose, -180 < x <= +180. Zero when the top of the vessel points
+    /// horizontally to the right of its heading and positive as the top turns towards the sky.
+    pub roll: f64,
+    /// Angle of the nose above (+) or below (-) the horizon.
+    pub pitch: f64,
+    /// Direction of the nose from north, 0 <= x < 360.
+    pub heading: f64,
+}
+
+impl EulerAngles {
+    /// Derives Euler angles from an attitude relative to the surface reference frame.
+    /// attitude: Rotation taking vectors in the vessel frame (x right, y nose, z bottom) to the
+    /// surface frame (x up, y north, z east)
+    pub fn from_attitude(attitude: &UnitQuaternion<f64>) -> EulerAngles {
+        let nose = attitude.transform_vector(&Vector3::y());
+        let top = attitude.transform_vector(&-Vector3::z());
+
+        // Reference for roll: horizontal and square to the nose, then rotated up towards the sky.
+        // Left at zero when the nose points straight up or down and roll is undefined.
+        let level = Vector3::x().cross(&nose);
+        let raised = nose.cross(&level);
+        let mut roll = top.dot(&raised).atan2(top.dot(&level)).to_degrees();
+        if roll <= -180.0 {
+            roll += 360.0;
+        }
+
+        EulerAngles {
+            roll,
+            pitch: nose[0].clamp(-1.0, 1.0).asin().to_degrees(),
+            heading: nose[2].atan2(nose[1]).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
+
+/// Read-only view of the vehicle's state. Everything the avionics needs to know about where the
+/// craft is and how it is oriented comes through this trait so that the flight software can run
+/// against KSP (via `KerbxTransport`) or against any other backend.
+pub trait VehicleSensors {
+    /// Latitude in degrees on the body the craft is orbiting.
+    fn get_lat(&self) -> Result<f64, KerbxError>;
+
+    /// Longitude in degrees on the body the craft is orbiting.
+    fn get_lon(&self) -> Result<f64, KerbxError>;
+
+    /// Altitude in meters above sea level.
+    fn get_alt(&self) -> Result<f64, KerbxError>;
+
+    /// Roll in degrees, -180 < x <= +180.
+    fn get_roll(&self) -> Result<f64, KerbxError>;
+
+    /// Pitch in degrees above (+) or below (-) the horizon.
+    fn get_pitch(&self) -> Result<f64, KerbxError>;
+
+    /// Heading in degrees from north, 0 <= x < 360.
+    fn get_heading(&self) -> Result<f64, KerbxError>;
+
+    /// Speed in m/s relative to the surface.
+    fn get_velocity(&self) -> Result<f64, KerbxError>;
+
+    /// Returns the current stage of the craft as reflected by the in-game UI
+    fn get_stage(&self) -> Result<i32, KerbxError>;
+
+    /// Elements of the craft's current orbit.
+    fn get_orbit(&self) -> Result<OrbitalElements, KerbxError>;
+
+    /// Levels of the monitored resources for the vessel and its current stage.
+    fn get_resources(&self) -> Result<VesselResources, KerbxError>;
+
+    /// Current thrust, mass and local gravity.
+    fn get_propulsion(&self) -> Result<Propulsion, KerbxError>;
+
+    /// Delta-v left in the current stage and each stage after it, in firing order. May take
+    /// many calls, so it is not meant to be read every tick.
+    fn get_delta_v(&self) -> Result<Vec<StageDeltaV>, KerbxError>;
+
+    /// Wall clock, Universal Time and mission elapsed time, read together.
+    fn get_clock(&self) -> Result<GameClock, KerbxError>;
+
+    /// Reads every sensor at once. Backends that can sample all values from the same physics
+    /// frame should override this; the default calls each getter in turn.
+    fn snapshot(&self) -> Result<VehicleState, KerbxError> {
+        read_each(self)
+    }
+
+    /// Checks the connection to the vehicle, trying to re-establish it if it dropped. Vehicles
+    /// that cannot lose their connection are always Nominal.
+    fn check_link(&self) -> LinkStatus {
+        LinkStatus::Nominal
+    }
+
+    /// Radio link to mission control. Vehicles without RemoteTech are always in contact.
+    fn get_comms(&self) -> Result<CommsStatus, KerbxError> {
+        Ok(CommsStatus::default())
+    }
+
+    /// Time warp in effect. Vehicles that cannot warp always run at the normal rate.
+    fn get_warp(&self) -> Result<WarpState, KerbxError> {
+        Ok(WarpState::default())
+    }
+
+    /// The vessel's next maneuver node, if it has one. Vehicles without maneuver nodes never
+    /// have one.
+    fn get_maneuver_node(&self) -> Result<Option<ManeuverNode>, KerbxError> {
+        Ok(None)
+    }
+
+    /// The vessel's parts sorted into stages, for checks made before flight. Vehicles that
+    /// cannot list their parts return None.
+    fn get_composition(&self) -> Result<Option<VesselComposition>, KerbxError> {
+        Ok(None)
+    }
+}
+
+/// Builds a snapshot by calling each getter of sensors in turn. Values may come from different
+/// physics frames.
+pub fn read_each<S: VehicleSensors + ?Sized>(sensors: &S) -> Result<VehicleState, KerbxError> {
+    Ok(VehicleState {
+        lat: sensors.get_lat()?,
+        lon: sensors.get_lon()?,
+        alt: sensors.get_alt()?,
+        roll: sensors.get_roll()?,
+        pitch: sensors.get_pitch()?,
+        heading: sensors.get_heading()?,
+        velocity: sensors.get_velocity()?,
+        stage: sensors.get_stage()?,
+        orbit: sensors.get_orbit()?,
+        resources: sensors.get_resources()?,
+        propulsion: sensors.get_propulsion()?,
+    })
+}
+
+/// Control outputs of the vehicle. Paired with `VehicleSensors` this is the complete interface the
+/// avionics computer uses to fly the craft.
+pub trait VehicleActuators {
+    /// percent must be a value between 0 and 1.
+    fn set_throttle(&self, percent: f32) -> Result<(), KerbxError>;
+
+    /// Activates the next stage of the craft.
+    fn trigger_stage(&self) -> Result<(), KerbxError>;
+
+    /// Turn on/off autopilot.
+    /// setting: if true, turns on autopilot; if false, turns off autopilot
+    fn set_auto_pilot(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Sets the direction the auto pilot should attempt to fly in.
+    /// pitch: Wanted pitch
+    /// heading: Wanted heading
+    fn set_auto_pilot_direction(&self, pitch: f32, heading: f32) -> Result<(), KerbxError>;
+
+    /// Sets the pilot inputs directly. Only takes effect while the autopilot is off, and stays
+    /// in place until set again.
+    fn set_control_inputs(&self, inputs: ControlInputs) -> Result<(), KerbxError>;
+
+    /// Turn on/off sas on craft.
+    fn set_sas(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Sets the direction SAS holds. SAS must be on for it to take effect.
+    fn set_sas_mode(&self, mode: SasMode) -> Result<(), KerbxError>;
+
+    /// Sets the velocity the SAS modes and the navball follow.
+    fn set_speed_mode(&self, mode: SpeedMode) -> Result<(), KerbxError>;
+
+    /// Turn on/off rcs on craft.
+    fn set_rcs(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Turn on/off one of the custom action groups.
+    /// group: Action group as numbered in game, 1 to 10
+    fn set_action_group(&self, group: u32, setting: bool) -> Result<(), KerbxError>;
+
+    /// Raise (false) or lower (true) the landing gear and legs.
+    fn set_gear(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Turn on/off the brakes.
+    fn set_brakes(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Turn on/off the lights.
+    fn set_lights(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Fires the abort action group.
+    fn abort(&self) -> Result<(), KerbxError>;
+
+    /// Arms every parachute so it deploys by itself once the craft is low enough and the air
+    /// is thick enough.
+    /// deploy_altitude: Altitude in meters above the terrain at which the parachutes fully open
+    /// min_pressure: Atmospheric pressure in atm at which the parachutes start to open
+    fn arm_parachutes(&self, deploy_altitude: f32, min_pressure: f32) -> Result<(), KerbxError>;
+
+    /// Deploys every parachute now, whatever the altitude and pressure.
+    fn deploy_parachutes(&self) -> Result<(), KerbxError>;
+
+    /// Extend (true) or retract (false) the solar panels.
+    fn set_solar_panels(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Extend (true) or retract (false) the antennas.
+    fn set_antennas(&self, setting: bool) -> Result<(), KerbxError>;
+
+    /// Moves an Infernal Robotics servo, or every servo of a servo group, to position.
+    /// position: Degrees for rotatrons, meters for extendatrons
+    /// speed: Multiple of each servo's configured speed
+    /// Vehicles without Infernal Robotics have no servos to move.
+    fn move_servos(&self, servos: &Servos, position: f32, speed: f32) -> Result<(), KerbxError> {
+        let _ = (servos, position, speed);
+        Err(KerbxError::ModUnavailable(String::from(
+            "Infernal Robotics",
+        )))
+    }
+
+    /// Starts time warp at factor, an index into RAILS_WARP_RATES or PHYSICS_WARP_RATES. Mode
+    /// None, or a factor of 0, ends any warp. Vehicles that cannot warp only accept ending it.
+    fn set_warp(&self, mode: WarpMode, factor: u32) -> Result<(), KerbxError> {
+        if mode == WarpMode::None || factor == 0 {
+            Ok(())
+        } else {
+            Err(KerbxError::Contract(String::from(
+                "Vehicle cannot time warp.",
+            )))
+        }
+    }
+
+    /// Deletes the vessel's next maneuver node, once it has been flown.
+    fn remove_maneuver_node(&self) -> Result<(), KerbxError> {
+        Err(KerbxError::Contract(String::from(
+            "Vehicle has no maneuver nodes.",
+        )))
+    }
+}
+
+/// Infernal Robotics servos a move applies to, by name as shown in game
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Servos {
+    Servo(String),
+    Group(String),
+}
+
+/// Number of custom action groups a craft has
+pub const ACTION_GROUPS: u32 = 10;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Attitude of a vessel with the given nose and top directions in the surface frame
+    fn attitude(nose: Vector3<f64>, top: Vector3<f64>) -> UnitQuaternion<f64> {
+        let right = top.cross(&nose);
+        let rotation = nalgebra::Matrix3::from_columns(&[right, nose, -top]);
+        UnitQuaternion::from_matrix(&rotation)
+    }
+
+    #[test]
+    fn maneuver_node_points_along_remaining_burn() {
+        let node = ManeuverNode {
+            ut: 0.0,
+            prograde: 100.0,
+            normal: 0.0,
+            radial: 0.0,
+            remaining: Vector3::new(50.0, 0.0, 50.0),
+        };
+        let (pitch, heading) = node.direction();
+
+        assert!((node.remaining_delta_v() - 50.0 * 2.0_f64.sqrt()).abs() < 1e-9);
+        assert!((pitch - 45.0).abs() < 1e-9);
+        assert!((heading - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn euler_angles_of_pitched_vessel() {
+        let pitch = 80.0_f64.to_radians();
+        let nose = Vector3::new(pitch.sin(), 0.0, pitch.cos());
+        let angles = EulerAngles::from_attitude(&attitude(nose, Vector3::new(0.0, -1.0, 0.0)));
+
+        assert!(angles.roll.abs() < 1e-9);
+        assert!((angles.pitch - 80.0).abs() < 1e-9);
+        assert!((angles.heading - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn euler_angles_roll_is_continuous() {
+        // Level flight heading north with the top rolled either side of the reference
+        let nose = Vector3::new(0.0, 1.0, 0.0);
+        for degrees in &[-179.0_f64, -90.0, -1.0, 1.0, 90.0, 179.0, 180.0] {
+            let angle = degrees.to_radians();
+            let top = Vector3::new(angle.sin(), 0.0, angle.cos());
+            let angles = EulerAngles::from_attitude(&attitude(nose, top));
+
+            assert!(
+                (angles.roll - degrees).abs() < 1e-9,
+                "{} != {}",
+                angles.roll,
+                degrees
+            );
+            assert!(angles.pitch.abs() < 1e-9);
+            assert!(angles.heading.abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Time warp through the SpaceCenter service. `VehicleSensors::get_warp` and
+//! `VehicleActuators::set_warp` are what the avionics uses; the calls here are for tools that
+//! can wait out a warp or want to know how far KSP lets them warp.
+
+use crate::{space_center, KerbxError, KerbxTransport};
+
+impl KerbxTransport {
+    /// Warps to Universal Time ut, returning once it is reached. KSP picks the rates, up to the
+    /// given ones, and slows down on its own ahead of ut. Blocks for the whole warp, so it is
+    /// not for use while flying a flight plan.
+    /// max_rails_rate: Highest rate on rails, e.g. 100000
+    /// max_physics_rate: Highest rate with physics running, 1 to 4
+    pub fn warp_to(
+        &self,
+        ut: f64,
+        max_rails_rate: f32,
+        max_physics_rate: f32,
+    ) -> Result<(), KerbxError> {
+        self.sim_feed.mk_call(&space_center::warp_to(
+            ut,
+            max_rails_rate,
+            max_physics_rate,
+        ))
+    }
+
+    /// Game seconds passing per wall clock second
+    pub fn warp_rate(&self) -> Result<f64, KerbxError> {
+        Ok(self.sim_feed.mk_call(&space_center::get_warp_rate())?.into())
+    }
+
+    /// Index of the rails or physics warp rate in effect, 0 when not warping
+    pub fn warp_factor(&self) -> Result<u32, KerbxError> {
+        Ok(self.sim_feed.mk_call(&space_center::get_warp_factor())? as u32)
+    }
+
+    /// Highest rails warp factor KSP allows where the vessel is now, lower the closer it is to
+    /// the surface
+    pub fn max_rails_warp_factor(&self) -> Result<u32, KerbxError> {
+        let factor = self
+            .sim_feed
+            .mk_call(&space_center::get_maximum_rails_warp_factor())?;
+        Ok(factor.max(0) as u32)
+    }
+}
@@ -22,13 +22,9 @@
 */
 
 use clap::{App, Arg};
-use libkerbx::{flightplan::*, kerbx::*};
-use serde::Serialize;
-use serde_json::{Result, Value};
-use std::fs::File;
-use std::io::BufWriter;
+use libkerbx::{flightplan::*, kerbx::*, KerbxError};
 
-fn main() {
+fn main() -> Result<(), KerbxError> {
     // Parse command line arguments.
     let matches = App::new("KerbX Flightplan Created")
         .version(env!("CARGO_PKG_VERSION"))
@@ -53,9 +49,9 @@ fn main() {
         1,
         Step_ActionType::IGNITE,
         gen_time_trigger(0),
-    ));
+    )?);
 
     let plan = gen_flightplan_from_steps(steps);
 
-    write_to_file(matches.value_of("output-file").unwrap(), &plan);
+    write_to_file(matches.value_of("output-file").unwrap(), &plan)
 }
@@ -0,0 +1,14 @@
+use protoc_rust;
+
+fn main() {
+    // Generate the kRPC wire protocol messages
+    println!("cargo:rerun-if-changed=protos/krpc.proto");
+    println!("cargo:rerun-if-changed=src/krpc.rs");
+
+    protoc_rust::Codegen::new()
+        .out_dir("src/")
+        .inputs(&["protos/krpc.proto"])
+        .include("protos")
+        .run()
+        .expect("Running protoc failed.");
+}
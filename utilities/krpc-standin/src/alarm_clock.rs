@@ -0,0 +1,142 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Stand-in for the Kerbal Alarm Clock service: a list of alarms clients can create, read,
+//! change and remove.
+
+use crate::value::Value;
+use crate::Shared;
+
+// Alarm handles count up from here, clear of the handles given to SpaceCenter objects
+const ALARM: u64 = 1 << 40;
+
+/// An alarm as Kerbal Alarm Clock holds it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScriptedAlarm {
+    pub id: String,
+    pub name: String,
+    pub notes: String,
+    /// kRPC AlarmType value, 0 being a raw alarm
+    pub alarm_type: i32,
+    /// kRPC AlarmAction value
+    pub action: i32,
+    /// Universal time of the alarm in seconds
+    pub time: f64,
+    pub margin: f64,
+    /// Handle of the vessel the alarm is for, 0 for none
+    pub vessel: u64,
+    handle: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct AlarmClock {
+    pub(crate) alarms: Vec<ScriptedAlarm>,
+    /// Answers as though the mod were missing
+    pub(crate) uninstalled: bool,
+    created: u64,
+}
+
+impl Shared {
+    /// Answers a KerbalAlarmClock procedure. Returns None for procedures the stand-in does not
+    /// model, and for everything but get_Available while the mod is uninstalled.
+    pub(crate) fn alarm_clock(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        let clock = &mut self.alarm_clock;
+        if procedure == "get_Available" {
+            return Some(Value::Bool(!clock.uninstalled));
+        }
+        if clock.uninstalled {
+            return None;
+        }
+
+        let handles = |alarms: &mut dyn Iterator<Item = &ScriptedAlarm>| {
+            Value::List(alarms.map(|alarm| Value::Object(alarm.handle)).collect())
+        };
+        let value = match procedure {
+            "get_Alarms" => handles(&mut clock.alarms.iter()),
+            "AlarmsWithType" => handles(
+                &mut clock
+                    .alarms
+                    .iter()
+                    .filter(|alarm| f64::from(alarm.alarm_type) == args[0].as_f64()),
+            ),
+            "AlarmWithName" => clock
+                .alarms
+                .iter()
+                .find(|alarm| Value::Text(alarm.name.clone()) == args[0])
+                .map_or(Value::None, |alarm| Value::Object(alarm.handle)),
+            "CreateAlarm" => {
+                clock.created += 1;
+                let handle = ALARM + clock.created;
+                clock.alarms.push(ScriptedAlarm {
+                    id: format!("{:032x}", clock.created),
+                    name: match &args[1] {
+                        Value::Text(name) => name.clone(),
+                        _ => String::new(),
+                    },
+                    alarm_type: args[0].as_f64() as i32,
+                    time: args[2].as_f64(),
+                    handle,
+                    ..Default::default()
+                });
+                Value::Object(handle)
+            }
+            _ => {
+                let ut = self.ut;
+                let index = clock
+                    .alarms
+                    .iter()
+                    .position(|alarm| Value::Object(alarm.handle) == args[0])?;
+                if procedure == "Alarm_Remove" {
+                    clock.alarms.remove(index);
+                    return Some(Value::None);
+                }
+
+                let alarm = &mut clock.alarms[index];
+                match procedure {
+                    "Alarm_get_ID" => Value::Text(alarm.id.clone()),
+                    "Alarm_get_Name" => Value::Text(alarm.name.clone()),
+                    "Alarm_get_Notes" => Value::Text(alarm.notes.clone()),
+                    "Alarm_get_Type" => Value::Number(f64::from(alarm.alarm_type)),
+                    "Alarm_get_Action" => Value::Number(f64::from(alarm.action)),
+                    "Alarm_get_Time" => Value::Number(alarm.time),
+                    "Alarm_get_Margin" => Value::Number(alarm.margin),
+                    "Alarm_get_Remaining" => Value::Number(alarm.time - ut),
+                    "Alarm_get_Vessel" => Value::Object(alarm.vessel),
+                    _ => {
+                        match (procedure, &args[1]) {
+                            ("Alarm_set_Name", Value::Text(name)) => alarm.name = name.clone(),
+                            ("Alarm_set_Notes", Value::Text(notes)) => alarm.notes = notes.clone(),
+                            ("Alarm_set_Action", action) => alarm.action = action.as_f64() as i32,
+                            ("Alarm_set_Time", time) => alarm.time = time.as_f64(),
+                            ("Alarm_set_Margin", margin) => alarm.margin = margin.as_f64(),
+                            ("Alarm_set_Vessel", Value::Object(vessel)) => alarm.vessel = *vessel,
+                            _ => return None,
+                        }
+                        Value::None
+                    }
+                }
+            }
+        };
+        Some(value)
+    }
+}
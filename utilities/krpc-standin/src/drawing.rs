@@ -0,0 +1,148 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Stand-in for the Drawing service: the lines clients have drawn, kept so tests can check
+//! what would be seen in the scene. Polygons and text are not modeled.
+
+use crate::value::Value;
+use crate::Shared;
+
+// Line handles count up from here, clear of the handles given to other objects
+const LINE: u64 = 1 << 41;
+
+/// A line as drawn in the scene. Positions are in the line's reference frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScriptedLine {
+    pub start: (f64, f64, f64),
+    pub end: (f64, f64, f64),
+    /// Handle of the reference frame the line was drawn in
+    pub reference_frame: u64,
+    pub color: (f64, f64, f64),
+    pub thickness: f64,
+    pub visible: bool,
+    handle: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Drawing {
+    pub(crate) lines: Vec<ScriptedLine>,
+    created: u64,
+}
+
+impl Drawing {
+    fn add(
+        &mut self,
+        start: (f64, f64, f64),
+        end: (f64, f64, f64),
+        reference_frame: u64,
+        visible: bool,
+    ) -> Value {
+        self.created += 1;
+        let handle = LINE + self.created;
+        self.lines.push(ScriptedLine {
+            start,
+            end,
+            reference_frame,
+            // kRPC draws lines white and 0.1 m thick to begin with
+            color: (1.0, 1.0, 1.0),
+            thickness: 0.1,
+            visible,
+            handle,
+        });
+        Value::Object(handle)
+    }
+}
+
+impl Shared {
+    /// Answers a Drawing procedure. Returns None for procedures the stand-in does not model.
+    pub(crate) fn drawing(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        let drawing = &mut self.drawing;
+        let frame = |value: &Value| match value {
+            Value::Object(id) => *id,
+            _ => 0,
+        };
+        let value = match procedure {
+            "AddLine" => drawing.add(
+                args[0].as_vector(),
+                args[1].as_vector(),
+                frame(&args[2]),
+                args[3].as_bool(),
+            ),
+            "AddDirection" => {
+                let (x, y, z) = args[0].as_vector();
+                let length = args[2].as_f64();
+                drawing.add(
+                    (0.0, 0.0, 0.0),
+                    (x * length, y * length, z * length),
+                    frame(&args[1]),
+                    args[3].as_bool(),
+                )
+            }
+            "Clear" => {
+                drawing.lines.clear();
+                Value::None
+            }
+            _ => {
+                let index = drawing
+                    .lines
+                    .iter()
+                    .position(|line| Value::Object(line.handle) == args[0])?;
+                if procedure == "Line_Remove" {
+                    drawing.lines.remove(index);
+                    return Some(Value::None);
+                }
+
+                let line = &mut drawing.lines[index];
+                match procedure {
+                    "Line_get_Start" => Value::vector(line.start),
+                    "Line_get_End" => Value::vector(line.end),
+                    "Line_get_Color" => Value::vector(line.color),
+                    "Line_get_Thickness" => Value::Number(line.thickness),
+                    "Line_get_Visible" => Value::Bool(line.visible),
+                    "Line_set_Start" => {
+                        line.start = args[1].as_vector();
+                        Value::None
+                    }
+                    "Line_set_End" => {
+                        line.end = args[1].as_vector();
+                        Value::None
+                    }
+                    "Line_set_Color" => {
+                        line.color = args[1].as_vector();
+                        Value::None
+                    }
+                    "Line_set_Thickness" => {
+                        line.thickness = args[1].as_f64();
+                        Value::None
+                    }
+                    "Line_set_Visible" => {
+                        line.visible = args[1].as_bool();
+                        Value::None
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(value)
+    }
+}
@@ -0,0 +1,127 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Stand-in for the Infernal Robotics service: the servo groups of each vessel as scripted in
+//! ScriptedVessel::servo_groups. Servos reach the position they are moved to at once.
+
+use crate::value::Value;
+use crate::{Command, Shared, VESSEL_STRIDE};
+
+// Handles of the servo objects of a vessel. Groups are the kind of object plus the index of the
+// group; servos add SERVO_STRIDE times the group index to the index of the servo in its group.
+const SERVO_GROUP: u64 = 90_000;
+const SERVO: u64 = 100_000;
+const SERVO_STRIDE: u64 = 1_000;
+
+/// An Infernal Robotics servo group and the servos in it
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedServoGroup {
+    pub name: &'static str,
+    pub servos: Vec<ScriptedServo>,
+}
+
+/// An Infernal Robotics servo. Positions are in degrees for rotatrons, meters for extendatrons.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedServo {
+    pub name: &'static str,
+    pub position: f32,
+    pub min_position: f32,
+    pub max_position: f32,
+    pub config_speed: f32,
+    pub locked: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct InfernalRobotics {
+    /// Answers as though the mod were missing
+    pub(crate) uninstalled: bool,
+}
+
+impl Shared {
+    /// Answers an InfernalRobotics procedure for the vessel its first object argument belongs
+    /// to. Returns None for procedures the stand-in does not model, and for everything but
+    /// get_Available while the mod is uninstalled.
+    pub(crate) fn infernal_robotics(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        if procedure == "get_Available" {
+            return Some(Value::Bool(!self.infernal_robotics.uninstalled));
+        }
+        if self.infernal_robotics.uninstalled {
+            return None;
+        }
+
+        let (index, handle) = match args.first() {
+            Some(Value::Object(id)) => (id / VESSEL_STRIDE, id % VESSEL_STRIDE),
+            _ => return None,
+        };
+        let groups = &mut self.vessels.get_mut(index as usize)?.servo_groups;
+        let tagged = |handle: u64| Value::Object(handle + index * VESSEL_STRIDE);
+        let value = match procedure {
+            // ServoGroups takes the vessel itself
+            "ServoGroups" => Value::List(
+                (0..groups.len() as u64)
+                    .map(|group| tagged(SERVO_GROUP + group))
+                    .collect(),
+            ),
+            "ServoGroup_get_Name" => {
+                let group = groups.get(handle.checked_sub(SERVO_GROUP)? as usize)?;
+                Value::Text(String::from(group.name))
+            }
+            "ServoGroup_get_Servos" => {
+                let group = handle.checked_sub(SERVO_GROUP)?;
+                Value::List(
+                    (0..groups.get(group as usize)?.servos.len() as u64)
+                        .map(|servo| tagged(SERVO + group * SERVO_STRIDE + servo))
+                        .collect(),
+                )
+            }
+            _ => {
+                let servo = handle.checked_sub(SERVO)?;
+                let servo = groups
+                    .get_mut((servo / SERVO_STRIDE) as usize)?
+                    .servos
+                    .get_mut((servo % SERVO_STRIDE) as usize)?;
+                match procedure {
+                    "Servo_get_Name" => Value::Text(String::from(servo.name)),
+                    "Servo_get_Position" => Value::Number(servo.position.into()),
+                    "Servo_get_MinPosition" => Value::Number(servo.min_position.into()),
+                    "Servo_get_MaxPosition" => Value::Number(servo.max_position.into()),
+                    "Servo_get_ConfigSpeed" => Value::Number(servo.config_speed.into()),
+                    "Servo_get_IsMoving" => Value::Bool(false),
+                    "Servo_get_IsLocked" => Value::Bool(servo.locked),
+                    "Servo_MoveTo" => {
+                        let position = args[1].as_f64() as f32;
+                        let speed = args[2].as_f64() as f32;
+                        if !servo.locked {
+                            servo.position = position.clamp(servo.min_position, servo.max_position);
+                        }
+                        self.commands
+                            .push(Command::MoveServo(servo.name, position, speed));
+                        Value::None
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(value)
+    }
+}
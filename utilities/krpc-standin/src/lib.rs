@@ -0,0 +1,1363 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! A stand-in for a kRPC server used by integration tests. It speaks the kRPC wire protocol and
+//! answers the `SpaceCenter` procedures `libkerbx::KerbxTransport` relies on from a scripted
+//! vehicle state, recording every control command the client sends. The mod services the
+//! transport uses are answered too, each as though its mod were installed. Like kRPC it listens on a
+//! second port for stream connections, pushing every stream of a client once per update period.
+
+// Proto generated library
+pub mod krpc;
+
+mod alarm_clock;
+mod drawing;
+mod infernal_robotics;
+mod maneuver;
+mod remote_tech;
+mod ui;
+mod value;
+mod warp;
+
+use crate::krpc::*;
+use crate::value::Value;
+pub use alarm_clock::ScriptedAlarm;
+pub use drawing::ScriptedLine;
+pub use infernal_robotics::{ScriptedServo, ScriptedServoGroup};
+pub use maneuver::ScriptedNode;
+use protobuf::{CodedInputStream, CodedOutputStream, Message, ProtobufResult};
+pub use remote_tech::{ScriptedAntenna, ScriptedComms};
+use serde_json::Value as Json;
+use std::collections::HashMap;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+pub use ui::{ScriptedMessage, ScriptedText};
+
+// Services the stand-in serves, described by the same definitions libkerbx generates its
+// bindings from
+const SERVICES: [(&str, &str); 6] = [
+    (
+        "SpaceCenter",
+        include_str!("../../../libkerbx/services/KRPC.SpaceCenter.json"),
+    ),
+    (
+        "KerbalAlarmClock",
+        include_str!("../../../libkerbx/services/KRPC.KerbalAlarmClock.json"),
+    ),
+    (
+        "RemoteTech",
+        include_str!("../../../libkerbx/services/KRPC.RemoteTech.json"),
+    ),
+    (
+        "InfernalRobotics",
+        include_str!("../../../libkerbx/services/KRPC.InfernalRobotics.json"),
+    ),
+    (
+        "Drawing",
+        include_str!("../../../libkerbx/services/KRPC.Drawing.json"),
+    ),
+    ("UI", include_str!("../../../libkerbx/services/KRPC.UI.json")),
+];
+
+// Handles of the remote objects handed out to the client. kRPC reserves 0 for null.
+const VESSEL: u64 = 1;
+const SURFACE_FRAME: u64 = 2;
+const VESSEL_FRAME: u64 = 3;
+const ORBITAL_FRAME: u64 = 4;
+const ORBIT: u64 = 5;
+const FLIGHT: u64 = 7;
+const CONTROL: u64 = 8;
+const AUTO_PILOT: u64 = 9;
+const PARTS: u64 = 10;
+const VESSEL_RESOURCES: u64 = 11;
+
+// Objects tied to a celestial body get a handle per body: the kind of object plus the index of
+// the body in BODIES.
+const BODY: u64 = 100;
+const BODY_FRAME: u64 = 200;
+const BODY_INERTIAL_FRAME: u64 = 300;
+const SURFACE_VELOCITY_FRAME: u64 = 400;
+const ORBITAL_VELOCITY_FRAME: u64 = 500;
+const SURFACE_FLIGHT: u64 = 600;
+const ORBITAL_FLIGHT: u64 = 700;
+
+// Objects tied to a part get a handle per part: the kind of object plus the index of the part in
+// ScriptedVessel::parts.
+const PART: u64 = 10_000;
+const ENGINE: u64 = 20_000;
+const DECOUPLER: u64 = 30_000;
+const PARACHUTE: u64 = 40_000;
+const PART_RESOURCES: u64 = 50_000;
+
+// Resources of the parts decoupled in a stage: the kind of object plus the stage number plus one,
+// as parts that are never decoupled have a decouple stage of -1.
+const STAGE_RESOURCES: u64 = 60_000;
+const CUMULATIVE_STAGE_RESOURCES: u64 = 70_000;
+
+// Every handle belongs to one vessel: the handles above plus VESSEL_STRIDE times the index of the
+// vessel in Shared::vessels. The active vessel is index 0, so its handles are the ones above.
+const VESSEL_STRIDE: u64 = 1_000_000;
+
+/// Bodies the scripted vessel can orbit: name, equatorial radius in m, gravitational parameter
+/// in m^3/s^2 and atmosphere depth in m.
+pub const BODIES: [(&str, f64, f64, f64); 3] = [
+    ("Kerbin", 600_000.0, 3.5316e12, 70_000.0),
+    ("Mun", 200_000.0, 6.5138398e10, 0.0),
+    ("Minmus", 60_000.0, 1.7658e9, 0.0),
+];
+
+// How often stream updates are pushed. Stands in for the KSP physics frame.
+const STREAM_PERIOD: Duration = Duration::from_millis(10);
+
+/// Vehicle state reported back to the client. Vectors are in the vessel's surface reference
+/// frame: x points up, y north and z east.
+#[derive(Clone, Debug)]
+pub struct ScriptedVessel {
+    pub name: &'static str,
+    /// Name of the body being orbited, one of BODIES. Position and velocity are relative to it.
+    pub body: &'static str,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    /// Unit vector along the nose of the vessel
+    pub direction: (f64, f64, f64),
+    /// Unit vector out of the top of the vessel, i.e. the vessel frame's -z axis
+    pub roof: (f64, f64, f64),
+    /// Angular velocity in rad/s
+    pub angular_velocity: (f64, f64, f64),
+    pub true_air_speed: f64,
+    /// Velocity relative to the surface
+    pub surface_velocity: (f64, f64, f64),
+    /// Velocity relative to the body's center
+    pub orbital_velocity: (f64, f64, f64),
+    pub mach: f64,
+    pub dynamic_pressure: f64,
+    pub stage: i32,
+    pub throttle: f32,
+    /// Thrust in N being produced, and what full throttle would give with and without thrust
+    /// limiters
+    pub thrust: f64,
+    pub available_thrust: f64,
+    pub max_thrust: f64,
+    pub orbit: ScriptedOrbit,
+    pub parts: Vec<ScriptedPart>,
+    pub comms: ScriptedComms,
+    pub servo_groups: Vec<ScriptedServoGroup>,
+    /// Mission elapsed time in seconds
+    pub met: f64,
+}
+
+/// Elements of the vessel's orbit, in the units kRPC reports them: angles are in radians.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedOrbit {
+    pub apoapsis_altitude: f64,
+    pub periapsis_altitude: f64,
+    pub time_to_apoapsis: f64,
+    pub time_to_periapsis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub semi_major_axis: f64,
+    pub period: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+}
+
+/// A part of the vessel, in the units kRPC reports them: masses are in kg.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedPart {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub stage: i32,
+    pub decouple_stage: i32,
+    pub mass: f64,
+    pub dry_mass: f64,
+    pub engine: Option<ScriptedEngine>,
+    pub decoupler: bool,
+    pub parachute: bool,
+    /// Name, amount and capacity of each resource held
+    pub resources: Vec<(&'static str, f64, f64)>,
+}
+
+/// Performance of an engine part. Thrust is in N and specific impulse in seconds.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedEngine {
+    pub max_thrust: f64,
+    pub max_vacuum_thrust: f64,
+    pub sea_level_specific_impulse: f64,
+    pub vacuum_specific_impulse: f64,
+    pub propellants: Vec<&'static str>,
+}
+
+impl ScriptedPart {
+    /// A two stage rocket: a Swivel under a small tank, dropped on stage 1, and a pod with a
+    /// parachute deployed on stage 0.
+    pub fn rocket() -> Vec<ScriptedPart> {
+        vec![
+            ScriptedPart {
+                name: "mk1pod.v2",
+                title: "Mk1 Command Pod",
+                stage: -1,
+                decouple_stage: -1,
+                mass: 840.0,
+                dry_mass: 800.0,
+                resources: vec![
+                    ("MonoPropellant", 10.0, 10.0),
+                    ("ElectricCharge", 50.0, 50.0),
+                ],
+                ..Default::default()
+            },
+            ScriptedPart {
+                name: "parachuteSingle",
+                title: "Mk16 Parachute",
+                stage: 0,
+                decouple_stage: -1,
+                mass: 100.0,
+                dry_mass: 100.0,
+                parachute: true,
+                ..Default::default()
+            },
+            ScriptedPart {
+                name: "Decoupler.1",
+                title: "TD-12 Decoupler",
+                stage: 1,
+                decouple_stage: 1,
+                mass: 40.0,
+                dry_mass: 40.0,
+                decoupler: true,
+                ..Default::default()
+            },
+            ScriptedPart {
+                name: "fuelTankSmallFlat",
+                title: "FL-T100 Fuel Tank",
+                stage: -1,
+                decouple_stage: 1,
+                mass: 562.5,
+                dry_mass: 62.5,
+                resources: vec![("LiquidFuel", 45.0, 45.0), ("Oxidizer", 55.0, 55.0)],
+                ..Default::default()
+            },
+            ScriptedPart {
+                name: "liquidEngine2.v2",
+                title: "LV-T45 \"Swivel\" Liquid Fuel Engine",
+                stage: 2,
+                decouple_stage: 1,
+                mass: 1_500.0,
+                dry_mass: 1_500.0,
+                engine: Some(ScriptedEngine {
+                    max_thrust: 167_969.0,
+                    max_vacuum_thrust: 215_000.0,
+                    sea_level_specific_impulse: 250.0,
+                    vacuum_specific_impulse: 320.0,
+                    propellants: vec!["LiquidFuel", "Oxidizer"],
+                }),
+                ..Default::default()
+            },
+        ]
+    }
+}
+
+impl Default for ScriptedVessel {
+    /// A vessel over the KSC pitched 80 degrees up, heading east with no roll
+    fn default() -> ScriptedVessel {
+        let pitch = 80.0_f64.to_radians();
+        ScriptedVessel {
+            name: "KerbX",
+            body: "Kerbin",
+            lat: -0.0972,
+            lon: -74.5577,
+            alt: 70.0,
+            direction: (pitch.sin(), 0.0, pitch.cos()),
+            roof: (0.0, -1.0, 0.0),
+            angular_velocity: (0.0, 0.0, 0.0),
+            true_air_speed: 0.0,
+            surface_velocity: (0.0, 0.0, 0.0),
+            // Kerbin's surface rotates east at 174.9 m/s at the equator
+            orbital_velocity: (0.0, 0.0, 174.9),
+            mach: 0.0,
+            dynamic_pressure: 0.0,
+            stage: 2,
+            throttle: 0.0,
+            thrust: 0.0,
+            available_thrust: 0.0,
+            max_thrust: 0.0,
+            orbit: ScriptedOrbit::default(),
+            parts: ScriptedPart::rocket(),
+            comms: ScriptedComms::default(),
+            servo_groups: Vec::new(),
+            met: 0.0,
+        }
+    }
+}
+
+impl ScriptedVessel {
+    /// Rotation from the vessel frame to the surface frame as an (x, y, z, w) quaternion
+    fn rotation(&self) -> (f64, f64, f64, f64) {
+        // Columns of the rotation matrix are the vessel's axes: right, nose and bottom
+        let right = cross(self.roof, self.direction);
+        let m = [
+            [right.0, self.direction.0, -self.roof.0],
+            [right.1, self.direction.1, -self.roof.1],
+            [right.2, self.direction.2, -self.roof.2],
+        ];
+
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            (
+                (m[2][1] - m[1][2]) / s,
+                (m[0][2] - m[2][0]) / s,
+                (m[1][0] - m[0][1]) / s,
+                s / 4.0,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            (
+                s / 4.0,
+                (m[0][1] + m[1][0]) / s,
+                (m[0][2] + m[2][0]) / s,
+                (m[2][1] - m[1][2]) / s,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            (
+                (m[0][1] + m[1][0]) / s,
+                s / 4.0,
+                (m[1][2] + m[2][1]) / s,
+                (m[0][2] - m[2][0]) / s,
+            )
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            (
+                (m[0][2] + m[2][0]) / s,
+                (m[1][2] + m[2][1]) / s,
+                s / 4.0,
+                (m[1][0] - m[0][1]) / s,
+            )
+        }
+    }
+
+    /// Index in BODIES of the body being orbited
+    fn body_index(&self) -> u64 {
+        BODIES
+            .iter()
+            .position(|body| body.0 == self.body)
+            .expect("Scripted vessel orbits an unknown body.") as u64
+    }
+
+    /// Handles of every part matching the filter, made into objects of the given kind
+    fn parts_where<F: Fn(&ScriptedPart) -> bool>(&self, kind: u64, filter: F) -> Value {
+        Value::List(
+            (0..self.parts.len() as u64)
+                .filter(|index| filter(&self.parts[*index as usize]))
+                .map(|index| Value::Object(kind + index))
+                .collect(),
+        )
+    }
+
+    /// Part behind an object tied to a part
+    fn part(&self, value: &Value) -> &ScriptedPart {
+        match value {
+            Value::Object(id) if *id >= PART => &self.parts[(id % PART) as usize],
+            _ => panic!("{:?} is not a part object.", value),
+        }
+    }
+
+    /// Engine behind an engine object
+    fn engine(&self, value: &Value) -> &ScriptedEngine {
+        self.part(value)
+            .engine
+            .as_ref()
+            .expect("Engine object of a part without an engine.")
+    }
+
+    /// Resources held by the parts a resources object covers
+    fn resources(&self, resources: &Value) -> Vec<(&'static str, f64, f64)> {
+        let decoupled_in = |id: u64| (id % PART) as i32 - 1;
+        let parts: Vec<&ScriptedPart> = match resources {
+            Value::Object(VESSEL_RESOURCES) => self.parts.iter().collect(),
+            Value::Object(id) if *id >= CUMULATIVE_STAGE_RESOURCES => self
+                .parts
+                .iter()
+                .filter(|part| part.decouple_stage <= decoupled_in(*id))
+                .collect(),
+            Value::Object(id) if *id >= STAGE_RESOURCES => self
+                .parts
+                .iter()
+                .filter(|part| part.decouple_stage == decoupled_in(*id))
+                .collect(),
+            _ => vec![self.part(resources)],
+        };
+        parts
+            .into_iter()
+            .flat_map(|part| part.resources.iter().cloned())
+            .collect()
+    }
+
+    /// Total amount and capacity of a resource, zero if none is held
+    fn resource(&self, resources: &Value, name: &Value) -> (f64, f64) {
+        self.resources(resources)
+            .iter()
+            .filter(|resource| Value::Text(String::from(resource.0)) == *name)
+            .fold((0.0, 0.0), |(amount, max), resource| {
+                (amount + resource.1, max + resource.2)
+            })
+    }
+
+    /// Velocity measured by the given flight object. The vessel's own frames move with it, and
+    /// the stand-in has no idea where the vessel is relative to any body but its own.
+    fn velocity_of(&self, flight: &Value) -> (f64, f64, f64) {
+        match body_object(flight) {
+            (SURFACE_FLIGHT, body) if body == self.body_index() => self.surface_velocity,
+            (ORBITAL_FLIGHT, body) if body == self.body_index() => self.orbital_velocity,
+            _ => (0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Control commands received from the client, in the order they arrived.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    ActivateNextStage,
+    SetThrottle(f32),
+    SetSas(bool),
+    SetRcs(bool),
+    /// kRPC SASMode value, 2 being prograde and 3 retrograde
+    SetSasMode(i32),
+    /// kRPC SpeedMode value: 0 orbit, 1 surface, 2 target
+    SetSpeedMode(i32),
+    EngageAutoPilot,
+    DisengageAutoPilot,
+    TargetPitchAndHeading(f32, f32),
+    SetPitch(f32),
+    SetYaw(f32),
+    SetRoll(f32),
+    SetForward(f32),
+    SetUp(f32),
+    SetRight(f32),
+    /// kRPC action group 0 to 9, where 0 is group 10 in game
+    SetActionGroup(u32, bool),
+    SetGear(bool),
+    SetBrakes(bool),
+    SetLights(bool),
+    SetAbort(bool),
+    SetSolarPanels(bool),
+    SetAntennas(bool),
+    SetParachuteDeployAltitude(f32),
+    SetParachuteDeployMinPressure(f32),
+    ArmParachute,
+    DeployParachute,
+    /// Servo name, position and speed of an Infernal Robotics MoveTo
+    MoveServo(&'static str, f32, f32),
+    /// Rails warp factor as set, after clamping to what is allowed
+    SetRailsWarp(i32),
+    SetPhysicsWarp(i32),
+    /// Universal time warped to
+    WarpTo(f64),
+}
+
+/// A stream added by a client through KRPC.AddStream
+struct ActiveStream {
+    id: u64,
+    client: Vec<u8>,
+    call: ProcedureCall,
+}
+
+struct Shared {
+    /// Every vessel in the game. The first is the active vessel.
+    vessels: Vec<ScriptedVessel>,
+    /// Vessels that split off the active vessel when its next stage fires
+    separating: Vec<ScriptedVessel>,
+    commands: Vec<Command>,
+    /// Identifiers handed out to RPC clients
+    clients: Vec<Vec<u8>>,
+    streams: Vec<ActiveStream>,
+    next_stream_id: u64,
+    /// Every open client socket, RPC and stream alike, so they can be dropped
+    connections: Vec<TcpStream>,
+    /// Universal time in seconds
+    ut: f64,
+    alarm_clock: alarm_clock::AlarmClock,
+    drawing: drawing::Drawing,
+    ui: ui::Ui,
+    warp: warp::Warp,
+    maneuver: maneuver::Maneuver,
+    remote_tech: remote_tech::RemoteTech,
+    infernal_robotics: infernal_robotics::InfernalRobotics,
+}
+
+/// Handle to a running stand-in server. The server keeps running until the process exits.
+pub struct StandinServer {
+    address: SocketAddr,
+    stream_address: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl StandinServer {
+    /// Starts a stand-in server on a free loopback port. Each client connection is served on
+    /// its own thread.
+    pub fn start(vessel: ScriptedVessel) -> std::io::Result<StandinServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let stream_listener = TcpListener::bind("127.0.0.1:0")?;
+        let stream_address = stream_listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared {
+            vessels: vec![vessel],
+            separating: Vec::new(),
+            commands: Vec::new(),
+            clients: Vec::new(),
+            streams: Vec::new(),
+            next_stream_id: 1,
+            connections: Vec::new(),
+            ut: 0.0,
+            alarm_clock: alarm_clock::AlarmClock::default(),
+            drawing: drawing::Drawing::default(),
+            ui: ui::Ui::default(),
+            warp: warp::Warp::default(),
+            maneuver: maneuver::Maneuver::default(),
+            remote_tech: remote_tech::RemoteTech::default(),
+            infernal_robotics: infernal_robotics::InfernalRobotics::default(),
+        }));
+        let procedures = Arc::new(Procedures::load());
+
+        let server_shared = shared.clone();
+        let server_procedures = procedures.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = server_shared.clone();
+                let procedures = server_procedures.clone();
+                // A client hanging up simply ends its thread
+                thread::spawn(move || serve(stream, &shared, &procedures));
+            }
+        });
+
+        let server_shared = shared.clone();
+        thread::spawn(move || {
+            for stream in stream_listener.incoming().flatten() {
+                let shared = server_shared.clone();
+                let procedures = procedures.clone();
+                thread::spawn(move || serve_streams(stream, &shared, &procedures));
+            }
+        });
+
+        Ok(StandinServer {
+            address,
+            stream_address,
+            shared,
+        })
+    }
+
+    /// Address to pass to `RPCClient::connect`
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Address of the stream server, passed to `StreamClient::connect`
+    pub fn stream_address(&self) -> SocketAddr {
+        self.stream_address
+    }
+
+    /// Every control command received so far
+    pub fn commands(&self) -> Vec<Command> {
+        self.shared.lock().unwrap().commands.clone()
+    }
+
+    /// Current scripted state of the active vessel, including any changes made by client
+    /// commands
+    pub fn vessel(&self) -> ScriptedVessel {
+        self.shared.lock().unwrap().vessels[0].clone()
+    }
+
+    /// Changes the scripted state of the active vessel seen by connected clients
+    pub fn update<F: FnOnce(&mut ScriptedVessel)>(&self, script: F) {
+        self.update_vessel(0, script);
+    }
+
+    /// Every vessel in the game, the active vessel first
+    pub fn vessels(&self) -> Vec<ScriptedVessel> {
+        self.shared.lock().unwrap().vessels.clone()
+    }
+
+    /// As update, for the vessel at the given index of vessels
+    pub fn update_vessel<F: FnOnce(&mut ScriptedVessel)>(&self, index: usize, script: F) {
+        script(&mut self.shared.lock().unwrap().vessels[index]);
+    }
+
+    /// Puts another vessel in the game, e.g. a second rocket on the runway
+    pub fn add_vessel(&self, vessel: ScriptedVessel) {
+        self.shared.lock().unwrap().vessels.push(vessel);
+    }
+
+    /// Makes vessel split off the active vessel the next time it fires a stage, as a booster
+    /// does when its decoupler fires
+    pub fn separate_on_next_stage(&self, vessel: ScriptedVessel) {
+        self.shared.lock().unwrap().separating.push(vessel);
+    }
+
+    /// Universal time in seconds as the clients see it
+    pub fn ut(&self) -> f64 {
+        self.shared.lock().unwrap().ut
+    }
+
+    /// Moves universal time, as the game does while it runs or warps
+    pub fn set_ut(&self, ut: f64) {
+        self.shared.lock().unwrap().ut = ut;
+    }
+
+    /// Limits rails warp to factor, as KSP does close to a body. 7 to begin with.
+    pub fn set_max_rails_warp(&self, factor: i32) {
+        self.shared.lock().unwrap().warp.max_rails = factor;
+    }
+
+    /// Maneuver nodes of the active vessel, soonest first
+    pub fn nodes(&self) -> Vec<ScriptedNode> {
+        self.shared.lock().unwrap().maneuver.nodes.clone()
+    }
+
+    /// Alarms set in the stand-in Kerbal Alarm Clock, in the order they were created
+    pub fn alarms(&self) -> Vec<ScriptedAlarm> {
+        self.shared.lock().unwrap().alarm_clock.alarms.clone()
+    }
+
+    /// Lines drawn in the scene, in the order they were added
+    pub fn lines(&self) -> Vec<ScriptedLine> {
+        self.shared.lock().unwrap().drawing.lines.clone()
+    }
+
+    /// Messages posted on screen, in the order they were posted
+    pub fn messages(&self) -> Vec<ScriptedMessage> {
+        self.shared.lock().unwrap().ui.messages.clone()
+    }
+
+    /// Text shown on client canvases, in the order it was added
+    pub fn texts(&self) -> Vec<ScriptedText> {
+        self.shared.lock().unwrap().ui.texts.clone()
+    }
+
+    /// Installs or removes the stand-in Kerbal Alarm Clock. It is installed to begin with.
+    pub fn set_alarm_clock_installed(&self, installed: bool) {
+        self.shared.lock().unwrap().alarm_clock.uninstalled = !installed;
+    }
+
+    /// Installs or removes the stand-in RemoteTech. It is installed to begin with.
+    pub fn set_remote_tech_installed(&self, installed: bool) {
+        self.shared.lock().unwrap().remote_tech.uninstalled = !installed;
+    }
+
+    /// Installs or removes the stand-in Infernal Robotics. It is installed to begin with.
+    pub fn set_infernal_robotics_installed(&self, installed: bool) {
+        self.shared.lock().unwrap().infernal_robotics.uninstalled = !installed;
+    }
+
+    /// Closes every client connection as a kRPC server restart would. The server keeps
+    /// accepting new connections.
+    pub fn drop_connections(&self) {
+        for connection in self.shared.lock().unwrap().connections.drain(..) {
+            // The client may already have hung up
+            let _ = connection.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Procedure signatures from the service definition, needed to decode arguments and encode
+/// results.
+struct Procedure {
+    service: String,
+    name: String,
+    parameters: Vec<Json>,
+    return_type: Option<Json>,
+}
+
+/// Procedures of every served service, keyed by service and procedure name
+struct Procedures {
+    by_name: HashMap<(String, String), Procedure>,
+    /// Service name of each service id
+    services: HashMap<u64, String>,
+    /// Procedure name of each (service id, procedure id)
+    by_id: HashMap<(u64, u64), String>,
+}
+
+impl Procedures {
+    fn load() -> Procedures {
+        let mut by_name = HashMap::new();
+        let mut services = HashMap::new();
+        let mut by_id = HashMap::new();
+        for (service_name, definition) in SERVICES.iter() {
+            let definition: Json =
+                serde_json::from_str(definition).expect("Invalid service definition.");
+            let service = &definition[*service_name];
+            let service_id = service["id"].as_u64().unwrap_or(0);
+            services.insert(service_id, String::from(*service_name));
+
+            for (name, procedure) in service["procedures"].as_object().into_iter().flatten() {
+                by_id.insert(
+                    (service_id, procedure["id"].as_u64().unwrap_or(0)),
+                    name.clone(),
+                );
+                by_name.insert(
+                    (String::from(*service_name), name.clone()),
+                    Procedure {
+                        service: String::from(*service_name),
+                        name: name.clone(),
+                        parameters: procedure["parameters"]
+                            .as_array()
+                            .cloned()
+                            .unwrap_or_default(),
+                        return_type: procedure.get("return_type").cloned(),
+                    },
+                );
+            }
+        }
+
+        Procedures {
+            by_name,
+            services,
+            by_id,
+        }
+    }
+
+    /// Clients may identify a procedure by name or by id
+    fn lookup(&self, call: &ProcedureCall) -> Option<&Procedure> {
+        let (service, name) = if call.get_service().is_empty() {
+            let service_id = call.get_service_id() as u64;
+            (
+                self.services.get(&service_id)?.clone(),
+                self.by_id
+                    .get(&(service_id, call.get_procedure_id() as u64))?
+                    .clone(),
+            )
+        } else {
+            (
+                String::from(call.get_service()),
+                String::from(call.get_procedure()),
+            )
+        };
+        self.by_name.get(&(service, name))
+    }
+}
+
+fn write<M: Message>(stream: &mut TcpStream, message: &M) -> ProtobufResult<()> {
+    let mut output = CodedOutputStream::new(stream);
+    output.write_message_no_tag(message)?;
+    output.flush()
+}
+
+/// Performs the connection handshake and then answers requests until the client disconnects.
+fn serve(
+    mut stream: TcpStream,
+    shared: &Mutex<Shared>,
+    procedures: &Procedures,
+) -> ProtobufResult<()> {
+    let mut reader = stream.try_clone()?;
+    let mut input = CodedInputStream::new(&mut reader);
+    shared.lock().unwrap().connections.push(stream.try_clone()?);
+
+    let request: ConnectionRequest = input.read_message()?;
+    let mut response = ConnectionResponse::new();
+    if request.get_field_type() != ConnectionRequest_Type::RPC {
+        response.set_status(ConnectionResponse_Status::WRONG_TYPE);
+        response.set_message(String::from("Expected an RPC connection."));
+        return write(&mut stream, &response);
+    }
+
+    let client = {
+        let mut shared = shared.lock().unwrap();
+        let client = format!("standin-client-{:02}", shared.clients.len()).into_bytes();
+        shared.clients.push(client.clone());
+        client
+    };
+    response.set_status(ConnectionResponse_Status::OK);
+    response.set_client_identifier(client.clone());
+    write(&mut stream, &response)?;
+
+    loop {
+        let request: Request = input.read_message()?;
+        let mut response = Response::new();
+        for call in request.get_calls() {
+            let mut shared = shared.lock().unwrap();
+            response
+                .mut_results()
+                .push(dispatch(call, &client, &mut shared, procedures));
+        }
+        write(&mut stream, &response)?;
+    }
+}
+
+/// Performs the stream connection handshake and then pushes the client's streams every
+/// STREAM_PERIOD until the client disconnects.
+fn serve_streams(
+    mut stream: TcpStream,
+    shared: &Mutex<Shared>,
+    procedures: &Procedures,
+) -> ProtobufResult<()> {
+    shared.lock().unwrap().connections.push(stream.try_clone()?);
+    let mut input = CodedInputStream::new(&mut stream);
+    let request: ConnectionRequest = input.read_message()?;
+    drop(input);
+
+    let client = request.get_client_identifier().to_vec();
+    let mut response = ConnectionResponse::new();
+    if request.get_field_type() != ConnectionRequest_Type::STREAM {
+        response.set_status(ConnectionResponse_Status::WRONG_TYPE);
+        response.set_message(String::from("Expected a stream connection."));
+        return write(&mut stream, &response);
+    }
+    if !shared.lock().unwrap().clients.contains(&client) {
+        response.set_status(ConnectionResponse_Status::MALFORMED_MESSAGE);
+        response.set_message(String::from("Unknown client identifier."));
+        return write(&mut stream, &response);
+    }
+    response.set_status(ConnectionResponse_Status::OK);
+    write(&mut stream, &response)?;
+
+    loop {
+        thread::sleep(STREAM_PERIOD);
+
+        // Every stream is evaluated under one lock so an update describes a single moment
+        let mut update = StreamUpdate::new();
+        {
+            let mut shared = shared.lock().unwrap();
+            let calls: Vec<(u64, ProcedureCall)> = shared
+                .streams
+                .iter()
+                .filter(|active| active.client == client)
+                .map(|active| (active.id, active.call.clone()))
+                .collect();
+            for (id, call) in calls {
+                let mut result = StreamResult::new();
+                result.set_id(id);
+                result.set_result(dispatch(&call, &client, &mut shared, procedures));
+                update.mut_results().push(result);
+            }
+        }
+        if !update.get_results().is_empty() {
+            write(&mut stream, &update)?;
+        }
+    }
+}
+
+fn error(call: &ProcedureCall, description: &str) -> Error {
+    let mut error = Error::new();
+    error.set_service(String::from(call.get_service()));
+    error.set_name(String::from(call.get_procedure()));
+    error.set_description(String::from(description));
+    error
+}
+
+fn dispatch(
+    call: &ProcedureCall,
+    client: &[u8],
+    shared: &mut Shared,
+    procedures: &Procedures,
+) -> ProcedureResult {
+    if call.get_service() == "KRPC" {
+        return shared.krpc(call, client);
+    }
+
+    let mut result = ProcedureResult::new();
+    let procedure = match procedures.lookup(call) {
+        Some(procedure) => procedure,
+        None => {
+            result.set_error(error(call, "Unknown procedure."));
+            return result;
+        }
+    };
+
+    let mut arguments = vec![Value::None; procedure.parameters.len()];
+    for argument in call.get_arguments() {
+        let position = argument.get_position() as usize;
+        if let Some(parameter) = procedure.parameters.get(position) {
+            match Value::decode(argument.get_value(), &parameter["type"]) {
+                Ok(value) => arguments[position] = value,
+                Err(_) => {
+                    result.set_error(error(call, "Malformed argument."));
+                    return result;
+                }
+            }
+        }
+    }
+
+    let value = match procedure.service.as_str() {
+        "KerbalAlarmClock" => shared.alarm_clock(&procedure.name, &arguments),
+        "RemoteTech" => shared.remote_tech(&procedure.name, &arguments),
+        "InfernalRobotics" => shared.infernal_robotics(&procedure.name, &arguments),
+        "Drawing" => shared.drawing(&procedure.name, &arguments),
+        "UI" => shared.ui(&procedure.name, &arguments),
+        _ => shared.call(&procedure.name, &arguments),
+    };
+    let value = match value {
+        Some(value) => value,
+        None => {
+            result.set_error(error(call, "Procedure not supported by the stand-in."));
+            return result;
+        }
+    };
+
+    if let Some(return_type) = &procedure.return_type {
+        match value.encode(return_type) {
+            Ok(bytes) => result.set_value(bytes),
+            Err(_) => result.set_error(error(call, "Could not encode result.")),
+        }
+    }
+    result
+}
+
+/// Splits the handle of an object tied to a celestial body into its kind and body index
+fn body_object(value: &Value) -> (u64, u64) {
+    match value {
+        Value::Object(id) if (BODY..PART).contains(id) => (id - id % 100, id % 100),
+        _ => (0, 0),
+    }
+}
+
+/// Handle relative to the vessel it belongs to
+fn untag(value: &Value) -> Value {
+    match value {
+        Value::Object(id) => Value::Object(id % VESSEL_STRIDE),
+        other => other.clone(),
+    }
+}
+
+/// Makes handles handed out for the vessel at index its own. Handles of other vessels are
+/// already tagged and are left alone.
+fn tag(value: Value, index: u64) -> Value {
+    match value {
+        Value::Object(id) if id != 0 && id < VESSEL_STRIDE => {
+            Value::Object(id + index * VESSEL_STRIDE)
+        }
+        Value::List(items) => Value::List(items.into_iter().map(|item| tag(item, index)).collect()),
+        Value::Tuple(items) => {
+            Value::Tuple(items.into_iter().map(|item| tag(item, index)).collect())
+        }
+        other => other,
+    }
+}
+
+fn cross(a: (f64, f64, f64), b: (f64, f64, f64)) -> (f64, f64, f64) {
+    (
+        a.1 * b.2 - a.2 * b.1,
+        a.2 * b.0 - a.0 * b.2,
+        a.0 * b.1 - a.1 * b.0,
+    )
+}
+
+impl Shared {
+    /// Answers the subset of the KRPC service needed to manage streams
+    fn krpc(&mut self, call: &ProcedureCall, client: &[u8]) -> ProcedureResult {
+        let mut result = ProcedureResult::new();
+        let argument = |position: u32| {
+            call.get_arguments()
+                .iter()
+                .find(|argument| argument.get_position() == position)
+                .map(|argument| argument.get_value())
+                .unwrap_or(&[])
+        };
+
+        match call.get_procedure() {
+            "AddStream" => match ProcedureCall::parse_from_bytes(argument(0)) {
+                Ok(stream_call) => {
+                    let id = self.next_stream_id;
+                    self.next_stream_id += 1;
+                    self.streams.push(ActiveStream {
+                        id,
+                        client: client.to_vec(),
+                        call: stream_call,
+                    });
+
+                    let mut stream = Stream::new();
+                    stream.set_id(id);
+                    match stream.write_to_bytes() {
+                        Ok(bytes) => result.set_value(bytes),
+                        Err(_) => result.set_error(error(call, "Could not encode result.")),
+                    }
+                }
+                Err(_) => result.set_error(error(call, "Malformed argument.")),
+            },
+            "RemoveStream" => match CodedInputStream::from_bytes(argument(0)).read_uint64() {
+                Ok(id) => self.streams.retain(|active| active.id != id),
+                Err(_) => result.set_error(error(call, "Malformed argument.")),
+            },
+            _ => result.set_error(error(call, "Procedure not supported by the stand-in.")),
+        }
+        result
+    }
+
+    /// Answers a single procedure call. Returns None for procedures the stand-in does not model.
+    /// The call is answered for the vessel its first object argument belongs to.
+    fn call(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        if procedure.contains("Warp") {
+            return self.warp(procedure, args);
+        }
+        if procedure.starts_with("Node_")
+            || (procedure.starts_with("Control_") && procedure.contains("Node"))
+        {
+            return self.maneuver(procedure, args);
+        }
+        let index = args
+            .iter()
+            .find_map(|arg| match arg {
+                Value::Object(id) if *id != 0 => Some(id / VESSEL_STRIDE),
+                _ => None,
+            })
+            .unwrap_or(0);
+        let args: Vec<Value> = args.iter().map(untag).collect();
+        self.call_for(index as usize, procedure, &args)
+            .map(|value| tag(value, index))
+    }
+
+    /// Answers a call for the vessel at index, with handles relative to that vessel
+    fn call_for(&mut self, index: usize, procedure: &str, args: &[Value]) -> Option<Value> {
+        let count = self.vessels.len() as u64;
+        let vessel = self.vessels.get_mut(index)?;
+        let value = match procedure {
+            "get_ActiveVessel" => Value::Object(VESSEL),
+            "get_UT" => Value::Number(self.ut),
+            "get_Vessels" => Value::List(
+                (0..count)
+                    .map(|index| Value::Object(VESSEL + index * VESSEL_STRIDE))
+                    .collect(),
+            ),
+            "Vessel_get_Name" => Value::Text(String::from(vessel.name)),
+            "Vessel_get_MET" => Value::Number(vessel.met),
+            "Vessel_get_SurfaceReferenceFrame" => Value::Object(SURFACE_FRAME),
+            "Vessel_get_ReferenceFrame" => Value::Object(VESSEL_FRAME),
+            "Vessel_get_OrbitalReferenceFrame" => Value::Object(ORBITAL_FRAME),
+            "Vessel_get_Orbit" => Value::Object(ORBIT),
+            "Orbit_get_Body" => Value::Object(BODY + vessel.body_index()),
+            "CelestialBody_get_Name" => {
+                Value::Text(String::from(BODIES[body_object(&args[0]).1 as usize].0))
+            }
+            "CelestialBody_get_EquatorialRadius" => {
+                Value::Number(BODIES[body_object(&args[0]).1 as usize].1)
+            }
+            "CelestialBody_get_GravitationalParameter" => {
+                Value::Number(BODIES[body_object(&args[0]).1 as usize].2)
+            }
+            "CelestialBody_get_AtmosphereDepth" => {
+                Value::Number(BODIES[body_object(&args[0]).1 as usize].3)
+            }
+            // Bodies are smooth spheres, so the terrain is at sea level everywhere
+            "CelestialBody_SurfaceHeight" => Value::Number(0.0),
+            "CelestialBody_PositionAtAltitude" => {
+                let radius = BODIES[body_object(&args[0]).1 as usize].1 + args[3].as_f64();
+                let (lat, lon) = (args[1].as_f64().to_radians(), args[2].as_f64().to_radians());
+                Value::vector((
+                    radius * lat.cos() * lon.cos(),
+                    radius * lat.sin(),
+                    radius * lat.cos() * lon.sin(),
+                ))
+            }
+            "CelestialBody_get_ReferenceFrame" => {
+                Value::Object(BODY_FRAME + body_object(&args[0]).1)
+            }
+            "CelestialBody_get_NonRotatingReferenceFrame" => {
+                Value::Object(BODY_INERTIAL_FRAME + body_object(&args[0]).1)
+            }
+            // Only the hybrids of a body frame and the surface frame are ever created
+            "ReferenceFrame_static_CreateHybrid" => match body_object(&args[0]) {
+                (BODY_INERTIAL_FRAME, body) => Value::Object(ORBITAL_VELOCITY_FRAME + body),
+                (_, body) => Value::Object(SURFACE_VELOCITY_FRAME + body),
+            },
+            "Vessel_Flight" => match body_object(&args[1]) {
+                (SURFACE_VELOCITY_FRAME, body) => Value::Object(SURFACE_FLIGHT + body),
+                (ORBITAL_VELOCITY_FRAME, body) => Value::Object(ORBITAL_FLIGHT + body),
+                _ => Value::Object(FLIGHT),
+            },
+            "Vessel_get_Parts" => Value::Object(PARTS),
+            "Parts_get_All" => vessel.parts_where(PART, |_| true),
+            "Parts_get_Engines" => vessel.parts_where(ENGINE, |part| part.engine.is_some()),
+            "Parts_get_Decouplers" => vessel.parts_where(DECOUPLER, |part| part.decoupler),
+            "Parts_get_Parachutes" => vessel.parts_where(PARACHUTE, |part| part.parachute),
+            "Engine_get_Part" | "Decoupler_get_Part" | "Parachute_get_Part" => match args[0] {
+                Value::Object(id) => Value::Object(PART + id % PART),
+                _ => Value::None,
+            },
+            "Part_get_Name" => Value::Text(String::from(vessel.part(&args[0]).name)),
+            "Part_get_Title" => Value::Text(String::from(vessel.part(&args[0]).title)),
+            "Part_get_Stage" => Value::Number(vessel.part(&args[0]).stage as f64),
+            "Part_get_DecoupleStage" => Value::Number(vessel.part(&args[0]).decouple_stage as f64),
+            "Part_get_Mass" => Value::Number(vessel.part(&args[0]).mass),
+            "Part_get_DryMass" => Value::Number(vessel.part(&args[0]).dry_mass),
+            "Part_get_Resources" => match args[0] {
+                Value::Object(id) => Value::Object(PART_RESOURCES + id % PART),
+                _ => Value::None,
+            },
+            "Resources_get_Names" => {
+                let mut names = Vec::new();
+                for resource in vessel.resources(&args[0]) {
+                    let name = Value::Text(String::from(resource.0));
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                Value::List(names)
+            }
+            "Vessel_get_Resources" => Value::Object(VESSEL_RESOURCES),
+            "Vessel_ResourcesInDecoupleStage" => {
+                let kind = if args[2].as_bool() {
+                    CUMULATIVE_STAGE_RESOURCES
+                } else {
+                    STAGE_RESOURCES
+                };
+                Value::Object(kind + (args[1].as_f64() as i64 + 1).max(0) as u64)
+            }
+            "Resources_Amount" => Value::Number(vessel.resource(&args[0], &args[1]).0),
+            "Resources_Max" => Value::Number(vessel.resource(&args[0], &args[1]).1),
+            "Engine_get_MaxThrust" => Value::Number(vessel.engine(&args[0]).max_thrust),
+            "Engine_get_MaxVacuumThrust" => {
+                Value::Number(vessel.engine(&args[0]).max_vacuum_thrust)
+            }
+            "Engine_get_KerbinSeaLevelSpecificImpulse" => {
+                Value::Number(vessel.engine(&args[0]).sea_level_specific_impulse)
+            }
+            "Engine_get_VacuumSpecificImpulse" => {
+                Value::Number(vessel.engine(&args[0]).vacuum_specific_impulse)
+            }
+            "Engine_get_PropellantNames" => Value::List(
+                vessel
+                    .engine(&args[0])
+                    .propellants
+                    .iter()
+                    .map(|name| Value::Text(String::from(*name)))
+                    .collect(),
+            ),
+            "Vessel_get_Thrust" => Value::Number(vessel.thrust),
+            "Vessel_get_AvailableThrust" => Value::Number(vessel.available_thrust),
+            "Vessel_get_MaxThrust" => Value::Number(vessel.max_thrust),
+            "Vessel_get_Mass" => Value::Number(vessel.parts.iter().map(|part| part.mass).sum()),
+            "Vessel_get_DryMass" => {
+                Value::Number(vessel.parts.iter().map(|part| part.dry_mass).sum())
+            }
+            "Vessel_get_Control" => Value::Object(CONTROL),
+            "Vessel_get_AutoPilot" => Value::Object(AUTO_PILOT),
+            "Vessel_Direction" => Value::vector(vessel.direction),
+            "Vessel_Rotation" => {
+                let (x, y, z, w) = vessel.rotation();
+                Value::Tuple(vec![
+                    Value::Number(x),
+                    Value::Number(y),
+                    Value::Number(z),
+                    Value::Number(w),
+                ])
+            }
+            "Vessel_AngularVelocity" => Value::vector(vessel.angular_velocity),
+            // Positions are only ever handed back to the body, which answers from the script.
+            // Only the body being orbited knows where the vessel is.
+            "Vessel_Position" => Value::vector((0.0, 0.0, 0.0)),
+            "CelestialBody_LatitudeAtPosition"
+            | "CelestialBody_LongitudeAtPosition"
+            | "CelestialBody_AltitudeAtPosition"
+                if body_object(&args[0]).1 != vessel.body_index() =>
+            {
+                Value::Number(f64::NAN)
+            }
+            "CelestialBody_LatitudeAtPosition" => Value::Number(vessel.lat),
+            "CelestialBody_LongitudeAtPosition" => Value::Number(vessel.lon),
+            "CelestialBody_AltitudeAtPosition" => Value::Number(vessel.alt),
+            "Orbit_get_ApoapsisAltitude" => Value::Number(vessel.orbit.apoapsis_altitude),
+            "Orbit_get_PeriapsisAltitude" => Value::Number(vessel.orbit.periapsis_altitude),
+            "Orbit_get_TimeToApoapsis" => Value::Number(vessel.orbit.time_to_apoapsis),
+            "Orbit_get_TimeToPeriapsis" => Value::Number(vessel.orbit.time_to_periapsis),
+            "Orbit_get_Eccentricity" => Value::Number(vessel.orbit.eccentricity),
+            "Orbit_get_Inclination" => Value::Number(vessel.orbit.inclination),
+            "Orbit_get_SemiMajorAxis" => Value::Number(vessel.orbit.semi_major_axis),
+            "Orbit_get_Period" => Value::Number(vessel.orbit.period),
+            "Orbit_get_LongitudeOfAscendingNode" => {
+                Value::Number(vessel.orbit.longitude_of_ascending_node)
+            }
+            "Orbit_get_ArgumentOfPeriapsis" => Value::Number(vessel.orbit.argument_of_periapsis),
+            "Orbit_get_Radius" => {
+                Value::Number(BODIES[vessel.body_index() as usize].1 + vessel.alt)
+            }
+            "Flight_get_Latitude" => Value::Number(vessel.lat),
+            "Flight_get_Longitude" => Value::Number(vessel.lon),
+            "Flight_get_MeanAltitude" => Value::Number(vessel.alt),
+            "Flight_get_TrueAirSpeed" => Value::Number(vessel.true_air_speed),
+            "Flight_get_Mach" => Value::Number(vessel.mach),
+            "Flight_get_DynamicPressure" => Value::Number(vessel.dynamic_pressure),
+            "Flight_get_Velocity" => Value::vector(vessel.velocity_of(&args[0])),
+            "Flight_get_Speed" => {
+                let (up, north, east) = vessel.velocity_of(&args[0]);
+                Value::Number((up * up + north * north + east * east).sqrt())
+            }
+            "Flight_get_VerticalSpeed" => Value::Number(vessel.velocity_of(&args[0]).0),
+            "Flight_get_HorizontalSpeed" => {
+                let (_, north, east) = vessel.velocity_of(&args[0]);
+                Value::Number((north * north + east * east).sqrt())
+            }
+            "TransformDirection" => {
+                let (x, y, z) = args[0].as_vector();
+                if args[1] == Value::Object(VESSEL_FRAME) && args[2] == Value::Object(SURFACE_FRAME)
+                {
+                    // Vessel frame: y along the nose, -z out of the roof, x completing the basis
+                    let nose = vessel.direction;
+                    let roof = vessel.roof;
+                    let right = cross(roof, nose);
+                    Value::vector((
+                        x * right.0 + y * nose.0 - z * roof.0,
+                        x * right.1 + y * nose.1 - z * roof.1,
+                        x * right.2 + y * nose.2 - z * roof.2,
+                    ))
+                } else {
+                    Value::vector((x, y, z))
+                }
+            }
+            "Control_get_CurrentStage" => Value::Number(vessel.stage as f64),
+            "Control_get_Throttle" => Value::Number(vessel.throttle as f64),
+            "Control_ActivateNextStage" => {
+                vessel.stage = (vessel.stage - 1).max(0);
+                self.commands.push(Command::ActivateNextStage);
+                let mut separated = Vec::new();
+                if index == 0 {
+                    for vessel in self.separating.drain(..) {
+                        separated.push(Value::Object(
+                            VESSEL + self.vessels.len() as u64 * VESSEL_STRIDE,
+                        ));
+                        self.vessels.push(vessel);
+                    }
+                }
+                Value::List(separated)
+            }
+            "Control_set_Throttle" => {
+                vessel.throttle = args[1].as_f64() as f32;
+                self.commands.push(Command::SetThrottle(vessel.throttle));
+                Value::None
+            }
+            "Control_set_SAS" => {
+                self.commands.push(Command::SetSas(args[1].as_bool()));
+                Value::None
+            }
+            "Control_set_SASMode" => {
+                self.commands
+                    .push(Command::SetSasMode(args[1].as_f64() as i32));
+                Value::None
+            }
+            "Control_set_SpeedMode" => {
+                self.commands
+                    .push(Command::SetSpeedMode(args[1].as_f64() as i32));
+                Value::None
+            }
+            "Control_set_RCS" => {
+                self.commands.push(Command::SetRcs(args[1].as_bool()));
+                Value::None
+            }
+            "Control_set_Pitch" => {
+                self.commands
+                    .push(Command::SetPitch(args[1].as_f64() as f32));
+                Value::None
+            }
+            "Control_set_Yaw" => {
+                self.commands.push(Command::SetYaw(args[1].as_f64() as f32));
+                Value::None
+            }
+            "Control_set_Roll" => {
+                self.commands
+                    .push(Command::SetRoll(args[1].as_f64() as f32));
+                Value::None
+            }
+            "Control_set_Forward" => {
+                self.commands
+                    .push(Command::SetForward(args[1].as_f64() as f32));
+                Value::None
+            }
+            "Control_set_Up" => {
+                self.commands.push(Command::SetUp(args[1].as_f64() as f32));
+                Value::None
+            }
+            "Control_set_Right" => {
+                self.commands
+                    .push(Command::SetRight(args[1].as_f64() as f32));
+                Value::None
+            }
+            "Control_SetActionGroup" => {
+                self.commands.push(Command::SetActionGroup(
+                    args[1].as_f64() as u32,
+                    args[2].as_bool(),
+                ));
+                Value::None
+            }
+            "Control_set_Gear" => {
+                self.commands.push(Command::SetGear(args[1].as_bool()));
+                Value::None
+            }
+            "Control_set_Brakes" => {
+                self.commands.push(Command::SetBrakes(args[1].as_bool()));
+                Value::None
+            }
+            "Control_set_Lights" => {
+                self.commands.push(Command::SetLights(args[1].as_bool()));
+                Value::None
+            }
+            "Control_set_Abort" => {
+                self.commands.push(Command::SetAbort(args[1].as_bool()));
+                Value::None
+            }
+            "Control_set_SolarPanels" => {
+                self.commands
+                    .push(Command::SetSolarPanels(args[1].as_bool()));
+                Value::None
+            }
+            "Control_set_Antennas" => {
+                self.commands.push(Command::SetAntennas(args[1].as_bool()));
+                Value::None
+            }
+            "Parachute_set_DeployAltitude" => {
+                self.commands
+                    .push(Command::SetParachuteDeployAltitude(args[1].as_f64() as f32));
+                Value::None
+            }
+            "Parachute_set_DeployMinPressure" => {
+                self.commands.push(Command::SetParachuteDeployMinPressure(
+                    args[1].as_f64() as f32
+                ));
+                Value::None
+            }
+            "Parachute_Arm" => {
+                self.commands.push(Command::ArmParachute);
+                Value::None
+            }
+            "Parachute_Deploy" => {
+                self.commands.push(Command::DeployParachute);
+                Value::None
+            }
+            "AutoPilot_Engage" => {
+                self.commands.push(Command::EngageAutoPilot);
+                Value::None
+            }
+            "AutoPilot_Disengage" => {
+                self.commands.push(Command::DisengageAutoPilot);
+                Value::None
+            }
+            "AutoPilot_TargetPitchAndHeading" => {
+                self.commands.push(Command::TargetPitchAndHeading(
+                    args[1].as_f64() as f32,
+                    args[2].as_f64() as f32,
+                ));
+                Value::None
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+}
@@ -0,0 +1,162 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Stand-in for the maneuver nodes of the active vessel. Burn vectors are laid out along the
+//! vessel's orbital velocity as it is now, as though every node were where the vessel is, and
+//! nothing is ever burned off a node, so the remaining burn is always the whole burn. Vectors
+//! are given in the surface frame whatever reference frame is asked for.
+
+use crate::value::Value;
+use crate::{cross, Shared};
+
+// Node handles count up from here, clear of the UI element handles
+const NODE: u64 = 1 << 44;
+
+/// A maneuver node. Delta-v is in m/s along the orbit at the node.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScriptedNode {
+    /// Universal time of the burn in seconds
+    pub ut: f64,
+    pub prograde: f64,
+    pub normal: f64,
+    pub radial: f64,
+    handle: u64,
+}
+
+impl ScriptedNode {
+    fn delta_v(&self) -> f64 {
+        (self.prograde.powi(2) + self.normal.powi(2) + self.radial.powi(2)).sqrt()
+    }
+
+    /// Burn vector in the surface frame for a vessel with the given orbital velocity
+    fn burn_vector(&self, velocity: (f64, f64, f64)) -> (f64, f64, f64) {
+        // KSP's frames are left-handed, so the cross products run the other way round
+        let prograde = unit(velocity);
+        let normal = unit(cross(velocity, (1.0, 0.0, 0.0)));
+        let radial = cross(normal, prograde);
+        (
+            prograde.0 * self.prograde + normal.0 * self.normal + radial.0 * self.radial,
+            prograde.1 * self.prograde + normal.1 * self.normal + radial.1 * self.radial,
+            prograde.2 * self.prograde + normal.2 * self.normal + radial.2 * self.radial,
+        )
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Maneuver {
+    /// Nodes in time order, as KSP keeps them
+    pub(crate) nodes: Vec<ScriptedNode>,
+    created: u64,
+}
+
+impl Maneuver {
+    fn sort(&mut self) {
+        self.nodes.sort_by(|a, b| a.ut.total_cmp(&b.ut));
+    }
+}
+
+fn unit(vector: (f64, f64, f64)) -> (f64, f64, f64) {
+    let length = (vector.0.powi(2) + vector.1.powi(2) + vector.2.powi(2)).sqrt();
+    (vector.0 / length, vector.1 / length, vector.2 / length)
+}
+
+impl Shared {
+    /// Answers a maneuver node procedure. Returns None for procedures the stand-in does not
+    /// model.
+    pub(crate) fn maneuver(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        let ut = self.ut;
+        let velocity = self.vessels[0].orbital_velocity;
+        let maneuver = &mut self.maneuver;
+        let value = match procedure {
+            "Control_AddNode" => {
+                maneuver.created += 1;
+                let handle = NODE + maneuver.created;
+                maneuver.nodes.push(ScriptedNode {
+                    ut: args[1].as_f64(),
+                    prograde: args[2].as_f64(),
+                    normal: args[3].as_f64(),
+                    radial: args[4].as_f64(),
+                    handle,
+                });
+                maneuver.sort();
+                Value::Object(handle)
+            }
+            "Control_get_Nodes" => Value::List(
+                maneuver
+                    .nodes
+                    .iter()
+                    .map(|node| Value::Object(node.handle))
+                    .collect(),
+            ),
+            "Control_RemoveNodes" => {
+                maneuver.nodes.clear();
+                Value::None
+            }
+            _ => {
+                let index = maneuver
+                    .nodes
+                    .iter()
+                    .position(|node| Value::Object(node.handle) == args[0])?;
+                match procedure {
+                    "Node_Remove" => {
+                        maneuver.nodes.remove(index);
+                        return Some(Value::None);
+                    }
+                    "Node_set_UT" => {
+                        maneuver.nodes[index].ut = args[1].as_f64();
+                        maneuver.sort();
+                        return Some(Value::None);
+                    }
+                    _ => {}
+                }
+
+                let node = &mut maneuver.nodes[index];
+                match procedure {
+                    "Node_get_UT" => Value::Number(node.ut),
+                    "Node_get_TimeTo" => Value::Number(node.ut - ut),
+                    "Node_get_Prograde" => Value::Number(node.prograde),
+                    "Node_get_Normal" => Value::Number(node.normal),
+                    "Node_get_Radial" => Value::Number(node.radial),
+                    "Node_get_DeltaV" | "Node_get_RemainingDeltaV" => Value::Number(node.delta_v()),
+                    "Node_BurnVector" | "Node_RemainingBurnVector" => {
+                        Value::vector(node.burn_vector(velocity))
+                    }
+                    "Node_set_Prograde" => {
+                        node.prograde = args[1].as_f64();
+                        Value::None
+                    }
+                    "Node_set_Normal" => {
+                        node.normal = args[1].as_f64();
+                        Value::None
+                    }
+                    "Node_set_Radial" => {
+                        node.radial = args[1].as_f64();
+                        Value::None
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(value)
+    }
+}
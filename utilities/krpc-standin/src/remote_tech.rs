@@ -0,0 +1,125 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Stand-in for the RemoteTech service: the link of each vessel as scripted in its
+//! ScriptedComms.
+
+use crate::value::Value;
+use crate::{Shared, VESSEL_STRIDE};
+
+// Handles of the RemoteTech objects of a vessel. Antennas are the kind of object plus the index
+// of the antenna in ScriptedComms::antennas.
+const COMMS: u64 = 12;
+const ANTENNA: u64 = 80_000;
+
+// kRPC Target values
+const TARGET_GROUND_STATION: f64 = 2.0;
+const TARGET_NONE: f64 = 4.0;
+
+/// RemoteTech link of a vessel. Signal delay is in seconds.
+#[derive(Clone, Debug)]
+pub struct ScriptedComms {
+    pub connected: bool,
+    pub ground_station_link: bool,
+    pub local_control: bool,
+    pub signal_delay: f64,
+    pub antennas: Vec<ScriptedAntenna>,
+}
+
+/// A RemoteTech antenna and the ground station it points at, if any
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedAntenna {
+    pub connected: bool,
+    pub ground_station: Option<&'static str>,
+}
+
+impl Default for ScriptedComms {
+    /// A probe core in sight of the KSC through a dish pointed at it
+    fn default() -> ScriptedComms {
+        ScriptedComms {
+            connected: true,
+            ground_station_link: true,
+            local_control: false,
+            signal_delay: 0.0,
+            antennas: vec![ScriptedAntenna {
+                connected: true,
+                ground_station: Some("Mission Control"),
+            }],
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct RemoteTech {
+    /// Answers as though the mod were missing
+    pub(crate) uninstalled: bool,
+}
+
+impl Shared {
+    /// Answers a RemoteTech procedure for the vessel its first object argument belongs to.
+    /// Returns None for procedures the stand-in does not model, and for everything but
+    /// get_Available while the mod is uninstalled.
+    pub(crate) fn remote_tech(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        if procedure == "get_Available" {
+            return Some(Value::Bool(!self.remote_tech.uninstalled));
+        }
+        if self.remote_tech.uninstalled {
+            return None;
+        }
+
+        let (index, handle) = match args.first() {
+            Some(Value::Object(id)) => (id / VESSEL_STRIDE, id % VESSEL_STRIDE),
+            _ => return None,
+        };
+        let comms = &self.vessels.get(index as usize)?.comms;
+        let tagged = |handle: u64| Value::Object(handle + index * VESSEL_STRIDE);
+        let value = match procedure {
+            // Comms takes the vessel itself
+            "Comms" => tagged(COMMS),
+            "Comms_get_HasConnection" => Value::Bool(comms.connected),
+            "Comms_get_HasConnectionToGroundStation" => Value::Bool(comms.ground_station_link),
+            "Comms_get_HasLocalControl" => Value::Bool(comms.local_control),
+            "Comms_get_SignalDelayToGroundStation" => Value::Number(comms.signal_delay),
+            "Comms_get_Antennas" => Value::List(
+                (0..comms.antennas.len() as u64)
+                    .map(|antenna| tagged(ANTENNA + antenna))
+                    .collect(),
+            ),
+            _ => {
+                let antenna = comms.antennas.get(handle.checked_sub(ANTENNA)? as usize)?;
+                match procedure {
+                    "Antenna_get_HasConnection" => Value::Bool(antenna.connected),
+                    "Antenna_get_Target" => Value::Number(match antenna.ground_station {
+                        Some(_) => TARGET_GROUND_STATION,
+                        None => TARGET_NONE,
+                    }),
+                    "Antenna_get_TargetGroundStation" => {
+                        Value::Text(String::from(antenna.ground_station.unwrap_or_default()))
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(value)
+    }
+}
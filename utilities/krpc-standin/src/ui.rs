@@ -0,0 +1,199 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Stand-in for the UI service: the messages posted and the text shown on client canvases, kept
+//! so tests can check what would be seen on screen. Layout is accepted but not modeled, and
+//! buttons and input fields are not offered.
+
+use crate::value::Value;
+use crate::Shared;
+
+// Canvas, panel and text handles count up from here, clear of the line handles
+const ELEMENT: u64 = 1 << 42;
+// Rect transform of an element, as a flag on the element's handle
+const RECT_TRANSFORM: u64 = 1 << 43;
+
+/// A message posted on screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptedMessage {
+    pub content: String,
+    /// Seconds the message stays on screen
+    pub duration: f64,
+    /// Value of the MessagePosition enumeration
+    pub position: i32,
+    pub color: (f64, f64, f64),
+    pub size: f64,
+}
+
+/// A text element on a canvas or panel
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScriptedText {
+    pub content: String,
+    pub color: (f64, f64, f64),
+    pub size: i32,
+    pub visible: bool,
+    handle: u64,
+    parent: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Ui {
+    pub(crate) messages: Vec<ScriptedMessage>,
+    pub(crate) texts: Vec<ScriptedText>,
+    /// Handle and parent of each canvas and panel. Canvases have no parent.
+    containers: Vec<(u64, u64)>,
+    created: u64,
+}
+
+impl Ui {
+    fn next_handle(&mut self) -> u64 {
+        self.created += 1;
+        ELEMENT + self.created
+    }
+
+    fn add_container(&mut self, parent: u64) -> Value {
+        let handle = self.next_handle();
+        self.containers.push((handle, parent));
+        Value::Object(handle)
+    }
+
+    fn add_text(&mut self, parent: u64, value: &Value, visible: bool) -> Value {
+        let handle = self.next_handle();
+        self.texts.push(ScriptedText {
+            content: content(value),
+            // Unity's default for new text
+            color: (1.0, 1.0, 1.0),
+            size: 14,
+            visible,
+            handle,
+            parent,
+        });
+        Value::Object(handle)
+    }
+
+    /// Removes an element along with everything on it
+    fn remove(&mut self, handle: u64) {
+        let panels: Vec<u64> = self
+            .containers
+            .iter()
+            .filter(|(_, parent)| *parent == handle)
+            .map(|(panel, _)| *panel)
+            .collect();
+        self.containers
+            .retain(|(container, _)| *container != handle);
+        self.texts
+            .retain(|text| text.handle != handle && text.parent != handle);
+        for panel in panels {
+            self.remove(panel);
+        }
+    }
+
+    fn exists(&self, handle: u64) -> bool {
+        self.containers
+            .iter()
+            .any(|(container, _)| *container == handle)
+            || self.texts.iter().any(|text| text.handle == handle)
+    }
+}
+
+fn content(value: &Value) -> String {
+    match value {
+        Value::Text(content) => content.clone(),
+        _ => String::new(),
+    }
+}
+
+impl Shared {
+    /// Answers a UI procedure. Returns None for procedures the stand-in does not model.
+    pub(crate) fn ui(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        let ui = &mut self.ui;
+        if procedure == "Message" {
+            ui.messages.push(ScriptedMessage {
+                content: content(&args[0]),
+                duration: args[1].as_f64(),
+                position: args[2].as_f64() as i32,
+                color: args[3].as_vector(),
+                size: args[4].as_f64(),
+            });
+            return Some(Value::None);
+        }
+        if procedure == "AddCanvas" {
+            return Some(ui.add_container(0));
+        }
+
+        let handle = match args.get(0) {
+            Some(Value::Object(handle)) => *handle,
+            _ => return None,
+        };
+        if procedure.starts_with("RectTransform_set_") {
+            // Layout is not modeled, only checked to belong to something on screen
+            return if ui.exists(handle & !RECT_TRANSFORM) {
+                Some(Value::None)
+            } else {
+                None
+            };
+        }
+        if !ui.exists(handle) {
+            return None;
+        }
+
+        let value = match procedure {
+            "Canvas_AddPanel" | "Panel_AddPanel" => ui.add_container(handle),
+            "Canvas_AddText" | "Panel_AddText" => ui.add_text(handle, &args[1], args[2].as_bool()),
+            "Canvas_get_RectTransform" | "Panel_get_RectTransform" | "Text_get_RectTransform" => {
+                Value::Object(handle | RECT_TRANSFORM)
+            }
+            "Canvas_Remove" | "Panel_Remove" | "Text_Remove" => {
+                ui.remove(handle);
+                Value::None
+            }
+            _ => {
+                let text = ui.texts.iter_mut().find(|text| text.handle == handle)?;
+                match procedure {
+                    "Text_get_Content" => Value::Text(text.content.clone()),
+                    "Text_get_Color" => Value::vector(text.color),
+                    "Text_get_Size" => Value::Number(text.size.into()),
+                    "Text_get_Visible" => Value::Bool(text.visible),
+                    "Text_set_Content" => {
+                        text.content = content(&args[1]);
+                        Value::None
+                    }
+                    "Text_set_Color" => {
+                        text.color = args[1].as_vector();
+                        Value::None
+                    }
+                    "Text_set_Size" => {
+                        text.size = args[1].as_f64() as i32;
+                        Value::None
+                    }
+                    "Text_set_Visible" => {
+                        text.visible = args[1].as_bool();
+                        Value::None
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(value)
+    }
+}
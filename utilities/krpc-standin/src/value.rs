@@ -0,0 +1,163 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Encoding of individual procedure arguments and return values. kRPC sends every value as the
+//! bare protobuf encoding of its type, so how a value is written depends on the type declared in
+//! the service definition rather than on the value itself.
+
+use crate::krpc::{List, Tuple};
+use protobuf::{CodedInputStream, CodedOutputStream, Message, ProtobufResult, RepeatedField};
+use serde_json::Value as Json;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// No value, or a null object
+    None,
+    /// Any numeric type. Narrowed to the declared type on the wire.
+    Number(f64),
+    Bool(bool),
+    Text(String),
+    /// Handle of a remote object
+    Object(u64),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// Decodes a value sent by the client.
+    /// type_: The "type" object of the parameter from the service definition
+    pub fn decode(bytes: &[u8], type_: &Json) -> ProtobufResult<Value> {
+        let mut input = CodedInputStream::from_bytes(bytes);
+        let value = match type_["code"].as_str().unwrap_or("") {
+            "DOUBLE" => Value::Number(input.read_double()?),
+            "FLOAT" => Value::Number(input.read_float()? as f64),
+            "SINT32" | "ENUMERATION" => Value::Number(input.read_sint32()? as f64),
+            "SINT64" => Value::Number(input.read_sint64()? as f64),
+            "UINT32" => Value::Number(input.read_uint32()? as f64),
+            "UINT64" => Value::Number(input.read_uint64()? as f64),
+            "BOOL" => Value::Bool(input.read_bool()?),
+            "STRING" => Value::Text(input.read_string()?),
+            "CLASS" => Value::Object(input.read_uint64()?),
+            "TUPLE" => {
+                let tuple = Tuple::parse_from_bytes(bytes)?;
+                let mut items = Vec::new();
+                for (item, item_type) in tuple
+                    .get_items()
+                    .iter()
+                    .zip(type_["types"].as_array().into_iter().flatten())
+                {
+                    items.push(Value::decode(item, item_type)?);
+                }
+                Value::Tuple(items)
+            }
+            "LIST" => {
+                let list = List::parse_from_bytes(bytes)?;
+                let mut items = Vec::new();
+                for item in list.get_items() {
+                    items.push(Value::decode(item, &type_["types"][0])?);
+                }
+                Value::List(items)
+            }
+            _ => Value::None,
+        };
+        Ok(value)
+    }
+
+    /// Encodes a value to send back to the client.
+    /// type_: The "return_type" object of the procedure from the service definition
+    pub fn encode(&self, type_: &Json) -> ProtobufResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        {
+            let mut output = CodedOutputStream::vec(&mut bytes);
+            match (type_["code"].as_str().unwrap_or(""), self) {
+                ("DOUBLE", Value::Number(x)) => output.write_double_no_tag(*x)?,
+                ("FLOAT", Value::Number(x)) => output.write_float_no_tag(*x as f32)?,
+                ("SINT32", Value::Number(x)) | ("ENUMERATION", Value::Number(x)) => {
+                    output.write_sint32_no_tag(*x as i32)?
+                }
+                ("SINT64", Value::Number(x)) => output.write_sint64_no_tag(*x as i64)?,
+                ("UINT32", Value::Number(x)) => output.write_uint32_no_tag(*x as u32)?,
+                ("UINT64", Value::Number(x)) => output.write_uint64_no_tag(*x as u64)?,
+                ("BOOL", Value::Bool(x)) => output.write_bool_no_tag(*x)?,
+                ("STRING", Value::Text(x)) => output.write_string_no_tag(x)?,
+                ("CLASS", Value::Object(x)) => output.write_uint64_no_tag(*x)?,
+                ("CLASS", Value::None) => output.write_uint64_no_tag(0)?,
+                ("TUPLE", Value::Tuple(items)) => {
+                    let mut tuple = Tuple::new();
+                    let mut encoded = Vec::new();
+                    for (item, item_type) in items
+                        .iter()
+                        .zip(type_["types"].as_array().into_iter().flatten())
+                    {
+                        encoded.push(item.encode(item_type)?);
+                    }
+                    tuple.set_items(RepeatedField::from_vec(encoded));
+                    tuple.write_to(&mut output)?;
+                }
+                ("LIST", Value::List(items)) => {
+                    let mut list = List::new();
+                    let mut encoded = Vec::new();
+                    for item in items {
+                        encoded.push(item.encode(&type_["types"][0])?);
+                    }
+                    list.set_items(RepeatedField::from_vec(encoded));
+                    list.write_to(&mut output)?;
+                }
+                _ => {}
+            }
+            output.flush()?;
+        }
+        Ok(bytes)
+    }
+
+    /// Numeric value of the argument, or 0 if it is not a number
+    pub fn as_f64(&self) -> f64 {
+        if let Value::Number(x) = self {
+            *x
+        } else {
+            0.0
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+
+    /// Three element vector carried by a tuple argument
+    pub fn as_vector(&self) -> (f64, f64, f64) {
+        if let Value::Tuple(items) = self {
+            if items.len() == 3 {
+                return (items[0].as_f64(), items[1].as_f64(), items[2].as_f64());
+            }
+        }
+        (0.0, 0.0, 0.0)
+    }
+
+    pub fn vector(vector: (f64, f64, f64)) -> Value {
+        Value::Tuple(vec![
+            Value::Number(vector.0),
+            Value::Number(vector.1),
+            Value::Number(vector.2),
+        ])
+    }
+}
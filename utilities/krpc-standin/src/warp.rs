@@ -0,0 +1,106 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+//! Stand-in for SpaceCenter's time warp: the rails and physics warp factors clients set, and
+//! WarpTo, which moves universal time straight to its target. Universal time does not run on
+//! its own, warping or not.
+
+use crate::value::Value;
+use crate::{Command, Shared};
+
+// Game seconds per wall clock second at each factor, as KSP offers them
+const RAILS_RATES: [f64; 8] = [1.0, 5.0, 10.0, 50.0, 100.0, 1_000.0, 10_000.0, 100_000.0];
+const PHYSICS_RATES: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+
+// kRPC WarpMode values
+const RAILS: f64 = 0.0;
+const PHYSICS: f64 = 1.0;
+const NONE: f64 = 2.0;
+
+pub(crate) struct Warp {
+    rails: i32,
+    physics: i32,
+    /// Highest rails factor allowed where the vessel is
+    pub(crate) max_rails: i32,
+}
+
+impl Default for Warp {
+    fn default() -> Self {
+        Warp {
+            rails: 0,
+            physics: 0,
+            max_rails: RAILS_RATES.len() as i32 - 1,
+        }
+    }
+}
+
+impl Shared {
+    /// Answers a time warp procedure. Returns None for procedures the stand-in does not model.
+    pub(crate) fn warp(&mut self, procedure: &str, args: &[Value]) -> Option<Value> {
+        let warp = &mut self.warp;
+        let value = match procedure {
+            "get_WarpMode" => Value::Number(match (warp.rails, warp.physics) {
+                (0, 0) => NONE,
+                (0, _) => PHYSICS,
+                _ => RAILS,
+            }),
+            "get_WarpRate" => Value::Number(if warp.rails > 0 {
+                RAILS_RATES[warp.rails as usize]
+            } else {
+                PHYSICS_RATES[warp.physics as usize]
+            }),
+            "get_WarpFactor" => Value::Number(warp.rails.max(warp.physics).into()),
+            "get_RailsWarpFactor" => Value::Number(warp.rails.into()),
+            "get_PhysicsWarpFactor" => Value::Number(warp.physics.into()),
+            "get_MaximumRailsWarpFactor" => Value::Number(warp.max_rails.into()),
+            "CanRailsWarpAt" => Value::Bool(args[0].as_f64() as i32 <= warp.max_rails),
+            // Like KSP, starting one kind of warp ends the other
+            "set_RailsWarpFactor" => {
+                warp.rails = (args[0].as_f64() as i32).clamp(0, warp.max_rails);
+                if warp.rails > 0 {
+                    warp.physics = 0;
+                }
+                self.commands.push(Command::SetRailsWarp(warp.rails));
+                Value::None
+            }
+            "set_PhysicsWarpFactor" => {
+                warp.physics = (args[0].as_f64() as i32).clamp(0, PHYSICS_RATES.len() as i32 - 1);
+                if warp.physics > 0 {
+                    warp.rails = 0;
+                }
+                self.commands.push(Command::SetPhysicsWarp(warp.physics));
+                Value::None
+            }
+            "WarpTo" => {
+                let ut = args[0].as_f64();
+                self.ut = self.ut.max(ut);
+                warp.rails = 0;
+                warp.physics = 0;
+                self.commands.push(Command::WarpTo(ut));
+                Value::None
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+}
@@ -0,0 +1,942 @@
+/*
+* =================================================================================================
+*
+*                                      PUBLIC DOMAIN NOTICE
+*                           Naval Surface Warfare Center - Crane Division
+*
+*  This software is a "United States Government Work" under the terms of the United States
+*  Copyright Act. It was written as part of the author's official duties as a United States
+*  Government employee and thus cannot be copyrighted. This software/database is freely available
+*  to the public for use. Naval Surface Warfare Center - Crane Division (NSWC-CD) and the U.S.
+*  Government have not places any restriction on its use or reproduction.
+*
+*  Although all reasonable efforts have been taken to ensure the accuracy and reliability of the
+*  software and data, NSWC-CD and the U.S. Government do not and cannot warrant the performance or
+*  results that may be obtained by using this software or data. NSWC-CD and the U.S. Government
+*  disclaim all warranties, express or implied, including warranties of performance,
+*  merchantability or fitness for any particular purpose.
+*
+*  Please cite the author in any work or product based on this material.
+*
+* =================================================================================================
+*/
+
+// Exercises KerbxTransport end-to-end over a real kRPC connection to the stand-in server.
+
+use krpc_mars::RPCClient;
+use krpc_standin::{
+    Command, ScriptedAntenna, ScriptedComms, ScriptedOrbit, ScriptedServo, ScriptedServoGroup,
+    ScriptedVessel, StandinServer, BODIES,
+};
+use libkerbx::clock;
+use libkerbx::flightplan::*;
+use libkerbx::hud::Hud;
+use libkerbx::kerbal_alarm_clock::{self, AlarmType};
+use libkerbx::kerbx::{Step_ActionType, Time_Base};
+use libkerbx::overlay::Overlay;
+use libkerbx::timeline::AlarmKind;
+use libkerbx::vehicle::{
+    CommsStatus, ControlInputs, LinkStatus, ResourceLevel, SasMode, Servos, SpeedMode,
+    VehicleActuators, VehicleSensors, WarpMode, WarpState,
+};
+use libkerbx::{KerbxError, KerbxTransport, VesselTarget, INITIAL_BACKOFF};
+use nalgebra::Vector3;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn connect(server: &StandinServer) -> KerbxTransport {
+    let client = RPCClient::connect("Stand-in Test", server.address())
+        .expect("Could not connect to stand-in server.");
+    KerbxTransport::new(client).expect("Could not create transport.")
+}
+
+#[test]
+fn reads_scripted_state() {
+    let server = StandinServer::start(ScriptedVessel {
+        surface_velocity: (0.0, 0.0, 120.0),
+        ..Default::default()
+    })
+    .unwrap();
+    let transport = connect(&server);
+
+    assert_eq!(transport.get_lat().unwrap(), -0.0972);
+    assert_eq!(transport.get_lon().unwrap(), -74.5577);
+    assert_eq!(transport.get_alt().unwrap(), 70.0);
+    assert_eq!(transport.get_velocity().unwrap(), 120.0);
+    assert_eq!(transport.get_stage().unwrap(), 2);
+    assert!((transport.get_pitch().unwrap() - 80.0).abs() < 1e-6);
+    assert!((transport.get_heading().unwrap() - 90.0).abs() < 1e-6);
+    assert!(transport.get_roll().unwrap().abs() < 1e-6);
+}
+
+#[test]
+fn follows_script_updates() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+
+    server.update(|vessel| vessel.alt = 10_000.0);
+    assert_eq!(transport.get_alt().unwrap(), 10_000.0);
+}
+
+#[test]
+fn reads_attitude() {
+    // Level, heading north and rolled so the top of the vessel points 30 degrees above east
+    let roll = 30.0_f64.to_radians();
+    let server = StandinServer::start(ScriptedVessel {
+        direction: (0.0, 1.0, 0.0),
+        roof: (roll.sin(), 0.0, roll.cos()),
+        // Turning about the local vertical
+        angular_velocity: (0.1, 0.0, 0.0),
+        ..Default::default()
+    })
+    .unwrap();
+    let transport = connect(&server);
+
+    let attitude = transport.get_attitude().unwrap();
+    let nose = attitude.transform_vector(&Vector3::y());
+    assert!((nose - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-9);
+
+    let angles = transport.get_euler_angles().unwrap();
+    assert!((angles.roll - 30.0).abs() < 1e-9);
+    assert!(angles.pitch.abs() < 1e-9);
+    assert!(angles.heading.abs() < 1e-9);
+
+    // Rates come back along the vessel's axes
+    let rate = transport.get_angular_velocity().unwrap();
+    let expected = Vector3::new(-0.1 * roll.cos(), 0.0, -0.1 * roll.sin());
+    assert!((rate - expected).norm() < 1e-9);
+}
+
+#[test]
+fn reads_velocities() {
+    let server = StandinServer::start(ScriptedVessel {
+        surface_velocity: (30.0, 0.0, 40.0),
+        orbital_velocity: (30.0, 0.0, 214.9),
+        mach: 0.15,
+        dynamic_pressure: 1200.0,
+        ..Default::default()
+    })
+    .unwrap();
+    let transport = connect(&server);
+
+    assert_eq!(
+        transport.get_surface_velocity().unwrap(),
+        Vector3::new(30.0, 0.0, 40.0)
+    );
+    assert_eq!(
+        transport.get_orbital_velocity().unwrap(),
+        Vector3::new(30.0, 0.0, 214.9)
+    );
+    assert_eq!(transport.get_surface_speed().unwrap(), 50.0);
+    assert_eq!(transport.get_velocity().unwrap(), 50.0);
+    assert!(transport.get_orbital_speed().unwrap() > 214.9);
+    assert_eq!(transport.get_vertical_speed().unwrap(), 30.0);
+    assert_eq!(transport.get_horizontal_speed().unwrap(), 40.0);
+    assert!((transport.get_mach().unwrap() - 0.15).abs() < 1e-6);
+    assert_eq!(transport.get_dynamic_pressure().unwrap(), 1200.0);
+}
+
+#[test]
+fn reads_orbit() {
+    let server = StandinServer::start(ScriptedVessel {
+        orbit: ScriptedOrbit {
+            apoapsis_altitude: 90_000.0,
+            periapsis_altitude: 80_000.0,
+            time_to_apoapsis: 600.0,
+            time_to_periapsis: 1_800.0,
+            eccentricity: 0.007,
+            inclination: 6.0_f64.to_radians(),
+            semi_major_axis: 685_000.0,
+            period: 2_400.0,
+            longitude_of_ascending_node: 90.0_f64.to_radians(),
+            argument_of_periapsis: 180.0_f64.to_radians(),
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    let transport = connect(&server);
+
+    assert_eq!(transport.get_apoapsis_altitude().unwrap(), 90_000.0);
+    assert_eq!(transport.get_periapsis_altitude().unwrap(), 80_000.0);
+    assert_eq!(transport.get_time_to_apoapsis().unwrap(), 600.0);
+    assert_eq!(transport.get_time_to_periapsis().unwrap(), 1_800.0);
+    assert_eq!(transport.get_eccentricity().unwrap(), 0.007);
+    assert!((transport.get_inclination().unwrap() - 6.0).abs() < 1e-9);
+    assert_eq!(transport.get_semi_major_axis().unwrap(), 685_000.0);
+    assert_eq!(transport.get_orbital_period().unwrap(), 2_400.0);
+    assert!((transport.get_longitude_of_ascending_node().unwrap() - 90.0).abs() < 1e-9);
+    assert!((transport.get_argument_of_periapsis().unwrap() - 180.0).abs() < 1e-9);
+
+    let orbit = transport.get_orbit().unwrap();
+    assert_eq!(orbit.apoapsis_altitude, 90_000.0);
+    assert!((orbit.inclination - 6.0).abs() < 1e-9);
+}
+
+#[test]
+fn streamed_snapshot_matches_getters() {
+    let server = StandinServer::start(ScriptedVessel {
+        surface_velocity: (0.0, 0.0, 120.0),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut transport = connect(&server);
+    transport.enable_streams(server.stream_address()).unwrap();
+
+    let state = transport.snapshot().unwrap();
+    assert_eq!(state.lat, transport.get_lat().unwrap());
+    assert_eq!(state.lon, transport.get_lon().unwrap());
+    assert_eq!(state.alt, transport.get_alt().unwrap());
+    assert_eq!(state.velocity, transport.get_velocity().unwrap());
+    assert_eq!(state.stage, transport.get_stage().unwrap());
+    assert!((state.pitch - transport.get_pitch().unwrap()).abs() < 1e-6);
+    assert!((state.heading - transport.get_heading().unwrap()).abs() < 1e-6);
+    assert!((state.roll - transport.get_roll().unwrap()).abs() < 1e-6);
+    assert_eq!(state.orbit, transport.get_orbit().unwrap());
+
+    // Snapshots follow the server's stream updates without further calls
+    server.update(|vessel| vessel.alt = 10_000.0);
+    let mut alt = state.alt;
+    for _ in 0..100 {
+        alt = transport.snapshot().unwrap().alt;
+        if alt == 10_000.0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(alt, 10_000.0);
+}
+
+#[test]
+fn follows_sphere_of_influence_change() {
+    let server = StandinServer::start(ScriptedVessel {
+        surface_velocity: (0.0, 0.0, 2_000.0),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut transport = connect(&server);
+    transport.enable_streams(server.stream_address()).unwrap();
+
+    assert_eq!(transport.get_body_name().unwrap(), "Kerbin");
+    assert_eq!(transport.get_body_atmosphere_depth().unwrap(), 70_000.0);
+    assert_eq!(transport.snapshot().unwrap().velocity, 2_000.0);
+
+    server.update(|vessel| {
+        vessel.body = "Mun";
+        vessel.alt = 15_000.0;
+        vessel.surface_velocity = (0.0, 0.0, 500.0);
+    });
+
+    // Streams are moved over to the new body as soon as the change shows up in an update
+    let mut velocity = 0.0;
+    for _ in 0..100 {
+        velocity = transport.snapshot().unwrap().velocity;
+        if velocity == 500.0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(velocity, 500.0);
+
+    assert_eq!(transport.get_body_name().unwrap(), "Mun");
+    assert_eq!(transport.get_body_radius().unwrap(), 200_000.0);
+    assert!((transport.get_body_gravitational_parameter().unwrap() - 6.5138398e10).abs() < 1e4);
+    assert_eq!(transport.get_body_atmosphere_depth().unwrap(), 0.0);
+    assert_eq!(transport.get_alt().unwrap(), 15_000.0);
+    assert_eq!(transport.get_surface_speed().unwrap(), 500.0);
+}
+
+#[test]
+fn reads_composition() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+
+    let composition = transport.get_composition().unwrap().unwrap();
+    let stages: Vec<i32> = composition.stages.iter().map(|stage| stage.stage).collect();
+    assert_eq!(stages, vec![2, 1, 0, -1]);
+
+    let launch = composition.stage(2).unwrap();
+    assert_eq!(launch.engines.len(), 1);
+    let engine = &launch.engines[0];
+    assert_eq!(engine.part.name, "liquidEngine2.v2");
+    assert_eq!(engine.part.decouple_stage, 1);
+    assert_eq!(engine.max_vacuum_thrust, 215_000.0);
+    assert_eq!(engine.vacuum_specific_impulse, 320.0);
+    assert_eq!(engine.propellants, vec!["LiquidFuel", "Oxidizer"]);
+
+    let separation = composition.stage(1).unwrap();
+    assert_eq!(separation.decouplers[0].title, "TD-12 Decoupler");
+    assert_eq!(separation.parts.len(), 3);
+    assert_eq!(separation.tanks.len(), 1);
+    let tank = &separation.tanks[0];
+    assert_eq!(tank.part.name, "fuelTankSmallFlat");
+    assert_eq!(tank.part.mass, 562.5);
+    assert_eq!(tank.resources[1].name, "Oxidizer");
+    assert_eq!(tank.resources[1].amount, 55.0);
+
+    assert_eq!(
+        composition.stage(0).unwrap().parachutes[0].name,
+        "parachuteSingle"
+    );
+
+    // Electric charge is not a fuel
+    let core = composition.stage(-1).unwrap();
+    assert_eq!(core.tanks.len(), 1);
+    assert_eq!(core.tanks[0].resources.len(), 1);
+    assert_eq!(core.tanks[0].resources[0].name, "MonoPropellant");
+}
+
+#[test]
+fn reads_resources() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let mut transport = connect(&server);
+
+    let resources = transport.get_resources().unwrap();
+    assert_eq!(resources.vessel.liquid_fuel.amount, 45.0);
+    assert_eq!(resources.vessel.mono_propellant.max, 10.0);
+    assert_eq!(resources.vessel.electric_charge.amount, 50.0);
+    assert_eq!(resources.vessel.solid_fuel.max, 0.0);
+    // Stage 2 burns from the tank dropped on stage 1, not from the pod
+    assert_eq!(resources.stage.oxidizer.amount, 55.0);
+    assert_eq!(resources.stage.electric_charge.max, 0.0);
+
+    transport.enable_streams(server.stream_address()).unwrap();
+    assert_eq!(transport.snapshot().unwrap().resources, resources);
+
+    // The streams follow both the burn and the change of stage
+    server.update(|vessel| vessel.parts[3].resources[0].1 = 20.0);
+    transport.trigger_stage().unwrap();
+    let mut streamed = transport.snapshot().unwrap();
+    for _ in 0..100 {
+        if streamed.resources.vessel.liquid_fuel.amount == 20.0 && streamed.stage == 1 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+        streamed = transport.snapshot().unwrap();
+    }
+    assert_eq!(streamed.resources.vessel.liquid_fuel.amount, 20.0);
+    assert_eq!(
+        streamed.resources.stage.liquid_fuel,
+        ResourceLevel::default()
+    );
+    assert_eq!(streamed.resources, transport.get_resources().unwrap());
+}
+
+#[test]
+fn reads_propulsion_and_delta_v() {
+    let server = StandinServer::start(ScriptedVessel {
+        available_thrust: 167_969.0,
+        max_thrust: 167_969.0,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut transport = connect(&server);
+
+    let propulsion = transport.get_propulsion().unwrap();
+    assert_eq!(propulsion.mass, 3_042.5);
+    assert_eq!(propulsion.dry_mass, 2_502.5);
+    // GM is sent as a float
+    assert!((propulsion.gravity - 3.5316e12 / 600_070.0_f64.powi(2)).abs() < 1e-4);
+    assert!((propulsion.available_twr() - 167_969.0 / (3_042.5 * propulsion.gravity)).abs() < 1e-9);
+    assert_eq!(propulsion.twr(), 0.0);
+
+    // Only the Swivel's stage has anything to burn
+    let delta_v = transport.get_delta_v().unwrap();
+    let stages: Vec<i32> = delta_v.iter().map(|stage| stage.stage).collect();
+    assert_eq!(stages, vec![2, 1, 0]);
+    assert_eq!(
+        (delta_v[0].start_mass, delta_v[0].end_mass),
+        (3_042.5, 2_542.5)
+    );
+    assert!((delta_v[0].delta_v - 320.0 * 9.80665 * (3_042.5_f64 / 2_542.5).ln()).abs() < 1e-6);
+    assert_eq!(delta_v[1].delta_v, 0.0);
+
+    // Burning propellant lowers the current stage's delta-v
+    server.update(|vessel| vessel.parts[3].resources[0].1 = 20.0);
+    assert!(transport.get_delta_v().unwrap()[0].delta_v < delta_v[0].delta_v);
+
+    transport.enable_streams(server.stream_address()).unwrap();
+    assert_eq!(transport.snapshot().unwrap().propulsion, propulsion);
+    server.update(|vessel| vessel.thrust = 100_000.0);
+    let mut thrust = 0.0;
+    for _ in 0..100 {
+        thrust = transport.snapshot().unwrap().propulsion.thrust;
+        if thrust == 100_000.0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(thrust, 100_000.0);
+}
+
+#[test]
+fn binds_by_name_and_id() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    server.add_vessel(ScriptedVessel {
+        name: "Relay",
+        alt: 90_000.0,
+        ..Default::default()
+    });
+
+    let vessels = connect(&server).list_vessels().unwrap();
+    let names: Vec<&str> = vessels.iter().map(|vessel| vessel.name.as_str()).collect();
+    assert_eq!(names, vec!["KerbX", "Relay"]);
+
+    let bind = |target: VesselTarget| {
+        let client = RPCClient::connect("Stand-in Test", server.address()).unwrap();
+        KerbxTransport::with_target(client, target)
+    };
+    let by_name = bind(VesselTarget::Name(String::from("Relay"))).unwrap();
+    assert_eq!(by_name.get_alt().unwrap(), 90_000.0);
+    let by_id = bind(VesselTarget::Id(vessels[1].id)).unwrap();
+    assert_eq!(by_id.get_alt().unwrap(), 90_000.0);
+    assert_eq!(bind(VesselTarget::Active).unwrap().get_alt().unwrap(), 70.0);
+
+    assert!(matches!(
+        bind(VesselTarget::Name(String::from("Jool 5"))),
+        Err(KerbxError::VesselNotFound(_))
+    ));
+}
+
+#[test]
+fn follows_separated_vessel() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    server.separate_on_next_stage(ScriptedVessel {
+        name: "KerbX Debris",
+        alt: 5_000.0,
+        ..Default::default()
+    });
+    let mut transport = connect(&server);
+    transport.enable_streams(server.stream_address()).unwrap();
+
+    transport.trigger_stage().unwrap();
+    let separated = transport.separated_vessels().unwrap();
+    assert_eq!(separated.len(), 1);
+    assert_eq!(separated[0].name, "KerbX Debris");
+
+    transport.follow(VesselTarget::Id(separated[0].id)).unwrap();
+    assert_eq!(transport.target(), VesselTarget::Id(separated[0].id));
+    assert_eq!(transport.get_alt().unwrap(), 5_000.0);
+    assert_eq!(transport.snapshot().unwrap().alt, 5_000.0);
+
+    // Commands now go to the separated vessel and the streams follow it
+    transport.set_throttle(0.5).unwrap();
+    assert_eq!(server.vessels()[1].throttle, 0.5);
+    assert_eq!(server.vessel().throttle, 0.0);
+    server.update_vessel(1, |vessel| vessel.alt = 4_000.0);
+    let mut alt = 0.0;
+    for _ in 0..100 {
+        alt = transport.snapshot().unwrap().alt;
+        if alt == 4_000.0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(alt, 4_000.0);
+}
+
+#[test]
+fn records_control_commands() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+
+    transport.set_sas(false).unwrap();
+    transport.set_rcs(false).unwrap();
+    transport.set_throttle(1.0).unwrap();
+    transport.trigger_stage().unwrap();
+    transport.set_auto_pilot(true).unwrap();
+    transport.set_auto_pilot_direction(45.0, 90.0).unwrap();
+    transport.set_auto_pilot(false).unwrap();
+
+    assert_eq!(
+        server.commands(),
+        vec![
+            Command::SetSas(false),
+            Command::SetRcs(false),
+            Command::SetThrottle(1.0),
+            Command::ActivateNextStage,
+            Command::EngageAutoPilot,
+            Command::TargetPitchAndHeading(45.0, 90.0),
+            Command::DisengageAutoPilot,
+        ]
+    );
+    assert_eq!(transport.get_stage().unwrap(), 1);
+}
+
+#[test]
+fn records_sas_modes() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+
+    transport.set_sas(true).unwrap();
+    transport.set_speed_mode(SpeedMode::Surface).unwrap();
+    transport.set_sas_mode(SasMode::Retrograde).unwrap();
+    transport.set_sas_mode(SasMode::AntiTarget).unwrap();
+
+    assert_eq!(
+        server.commands(),
+        vec![
+            Command::SetSas(true),
+            Command::SetSpeedMode(1),
+            Command::SetSasMode(3),
+            Command::SetSasMode(9),
+        ]
+    );
+}
+
+#[test]
+fn records_control_inputs() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+
+    transport
+        .set_control_inputs(ControlInputs {
+            pitch: -0.5,
+            yaw: 0.25,
+            roll: 0.0,
+            forward: 1.0,
+            up: 0.0,
+            right: -1.0,
+        })
+        .unwrap();
+
+    assert_eq!(
+        server.commands(),
+        vec![
+            Command::SetPitch(-0.5),
+            Command::SetYaw(0.25),
+            Command::SetRoll(0.0),
+            Command::SetForward(1.0),
+            Command::SetUp(0.0),
+            Command::SetRight(-1.0),
+        ]
+    );
+}
+
+#[test]
+fn records_part_group_commands() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+
+    transport.set_action_group(1, true).unwrap();
+    transport.set_action_group(10, false).unwrap();
+    assert!(transport.set_action_group(11, true).is_err());
+    transport.set_gear(false).unwrap();
+    transport.set_brakes(true).unwrap();
+    transport.set_lights(true).unwrap();
+    transport.set_solar_panels(true).unwrap();
+    transport.set_antennas(true).unwrap();
+    transport.arm_parachutes(1_500.0, 0.04).unwrap();
+    transport.deploy_parachutes().unwrap();
+    transport.abort().unwrap();
+
+    assert_eq!(
+        server.commands(),
+        vec![
+            Command::SetActionGroup(1, true),
+            Command::SetActionGroup(0, false),
+            Command::SetGear(false),
+            Command::SetBrakes(true),
+            Command::SetLights(true),
+            Command::SetSolarPanels(true),
+            Command::SetAntennas(true),
+            Command::SetParachuteDeployAltitude(1_500.0),
+            Command::SetParachuteDeployMinPressure(0.04),
+            Command::ArmParachute,
+            Command::DeployParachute,
+            Command::SetAbort(true),
+        ]
+    );
+}
+
+#[test]
+fn reconnects_after_link_loss() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let mut transport = KerbxTransport::connect("Stand-in Test", server.address()).unwrap();
+    transport.enable_streams(server.stream_address()).unwrap();
+
+    server.drop_connections();
+    assert!(transport.get_alt().unwrap_err().is_link_failure());
+    assert_eq!(transport.link_status(), LinkStatus::Degraded);
+
+    // Calls fail fast until the backoff has elapsed and the link is re-established
+    assert_eq!(transport.check_link(), LinkStatus::Degraded);
+    assert!(transport.snapshot().unwrap_err().is_link_failure());
+    thread::sleep(INITIAL_BACKOFF + Duration::from_millis(50));
+    assert_eq!(transport.check_link(), LinkStatus::Nominal);
+
+    server.update(|vessel| vessel.alt = 5_000.0);
+    assert_eq!(transport.get_alt().unwrap(), 5_000.0);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(transport.snapshot().unwrap().alt, 5_000.0);
+}
+
+#[test]
+fn backs_off_while_vessel_is_missing_after_reconnect() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let target = VesselTarget::Name(String::from("KerbX"));
+    let transport = KerbxTransport::connect_to("Stand-in Test", server.address(), target).unwrap();
+
+    // The server comes back before the vessel does, as when KSP is reloading the flight scene
+    server.drop_connections();
+    server.update(|vessel| vessel.name = "Loading");
+    assert!(transport.get_alt().unwrap_err().is_link_failure());
+    thread::sleep(INITIAL_BACKOFF + Duration::from_millis(50));
+    assert_eq!(transport.check_link(), LinkStatus::Degraded);
+
+    // The failed rebind doubles the backoff rather than starting over from the first one
+    server.update(|vessel| vessel.name = "KerbX");
+    thread::sleep(INITIAL_BACKOFF + Duration::from_millis(50));
+    assert_eq!(transport.check_link(), LinkStatus::Degraded);
+    thread::sleep(INITIAL_BACKOFF + Duration::from_millis(50));
+    assert_eq!(transport.check_link(), LinkStatus::Nominal);
+    assert_eq!(transport.get_alt().unwrap(), 70.0);
+}
+
+#[test]
+fn keeps_mission_timeline_in_alarm_clock() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    server.set_ut(1_000.0);
+    let transport = connect(&server);
+
+    // Alarms made by hand in game are left alone
+    let client = RPCClient::connect("Stand-in Test", server.address()).unwrap();
+    client
+        .mk_call(&kerbal_alarm_clock::create_alarm(
+            AlarmType::Raw,
+            "Crew rotation",
+            5_000.0,
+        ))
+        .unwrap();
+
+    let maneuver = transport
+        .add_alarm(AlarmKind::Maneuver, "circularize", 1_600.0, 60.0)
+        .unwrap();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let plan = gen_flightplan_from_steps(vec![
+        gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+        gen_throttle_step(2, 0.5, gen_time_trigger(now + 300)),
+        gen_throttle_step(3, 0.0, gen_apoapsis_trigger(80_000.0)),
+    ]);
+    let steps = transport.alarm_flight_plan(&plan, 10.0).unwrap();
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0].kind, AlarmKind::Step);
+    assert!((steps[0].ut - 1_300.0).abs() < 5.0);
+
+    let alarms = transport.mission_alarms().unwrap();
+    assert_eq!(alarms.len(), 2);
+    assert_eq!(alarms[0].name, "step 2 THROTTLELEVEL");
+    assert_eq!(alarms[1], maneuver);
+
+    let in_game = server.alarms();
+    assert_eq!(in_game[1].name, "KerbX: circularize");
+    assert_eq!(in_game[1].margin, 60.0);
+    // Stops time warp for the vessel being flown
+    assert_eq!(in_game[1].action, 2);
+    assert_ne!(in_game[1].vessel, 0);
+
+    assert!(transport.remove_alarm(&maneuver.id).unwrap());
+    assert!(!transport.remove_alarm(&maneuver.id).unwrap());
+    assert_eq!(transport.clear_alarms().unwrap(), 1);
+    assert_eq!(server.alarms().len(), 1);
+
+    server.set_alarm_clock_installed(false);
+    assert!(matches!(
+        transport.add_alarm(AlarmKind::SoiChange, "Mun", 9_000.0, 0.0),
+        Err(KerbxError::ModUnavailable(_))
+    ));
+    assert!(matches!(
+        transport.clear_alarms(),
+        Err(KerbxError::ModUnavailable(_))
+    ));
+}
+
+#[test]
+fn reads_remote_tech_link() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+
+    let comms = transport.get_comms().unwrap();
+    assert!(comms.remote_tech);
+    assert!(comms.in_contact());
+    assert_eq!(comms.ground_station.as_deref(), Some("Mission Control"));
+
+    // Behind the Mun, still relayed but a long way round
+    server.update(|vessel| {
+        vessel.comms = ScriptedComms {
+            ground_station_link: false,
+            local_control: true,
+            signal_delay: 1.5,
+            antennas: vec![ScriptedAntenna::default()],
+            ..Default::default()
+        }
+    });
+    let comms = transport.get_comms().unwrap();
+    assert!(comms.connected);
+    assert!(!comms.in_contact());
+    assert!(comms.local_control);
+    assert_eq!(comms.signal_delay, 1.5);
+    assert_eq!(comms.ground_station, None);
+
+    // Without the mod the vessel is always in contact
+    server.set_remote_tech_installed(false);
+    assert_eq!(transport.get_comms().unwrap(), CommsStatus::default());
+}
+
+#[test]
+fn moves_infernal_robotics_servos() {
+    let leg = |name| ScriptedServo {
+        name,
+        min_position: 0.0,
+        max_position: 90.0,
+        config_speed: 20.0,
+        ..Default::default()
+    };
+    let server = StandinServer::start(ScriptedVessel {
+        servo_groups: vec![
+            ScriptedServoGroup {
+                name: "Legs",
+                servos: vec![leg("Leg 1"), leg("Leg 2")],
+            },
+            ScriptedServoGroup {
+                name: "Arm",
+                servos: vec![ScriptedServo {
+                    name: "Wrist",
+                    min_position: -180.0,
+                    max_position: 180.0,
+                    ..Default::default()
+                }],
+            },
+        ],
+        ..Default::default()
+    })
+    .unwrap();
+    let transport = connect(&server);
+
+    let groups = transport.servo_groups().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].name, "Legs");
+    assert_eq!(groups[0].servos[1].name, "Leg 2");
+    assert_eq!(groups[0].servos[1].config_speed, 20.0);
+
+    transport
+        .move_servos(&Servos::Group(String::from("Legs")), 90.0, 0.5)
+        .unwrap();
+    transport
+        .move_servos(&Servos::Servo(String::from("Wrist")), -45.0, 1.0)
+        .unwrap();
+    assert_eq!(
+        server.commands(),
+        vec![
+            Command::MoveServo("Leg 1", 90.0, 0.5),
+            Command::MoveServo("Leg 2", 90.0, 0.5),
+            Command::MoveServo("Wrist", -45.0, 1.0),
+        ]
+    );
+    assert_eq!(transport.servo("Leg 1").unwrap().unwrap().position, 90.0);
+    assert_eq!(transport.servo("Wrist").unwrap().unwrap().position, -45.0);
+    assert_eq!(transport.servo("Elbow").unwrap(), None);
+    assert!(matches!(
+        transport.move_servos(&Servos::Group(String::from("Wheels")), 0.0, 1.0),
+        Err(KerbxError::Contract(_))
+    ));
+
+    server.set_infernal_robotics_installed(false);
+    assert!(matches!(
+        transport.servo_groups(),
+        Err(KerbxError::ModUnavailable(_))
+    ));
+}
+
+#[test]
+fn draws_flight_plan_and_vectors() {
+    let server = StandinServer::start(ScriptedVessel {
+        surface_velocity: (0.0, 0.0, 120.0),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut overlay = Overlay::new(connect(&server));
+
+    let plan = gen_flightplan_from_steps(vec![
+        gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap(),
+        gen_reorient_step(2, 0.0, 80.0, 90.0, gen_alt_trigger(1_000.0)),
+        gen_reorient_step(3, 0.0, 45.0, 90.0, gen_pos_trigger(-0.1, -74.0)),
+    ]);
+    overlay.draw_flight_plan(&plan).unwrap();
+    overlay.update(Some((45.0, 90.0))).unwrap();
+
+    // Two legs of the path, a pole at the position trigger, then the vectors
+    let lines = server.lines();
+    assert_eq!(lines.len(), 6);
+    let radius = BODIES[0].1;
+    let (x, y, z) = lines[0].start;
+    assert!(((x * x + y * y + z * z).sqrt() - (radius + 70.0)).abs() < 1e-3);
+    assert_eq!(lines[0].end, lines[1].start);
+    assert_eq!(lines[0].color, lines[1].color);
+    assert_ne!(lines[2].color, lines[1].color);
+
+    let velocity = &lines[3];
+    assert!(velocity.visible);
+    assert!((velocity.end.2 - 20.0).abs() < 1e-9);
+    let commanded = &lines[5];
+    assert!((commanded.end.0 - commanded.end.2).abs() < 1e-9);
+    assert!(commanded.end.1.abs() < 1e-9);
+
+    // Nothing steering hides the commanded vector, which stays in place for the next REORIENT
+    thread::sleep(Duration::from_millis(150));
+    overlay.update(None).unwrap();
+    assert!(!server.lines()[5].visible);
+
+    overlay.clear().unwrap();
+    assert!(server.lines().is_empty());
+}
+
+#[test]
+fn shows_avionics_on_the_hud() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let mut hud = Hud::new(connect(&server));
+
+    let ignite = gen_other_step(1, Step_ActionType::IGNITE, gen_time_trigger(0)).unwrap();
+    let turn = gen_reorient_step(2, 0.0, 80.0, 90.0, gen_alt_trigger(1_000.0));
+    hud.state("READY").unwrap();
+    hud.countdown(3).unwrap();
+    hud.step_executed(&ignite).unwrap();
+    hud.error("Lost link to vehicle.").unwrap();
+
+    let messages: Vec<String> = server
+        .messages()
+        .into_iter()
+        .map(|message| message.content)
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Avionics READY",
+            "Launching in 3",
+            "Step 1 IGNITE at UNIX time 0 s",
+            "Avionics ERROR: Lost link to vehicle.",
+        ]
+    );
+    assert_ne!(server.messages()[3].color, server.messages()[0].color);
+
+    // The panel is built on first use and then only updated
+    hud.update_panel(None, Some(&ignite)).unwrap();
+    hud.update_panel(Some(&ignite), Some(&turn)).unwrap();
+    let texts: Vec<String> = server
+        .texts()
+        .into_iter()
+        .map(|text| text.content)
+        .collect();
+    assert_eq!(texts, vec!["Step 1 IGNITE", "Next: altitude 1000 m"]);
+
+    hud.update_panel(Some(&turn), None).unwrap();
+    assert_eq!(server.texts()[1].content, "Flight plan complete");
+
+    hud.clear().unwrap();
+    assert!(server.texts().is_empty());
+}
+
+#[test]
+fn reads_game_clock() {
+    let server = StandinServer::start(ScriptedVessel {
+        met: 120.0,
+        ..Default::default()
+    })
+    .unwrap();
+    server.set_ut(5_000.25);
+    let transport = connect(&server);
+
+    let clock = transport.get_clock().unwrap();
+    assert_eq!(clock.ut, 5_000.25);
+    assert_eq!(clock.met, 120.0);
+
+    let launch = clock::from_seconds(0.0, Time_Base::MISSION);
+    let ut = transport
+        .convert_time(&launch, Time_Base::UNIVERSAL)
+        .unwrap();
+    assert_eq!(ut.get_seconds(), 4_880);
+    assert_eq!(ut.get_nanos(), 250_000_000);
+
+    // Half a second of game time later in any base is half a second of wall clock later
+    let wall = transport
+        .convert_time(
+            &clock::from_seconds(5_000.75, Time_Base::UNIVERSAL),
+            Time_Base::WALLCLOCK,
+        )
+        .unwrap();
+    let now = libkerbx::time().unwrap();
+    assert!((clock::seconds(&wall) - clock::seconds(&now) - 0.5).abs() < 0.1);
+}
+
+#[test]
+fn controls_time_warp() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+    assert_eq!(transport.get_warp().unwrap(), WarpState::default());
+
+    transport.set_warp(WarpMode::Rails, 5).unwrap();
+    let warp = transport.get_warp().unwrap();
+    assert_eq!(warp.mode, WarpMode::Rails);
+    assert_eq!(warp.factor, 5);
+    assert_eq!(warp.rate, 1_000.0);
+
+    // Close to the surface KSP allows less
+    server.set_max_rails_warp(3);
+    assert_eq!(transport.max_rails_warp_factor().unwrap(), 3);
+    transport.set_warp(WarpMode::Rails, 7).unwrap();
+    assert_eq!(transport.warp_factor().unwrap(), 3);
+
+    transport.set_warp(WarpMode::Physics, 3).unwrap();
+    assert_eq!(transport.get_warp().unwrap().mode, WarpMode::Physics);
+    assert_eq!(transport.warp_rate().unwrap(), 4.0);
+    transport.set_warp(WarpMode::None, 0).unwrap();
+    assert_eq!(transport.get_warp().unwrap().mode, WarpMode::None);
+
+    transport.warp_to(3_600.0, 100_000.0, 2.0).unwrap();
+    assert_eq!(transport.ut().unwrap(), 3_600.0);
+    assert!(server.commands().contains(&Command::WarpTo(3_600.0)));
+}
+
+#[test]
+fn plans_maneuver_nodes() {
+    let server = StandinServer::start(ScriptedVessel::default()).unwrap();
+    let transport = connect(&server);
+    assert_eq!(transport.get_maneuver_node().unwrap(), None);
+
+    let later = transport.add_node(600.0, 100.0, 0.0, 0.0).unwrap();
+    transport.add_node(300.0, 0.0, 20.0, 0.0).unwrap();
+    assert_eq!(transport.nodes().unwrap().len(), 2);
+
+    // Soonest first, with the default vessel heading east so normal points north
+    let next = transport.get_maneuver_node().unwrap().unwrap();
+    assert_eq!(next.ut, 300.0);
+    assert_eq!(next.normal, 20.0);
+    assert!((next.remaining - Vector3::new(0.0, 20.0, 0.0)).norm() < 1e-9);
+
+    transport.set_node_delta_v(&later, 50.0, 0.0, 10.0).unwrap();
+    transport.set_node_ut(&later, 200.0).unwrap();
+    let nodes = server.nodes();
+    assert_eq!(
+        (nodes[0].ut, nodes[0].prograde, nodes[0].radial),
+        (200.0, 50.0, 10.0)
+    );
+    let burn = transport.remaining_burn_vector(&later).unwrap();
+    assert!((burn - Vector3::new(10.0, 0.0, 50.0)).norm() < 1e-9);
+
+    server.update(|vessel| vessel.available_thrust = 50_000.0);
+    let mass = transport.get_propulsion().unwrap().mass;
+    let burn_time = transport.burn_time(&later).unwrap();
+    assert!((burn_time - 2_600.0_f64.sqrt() * mass / 50_000.0).abs() < 1e-9);
+
+    transport.remove_maneuver_node().unwrap();
+    assert_eq!(server.nodes().len(), 1);
+    assert_eq!(server.nodes()[0].ut, 300.0);
+    transport.remove_nodes().unwrap();
+    assert!(server.nodes().is_empty());
+    assert!(matches!(
+        transport.remove_maneuver_node(),
+        Err(KerbxError::Contract(_))
+    ));
+}